@@ -0,0 +1,40 @@
+use rustlock_core::RustLock;
+use rustlock_derive::LicenseFeatures;
+
+// `rustlock-schema.json` is written by `rustlock-admin export schema`, a field that
+// doesn't match a feature of the application fails to compile.
+#[derive(LicenseFeatures)]
+#[rustlock(schema = "rustlock-schema.json")]
+struct Features {
+    export_pdf: bool,
+    #[rustlock(feature = "Cloud Sync")]
+    sync: bool,
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // These values would normally be generated by the `rustlock-admin` tool
+    // and copied into your application.
+    let license_public_key = "YOUR_LICENSE_PUBLIC_KEY".to_string();
+    let machine_key = "YOUR_MACHINE_KEY".to_string();
+    let info_private_key = "YOUR_INFO_PRIVATE_KEY".to_string();
+
+    let version = env!("CARGO_PKG_VERSION").to_string();
+
+    let lock = RustLock::new(license_public_key, vec![], version, machine_key, info_private_key)?;
+
+    // Expect the license string as the first CLI argument
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() < 2 {
+        eprintln!("Usage: features <LICENSE_STRING>");
+        std::process::exit(1);
+    }
+
+    let license = lock.validate_license(&args[1])?;
+    let features: Features = license.features();
+
+    println!("License validated for: {}", license.name);
+    println!("Export PDF enabled: {}", features.export_pdf());
+    println!("Cloud Sync enabled: {}", features.sync());
+
+    Ok(())
+}
@@ -8,7 +8,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     let info_private_key = "YOUR_INFO_PRIVATE_KEY".to_string();
 
     // Example list of blocked customer IDs
-    let blocked_customers = vec![9999u16];
+    let blocked_customers = vec![9999u64];
 
     // Typically you would use the version of your application here
     let version = env!("CARGO_PKG_VERSION").to_string();
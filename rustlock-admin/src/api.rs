@@ -0,0 +1,479 @@
+use std::net::SocketAddr;
+use std::path::PathBuf;
+
+use axum::extract::{Path, Query, Request, State};
+use axum::http::{StatusCode, header};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use clap::Args;
+use log::info;
+use rustlock_core::config::AppConfig;
+use rustlock_core::features::FeatureSlot;
+use rustlock_core::license::{License, VersionPolicy};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use sha2::{Digest, Sha256};
+use sqlx::{Any, Pool};
+
+use crate::applications::{self, NewApplication, select_application};
+use crate::customers::{self, NewCustomer, select_customer};
+use crate::db::{Application, Customer, fetch_application, fetch_customer};
+use crate::error::AdminError;
+use crate::input;
+use crate::keystore;
+use crate::license::{self, IssueRequest, IssuedLicenseRecord, LicenseRecord};
+
+/// Bearer token clients have to send, used when `--token-file` is left out
+pub const TOKEN_ENV: &str = "RUSTLOCK_API_TOKEN";
+/// Shorter tokens are refused at startup
+const MIN_TOKEN_LEN: usize = 16;
+
+/// Inputs for `serve`
+#[derive(Args, Debug)]
+pub struct ServeArgs {
+    /// Address to listen on
+    #[arg(long, default_value = "127.0.0.1:8080")]
+    pub listen: SocketAddr,
+    /// File holding the bearer token, `RUSTLOCK_API_TOKEN` is used when left out
+    #[arg(long)]
+    pub token_file: Option<PathBuf>,
+}
+
+#[derive(Clone)]
+struct ApiState {
+    pool: Pool<Any>,
+    /// SHA-256 of the token, so comparing doesn't depend on where the first wrong byte is
+    token: [u8; 32],
+}
+
+/// An [`AdminError`] as a JSON response, with the status matching its exit code
+struct ApiError(AdminError);
+
+impl<E: Into<AdminError>> From<E> for ApiError {
+    fn from(e: E) -> Self {
+        Self(e.into())
+    }
+}
+
+/// HTTP status of an error, also used by the dashboard
+pub fn status_of(e: &AdminError) -> StatusCode {
+    match e {
+        AdminError::MissingInput(_) => StatusCode::BAD_REQUEST,
+        AdminError::Invalid(_) | AdminError::License(_) => StatusCode::UNPROCESSABLE_ENTITY,
+        AdminError::NotFound(_) => StatusCode::NOT_FOUND,
+        AdminError::Database(_) | AdminError::Io(_) | AdminError::Prompt(_) | AdminError::Other(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (status_of(&self.0), Json(json!({ "error": self.0.to_string() }))).into_response()
+    }
+}
+
+type ApiResult<T> = Result<T, ApiError>;
+
+fn hash_token(token: &str) -> [u8; 32] {
+    Sha256::digest(token.as_bytes()).into()
+}
+
+async fn authorize(State(state): State<ApiState>, request: Request, next: Next) -> Response {
+    let token = request.headers().get(header::AUTHORIZATION).and_then(|value| value.to_str().ok()).and_then(|value| value.strip_prefix("Bearer "));
+
+    if token.is_some_and(|token| hash_token(token.trim()) == state.token) {
+        next.run(request).await
+    } else {
+        (StatusCode::UNAUTHORIZED, [(header::WWW_AUTHENTICATE, "Bearer")], Json(json!({ "error": "missing or invalid bearer token" }))).into_response()
+    }
+}
+
+#[derive(Deserialize)]
+struct ListQuery {
+    /// Include archived records
+    #[serde(default)]
+    all: bool,
+}
+
+async fn list_customers(State(state): State<ApiState>, Query(query): Query<ListQuery>) -> ApiResult<Json<Vec<Customer>>> {
+    let customers = crate::db::fetch_customers(&state.pool).await?;
+    Ok(Json(customers.into_iter().filter(|c| query.all || c.archived_at.is_none()).collect()))
+}
+
+async fn get_customer(State(state): State<ApiState>, Path(id): Path<i64>) -> ApiResult<Json<Customer>> {
+    Ok(Json(fetch_customer(&state.pool, id).await?))
+}
+
+async fn create_customer(State(state): State<ApiState>, Json(customer): Json<NewCustomer>) -> ApiResult<(StatusCode, Json<Customer>)> {
+    let id = customers::create_customer(&state.pool, &customer).await?;
+    Ok((StatusCode::CREATED, Json(fetch_customer(&state.pool, id).await?)))
+}
+
+/// Fields of `PUT /customers/{id}`, missing ones keep their current value
+#[derive(Deserialize)]
+struct CustomerUpdate {
+    name: Option<String>,
+    contact_email: Option<String>,
+    mobile: Option<String>,
+}
+
+async fn update_customer(State(state): State<ApiState>, Path(id): Path<i64>, Json(update): Json<CustomerUpdate>) -> ApiResult<Json<Customer>> {
+    let mut customer = fetch_customer(&state.pool, id).await?;
+    customer.name = update.name.unwrap_or(customer.name);
+    customer.contact_email = update.contact_email.unwrap_or(customer.contact_email);
+    customer.mobile = update.mobile.unwrap_or(customer.mobile);
+
+    customers::update_customer(&state.pool, &customer).await?;
+    Ok(Json(fetch_customer(&state.pool, id).await?))
+}
+
+/// An application without its keys, `/applications/{id}/config` has the ones shipped to clients
+#[derive(Serialize)]
+struct ApplicationView {
+    id: i64,
+    name: String,
+    features: Vec<FeatureSlot>,
+    version_policy: &'static str,
+    blocked_customer_ids: Vec<u64>,
+    hardware_changes_allowed: i64,
+    archived_at: Option<String>,
+}
+
+impl From<&Application> for ApplicationView {
+    fn from(app: &Application) -> Self {
+        Self {
+            id: app.id,
+            name: app.name.clone(),
+            features: app.feature_schema().features,
+            version_policy: app.version_policy.as_str(),
+            blocked_customer_ids: app.blocked_customer_ids.clone(),
+            hardware_changes_allowed: app.hardware_changes_allowed,
+            archived_at: app.archived_at.clone(),
+        }
+    }
+}
+
+async fn list_applications(State(state): State<ApiState>, Query(query): Query<ListQuery>) -> ApiResult<Json<Vec<ApplicationView>>> {
+    let apps = crate::db::fetch_applications(&state.pool).await?;
+    Ok(Json(apps.iter().filter(|a| query.all || a.archived_at.is_none()).map(ApplicationView::from).collect()))
+}
+
+async fn get_application(State(state): State<ApiState>, Path(id): Path<i64>) -> ApiResult<Json<ApplicationView>> {
+    Ok(Json(ApplicationView::from(&fetch_application(&state.pool, id).await?)))
+}
+
+async fn get_application_config(State(state): State<ApiState>, Path(id): Path<i64>) -> ApiResult<Json<AppConfig>> {
+    Ok(Json(applications::app_config(&fetch_application(&state.pool, id).await?)))
+}
+
+/// Body of `POST /applications`
+#[derive(Deserialize)]
+struct NewApplicationBody {
+    name: String,
+    /// Up to five feature names, in slot order
+    #[serde(default)]
+    features: Vec<String>,
+    /// `major_minor`, `semver_range` or `release_date`
+    version_policy: Option<String>,
+}
+
+async fn create_application(State(state): State<ApiState>, Json(body): Json<NewApplicationBody>) -> ApiResult<(StatusCode, Json<ApplicationView>)> {
+    if body.features.len() > 5 {
+        return Err(AdminError::Invalid(format!("an application has at most 5 features, got {}", body.features.len())).into());
+    }
+
+    let mut features: [Option<String>; 5] = Default::default();
+    for (feature, name) in features.iter_mut().zip(body.features) {
+        *feature = input::non_empty(name);
+    }
+    let version_policy = match body.version_policy {
+        Some(policy) => policy.parse().map_err(AdminError::Invalid)?,
+        None => VersionPolicy::default(),
+    };
+
+    let app = applications::create_application(&state.pool, NewApplication { name: body.name, features, version_policy }).await?;
+    Ok((StatusCode::CREATED, Json(ApplicationView::from(&app))))
+}
+
+#[derive(Deserialize)]
+struct LicenseQuery {
+    application: i64,
+    customer: Option<i64>,
+}
+
+async fn list_licenses(State(state): State<ApiState>, Query(query): Query<LicenseQuery>) -> ApiResult<Json<Vec<LicenseRecord>>> {
+    let app = fetch_application(&state.pool, query.application).await?;
+    Ok(Json(license::fetch_licenses(&state.pool, &app, query.customer).await?))
+}
+
+/// Body of `POST /licenses`, the fields of `issue`
+#[derive(Deserialize)]
+struct IssueBody {
+    application: i64,
+    customer: i64,
+    hwid: String,
+    version: String,
+    max_version: Option<String>,
+    #[serde(default = "default_support_years")]
+    support_years: i32,
+    /// Feature names
+    #[serde(default)]
+    features: Vec<String>,
+}
+
+const fn default_support_years() -> i32 {
+    1
+}
+
+async fn issue_license(State(state): State<ApiState>, Json(body): Json<IssueBody>) -> ApiResult<(StatusCode, Json<IssuedLicenseRecord>)> {
+    let apps = crate::db::fetch_applications(&state.pool).await?;
+    let app = select_application(&apps, Some(body.application), "")?;
+    let customers = crate::db::fetch_customers(&state.pool).await?;
+    let customer = select_customer(&customers, Some(body.customer), "")?;
+
+    let request = IssueRequest {
+        features: license::features_by_name(app, &body.features)?,
+        hwid: body.hwid,
+        support_years: body.support_years,
+        version: body.version,
+        max_version: body.max_version,
+        end: None,
+    };
+    let issued = license::issue_license(&state.pool, app, customer, &request).await?;
+    info!("Issued license {} for app {} to customer {} over the API", issued.id, app.id, customer.id);
+
+    Ok((StatusCode::CREATED, Json(IssuedLicenseRecord::new(app, customer, &request.hwid, &issued))))
+}
+
+/// Body of `POST /licenses/validate`
+#[derive(Deserialize)]
+struct ValidateBody {
+    application: i64,
+    license: String,
+}
+
+/// Whether the license string decodes with the application's keys, and the status of its row
+#[derive(Serialize)]
+struct ValidateResponse {
+    valid: bool,
+    /// `active`, `revoked` or `superseded`, `None` when no license row has its ID
+    status: Option<String>,
+    license: Option<License>,
+    error: Option<String>,
+}
+
+async fn validate_license(State(state): State<ApiState>, Json(body): Json<ValidateBody>) -> ApiResult<Json<ValidateResponse>> {
+    let app = fetch_application(&state.pool, body.application).await?;
+
+    Ok(Json(match rustlock_core::read_license(&app.lic_public_key, body.license.trim()) {
+        Ok(lic) => {
+            let status = sqlx::query_scalar("SELECT status FROM licenses WHERE license_uid = $1 AND application_id = $2").bind(&lic.id).bind(app.id).fetch_optional(&state.pool).await?;
+            ValidateResponse {
+                valid: true,
+                status,
+                license: Some(lic),
+                error: None,
+            }
+        }
+        Err(e) => ValidateResponse {
+            valid: false,
+            status: None,
+            license: None,
+            error: Some(e.to_string()),
+        },
+    }))
+}
+
+async fn openapi() -> Json<Value> {
+    Json(openapi_document())
+}
+
+/// `OpenAPI` 3.1 description of the routes below
+fn openapi_document() -> Value {
+    let body = |schema: &str| json!({ "required": true, "content": { "application/json": { "schema": { "$ref": format!("#/components/schemas/{schema}") } } } });
+    let reply = |description: &str, schema: Value| json!({ "description": description, "content": { "application/json": { "schema": schema } } });
+    let one = |schema: &str| json!({ "$ref": format!("#/components/schemas/{schema}") });
+    let many = |schema: &str| json!({ "type": "array", "items": { "$ref": format!("#/components/schemas/{schema}") } });
+    let id = json!({ "name": "id", "in": "path", "required": true, "schema": { "type": "integer" } });
+    let all = json!({ "name": "all", "in": "query", "schema": { "type": "boolean" }, "description": "Include archived records" });
+    let errors = json!({
+        "400": { "$ref": "#/components/responses/Error" },
+        "401": { "$ref": "#/components/responses/Error" },
+        "404": { "$ref": "#/components/responses/Error" },
+        "422": { "$ref": "#/components/responses/Error" },
+    });
+    let with_errors = |ok: Value| {
+        let mut responses = errors.clone();
+        if let (Value::Object(responses), Value::Object(ok)) = (&mut responses, ok) {
+            responses.extend(ok);
+        }
+        responses
+    };
+    let string = json!({ "type": "string" });
+    let nullable = json!({ "type": ["string", "null"] });
+    let integer = json!({ "type": "integer" });
+
+    json!({
+        "openapi": "3.1.0",
+        "info": { "title": "rustlock-admin", "version": env!("CARGO_PKG_VERSION") },
+        "servers": [{ "url": "/api/v1" }],
+        "security": [{ "bearer": [] }],
+        "paths": {
+            "/customers": {
+                "get": { "summary": "List customers", "parameters": [all], "responses": with_errors(json!({ "200": reply("Customers", many("Customer")) })) },
+                "post": { "summary": "Create a customer", "requestBody": body("NewCustomer"), "responses": with_errors(json!({ "201": reply("The new customer", one("Customer")) })) },
+            },
+            "/customers/{id}": {
+                "get": { "summary": "Get a customer", "parameters": [id], "responses": with_errors(json!({ "200": reply("The customer", one("Customer")) })) },
+                "put": { "summary": "Update a customer, missing fields are kept", "parameters": [id], "requestBody": body("CustomerUpdate"), "responses": with_errors(json!({ "200": reply("The updated customer", one("Customer")) })) },
+            },
+            "/applications": {
+                "get": { "summary": "List applications", "parameters": [all], "responses": with_errors(json!({ "200": reply("Applications", many("Application")) })) },
+                "post": { "summary": "Create an application and generate its keys", "requestBody": body("NewApplication"), "responses": with_errors(json!({ "201": reply("The new application", one("Application")) })) },
+            },
+            "/applications/{id}": {
+                "get": { "summary": "Get an application", "parameters": [id], "responses": with_errors(json!({ "200": reply("The application", one("Application")) })) },
+            },
+            "/applications/{id}/config": {
+                "get": { "summary": "Settings and keys shipped with the application, as read by AppConfig", "parameters": [id], "responses": with_errors(json!({ "200": reply("The config", json!({ "type": "object" })) })) },
+            },
+            "/licenses": {
+                "get": {
+                    "summary": "List the licenses of an application",
+                    "parameters": [
+                        { "name": "application", "in": "query", "required": true, "schema": integer },
+                        { "name": "customer", "in": "query", "schema": integer },
+                    ],
+                    "responses": with_errors(json!({ "200": reply("Licenses", json!({ "type": "array", "items": { "type": "object" } })) })),
+                },
+                "post": { "summary": "Issue a license", "requestBody": body("IssueLicense"), "responses": with_errors(json!({ "201": reply("The issued license", one("IssuedLicense")) })) },
+            },
+            "/licenses/validate": {
+                "post": { "summary": "Check a license string against an application's keys", "requestBody": body("ValidateLicense"), "responses": with_errors(json!({ "200": reply("The result", one("ValidateResult")) })) },
+            },
+        },
+        "components": {
+            "securitySchemes": { "bearer": { "type": "http", "scheme": "bearer" } },
+            "responses": {
+                "Error": { "description": "The request failed", "content": { "application/json": { "schema": { "type": "object", "properties": { "error": string } } } } },
+            },
+            "schemas": {
+                "Customer": {
+                    "type": "object",
+                    "properties": { "id": integer, "name": string, "contact_email": string, "mobile": string, "archived_at": nullable },
+                },
+                "NewCustomer": {
+                    "type": "object",
+                    "required": ["name", "contact_email", "mobile"],
+                    "properties": { "name": string, "contact_email": string, "mobile": string },
+                },
+                "CustomerUpdate": {
+                    "type": "object",
+                    "properties": { "name": string, "contact_email": string, "mobile": string },
+                },
+                "Application": {
+                    "type": "object",
+                    "properties": {
+                        "id": integer,
+                        "name": string,
+                        "features": { "type": "array", "items": { "type": "object", "properties": { "slot": integer, "name": string } } },
+                        "version_policy": { "enum": ["major_minor", "semver_range", "release_date"] },
+                        "blocked_customer_ids": { "type": "array", "items": integer },
+                        "hardware_changes_allowed": integer,
+                        "archived_at": nullable,
+                    },
+                },
+                "NewApplication": {
+                    "type": "object",
+                    "required": ["name"],
+                    "properties": {
+                        "name": string,
+                        "features": { "type": "array", "items": string, "maxItems": 5 },
+                        "version_policy": { "enum": ["major_minor", "semver_range", "release_date"] },
+                    },
+                },
+                "IssueLicense": {
+                    "type": "object",
+                    "required": ["application", "customer", "hwid", "version"],
+                    "properties": {
+                        "application": integer,
+                        "customer": integer,
+                        "hwid": string,
+                        "version": string,
+                        "max_version": string,
+                        "support_years": { "type": "integer", "default": 1 },
+                        "features": { "type": "array", "items": string },
+                    },
+                },
+                "IssuedLicense": {
+                    "type": "object",
+                    "properties": {
+                        "id": integer,
+                        "license_uid": string,
+                        "application_id": integer,
+                        "customer_id": integer,
+                        "version": string,
+                        "start": string,
+                        "end": string,
+                        "features": string,
+                        "issued_license": string,
+                    },
+                },
+                "ValidateLicense": {
+                    "type": "object",
+                    "required": ["application", "license"],
+                    "properties": { "application": integer, "license": string },
+                },
+                "ValidateResult": {
+                    "type": "object",
+                    "properties": { "valid": { "type": "boolean" }, "status": nullable, "license": { "type": ["object", "null"] }, "error": nullable },
+                },
+            },
+        },
+    })
+}
+
+fn router(state: ApiState) -> Router {
+    let api = Router::new()
+        .route("/customers", get(list_customers).post(create_customer))
+        .route("/customers/{id}", get(get_customer).put(update_customer))
+        .route("/applications", get(list_applications).post(create_application))
+        .route("/applications/{id}", get(get_application))
+        .route("/applications/{id}/config", get(get_application_config))
+        .route("/licenses", get(list_licenses).post(issue_license))
+        .route("/licenses/validate", post(validate_license))
+        .route_layer(middleware::from_fn_with_state(state.clone(), authorize));
+
+    Router::new().route("/openapi.json", get(openapi)).nest("/api/v1", api).with_state(state)
+}
+
+/// Serve the JSON API until Ctrl-C
+pub async fn serve(pool: Pool<Any>, args: ServeArgs) -> Result<(), AdminError> {
+    // Trimmed either way, a token file or variable set from one usually ends in a newline
+    let token = match args.token_file {
+        Some(path) => std::fs::read_to_string(path)?,
+        None => std::env::var(TOKEN_ENV).map_err(|_| AdminError::MissingInput(format!("--token-file or {TOKEN_ENV}")))?,
+    };
+    let token = token.trim();
+    if token.chars().count() < MIN_TOKEN_LEN {
+        return Err(AdminError::Invalid(format!("the API token must be at least {MIN_TOKEN_LEN} characters")));
+    }
+
+    // Ask for the master passphrase now, requests must never prompt
+    keystore::unlock(&pool).await?;
+    input::disable_prompts();
+
+    let listener = tokio::net::TcpListener::bind(args.listen).await?;
+    info!("Serving the API on http://{}/api/v1, described at /openapi.json", listener.local_addr()?);
+
+    let state = ApiState { pool, token: hash_token(token) };
+    axum::serve(listener, router(state))
+        .with_graceful_shutdown(async {
+            let _ = tokio::signal::ctrl_c().await;
+        })
+        .await?;
+
+    info!("API stopped");
+    Ok(())
+}
@@ -1,90 +1,152 @@
-use std::error::Error;
+use std::path::{Path, PathBuf};
 
+use clap::{Args, ValueEnum};
 use dialoguer::{Input, Select, theme::ColorfulTheme};
 use ecies::utils::generate_keypair;
 use log::info;
-use rustlock_core::RustLock;
+use rustlock_core::config::{AppConfig, ENV_APPLICATION, ENV_BLOCKED_CUSTOMERS, ENV_FEATURE_PREFIX, ENV_INFO_KEY, ENV_LICENSE_KEY, ENV_MACHINE_KEY, ENV_VERSION_POLICY};
+use rustlock_core::{RustLock, license::VersionPolicy};
+use serde::{Deserialize, Serialize};
 use serde_json::to_string as json_to_string;
-use sqlx::{Pool, Row, Sqlite};
+use sqlx::{Any, Pool, Row};
+
+use crate::audit;
+use crate::db::Application;
+use crate::error::AdminError;
+use crate::input;
+use crate::keystore;
+use crate::output::{self, OutputFormat};
+
+/// Inputs for `add application`, missing values are prompted for when a terminal is attached
+#[derive(Args, Serialize, Deserialize, Default, Debug)]
+pub struct AddApplicationArgs {
+    /// Application name
+    #[arg(long)]
+    pub name: Option<String>,
+    /// Feature name, repeat up to five times to fill the feature slots in order
+    #[arg(long = "feature")]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub features: Vec<String>,
+    /// Version policy: `major_minor`, `semver_range` or `release_date`
+    #[arg(long)]
+    pub version_policy: Option<String>,
+    /// Read the fields above from a JSON or TOML file
+    #[arg(long)]
+    #[serde(skip)]
+    pub input: Option<PathBuf>,
+    /// Skip the confirmation, required when no terminal is attached
+    #[arg(long)]
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub yes: bool,
+}
 
-/// Prompt the user to select one application, then print all its key fields and feature names.
-pub async fn show_application_config(pool: &Pool<Sqlite>) -> Result<(), Box<dyn std::error::Error>> {
-    // 1) Fetch all columns of each application, including the five feature columns
-    let rows = sqlx::query(
-        r"
-        SELECT
-          id,
-          name,
-          lic_public_key,
-          lic_private_key,
-          blocked_customer_ids,
-          machine_id_key,
-          info_public_key,
-          info_private_key,
-          feature1,
-          feature2,
-          feature3,
-          feature4,
-          feature5
-        FROM applications
-        ORDER BY id
-        ",
-    )
-    .fetch_all(pool)
-    .await?;
+/// Inputs for `update application`, fields left out keep their current value when no terminal is attached
+#[derive(Args, Serialize, Deserialize, Default, Debug)]
+pub struct UpdateApplicationArgs {
+    /// ID of the application to update
+    #[arg(long)]
+    pub id: Option<i64>,
+    /// Application name
+    #[arg(long)]
+    pub name: Option<String>,
+    #[arg(long)]
+    pub lic_public_key: Option<String>,
+    #[arg(long)]
+    pub lic_private_key: Option<String>,
+    #[arg(long)]
+    pub machine_id_key: Option<String>,
+    #[arg(long)]
+    pub info_public_key: Option<String>,
+    #[arg(long)]
+    pub info_private_key: Option<String>,
+    /// Feature names, an empty value removes the feature
+    #[arg(long)]
+    pub feature1: Option<String>,
+    #[arg(long)]
+    pub feature2: Option<String>,
+    #[arg(long)]
+    pub feature3: Option<String>,
+    #[arg(long)]
+    pub feature4: Option<String>,
+    #[arg(long)]
+    pub feature5: Option<String>,
+    /// Version policy: `major_minor`, `semver_range` or `release_date`
+    #[arg(long)]
+    pub version_policy: Option<String>,
+    /// Comma separated IDs of customers whose licenses the application refuses, e.g. `9999,12`
+    #[arg(long)]
+    pub blocked_customers: Option<String>,
+    /// Lock components a fingerprint may change on `reissue` without approval, 0 to 4
+    #[arg(long)]
+    pub hardware_changes_allowed: Option<i64>,
+    /// Read the fields above from a JSON or TOML file
+    #[arg(long)]
+    #[serde(skip)]
+    pub input: Option<PathBuf>,
+}
 
-    if rows.is_empty() {
-        println!("⚠️  No applications found.");
-        return Ok(());
-    }
+/// An application that hasn't been stored yet, keys are generated on insert
+#[derive(Debug, Default)]
+pub struct NewApplication {
+    pub name: String,
+    pub features: [Option<String>; 5],
+    pub version_policy: VersionPolicy,
+}
+
+/// Pick an application by `--application`, or prompt for one when a terminal is attached. Archived applications are only found by ID
+pub fn select_application<'a>(apps: &'a [Application], id: Option<i64>, prompt: &str) -> Result<&'a Application, AdminError> {
+    let items: Vec<(i64, String)> = apps.iter().filter(|app| app.archived_at.is_none() || Some(app.id) == id).map(|app| (app.id, app.name.clone())).collect();
+    let selection = input::select_id(id, &items, "application", prompt)?;
+    apps.iter().find(|app| app.id == items[selection].0).ok_or_else(|| AdminError::NotFound(format!("application with ID {}", items[selection].0)))
+}
+
+/// Prompt the user to select one application, then print all its key fields and feature names.
+pub async fn show_application_config(pool: &Pool<Any>, application: Option<i64>, format: OutputFormat) -> Result<(), AdminError> {
+    // 1) Fetch all applications, including the five feature columns
+    let apps = crate::db::fetch_applications(pool).await?;
 
-    // 2) Build a Vec<(id, name)> so we can prompt with “ID – Name”
-    let mut choices = Vec::new();
-    for row in &rows {
-        let id: i64 = row.try_get("id")?;
-        let name: String = row.try_get("name")?;
-        choices.push(format!("ID {id} – {name}"));
+    // 2) Let the user pick one
+    let app = select_application(&apps, application, "Select an application to dump config for")?;
+
+    if !format.is_table() {
+        let config = ApplicationConfig {
+            id: app.id,
+            name: app.name.clone(),
+            license_public_key: app.lic_public_key.clone(),
+            machine_key: app.machine_id_key.clone(),
+            info_private_key: app.info_private_key.clone(),
+            blocked_customers: app.blocked_customer_ids.clone(),
+            version_policy: app.version_policy.to_string(),
+            feature1: app.feature1.clone(),
+            feature2: app.feature2.clone(),
+            feature3: app.feature3.clone(),
+            feature4: app.feature4.clone(),
+            feature5: app.feature5.clone(),
+        };
+        return output::print_records(format, &[config], |_| {});
     }
 
-    // 3) Let the user pick one
-    let selection = Select::with_theme(&ColorfulTheme::default()).with_prompt("Select an application to dump config for").default(0).items(&choices).interact()?;
-
-    // 4) Extract the chosen row
-    let chosen_row = &rows[selection];
-    let id: i64 = chosen_row.try_get("id")?;
-    let name: String = chosen_row.try_get("name")?;
-    let lic_pub: String = chosen_row.try_get("lic_public_key")?;
-    let machine_key: String = chosen_row.try_get("machine_id_key")?;
-    let info_priv: String = chosen_row.try_get("info_private_key")?;
-
-    // 5) Extract each feature (they may be NULL)
-    let feature1: Option<String> = chosen_row.try_get("feature1")?;
-    let feature2: Option<String> = chosen_row.try_get("feature2")?;
-    let feature3: Option<String> = chosen_row.try_get("feature3")?;
-    let feature4: Option<String> = chosen_row.try_get("feature4")?;
-    let feature5: Option<String> = chosen_row.try_get("feature5")?;
-
-    // 6) Print everything out, including features
+    // 3) Print everything out, including features
     println!();
-    println!("—— Application Config for {name}  - ID {id} —————————————");
+    println!("—— Application Config for {}  - ID {} —————————————", app.name, app.id);
     println!();
 
     println!("// Copy and paste the following into your application:");
-    println!("let license_public_key = \"{lic_pub}\".to_string();");
-    println!("let machine_key = \"{machine_key}\".to_string();");
-    println!("let info_private_key = \"{info_priv}\".to_string(); // Info encrypted on client side");
+    println!("let license_public_key = \"{}\".to_string();", app.lic_public_key);
+    println!("let machine_key = \"{}\".to_string();", app.machine_id_key);
+    println!("let info_private_key = \"{}\".to_string(); // Info encrypted on client side", app.info_private_key);
     println!();
-    println!("let blocked_customers = vec![9999]; // Example Block Customer 9999");
+    println!("let blocked_customers = vec![{}];", app.blocked_customer_ids.iter().map(ToString::to_string).collect::<Vec<_>>().join(", "));
     println!("let version = env!(\"CARGO_PKG_VERSION\").to_string();");
     println!();
 
     // Print each feature; if None, show as empty string
     println!("// Feature names (empty if none):");
-    println!("let feature1 = \"{}\".to_string();", feature1.unwrap_or_default());
-    println!("let feature2 = \"{}\".to_string();", feature2.unwrap_or_default());
-    println!("let feature3 = \"{}\".to_string();", feature3.unwrap_or_default());
-    println!("let feature4 = \"{}\".to_string();", feature4.unwrap_or_default());
-    println!("let feature5 = \"{}\".to_string();", feature5.unwrap_or_default());
+    println!("let feature1 = \"{}\".to_string();", app.feature1.clone().unwrap_or_default());
+    println!("let feature2 = \"{}\".to_string();", app.feature2.clone().unwrap_or_default());
+    println!("let feature3 = \"{}\".to_string();", app.feature3.clone().unwrap_or_default());
+    println!("let feature4 = \"{}\".to_string();", app.feature4.clone().unwrap_or_default());
+    println!("let feature5 = \"{}\".to_string();", app.feature5.clone().unwrap_or_default());
     println!();
 
     println!("let lock = RustLock::new(");
@@ -93,167 +155,436 @@ pub async fn show_application_config(pool: &Pool<Sqlite>) -> Result<(), Box<dyn
     println!("    version,");
     println!("    machine_key,");
     println!("    info_private_key");
-    println!(");");
+    if app.version_policy == VersionPolicy::ReleaseDate {
+        println!(")?");
+        println!(".with_release_date(2025, 1); // Year and month this build was released");
+    } else {
+        println!(");");
+    }
     println!();
 
     println!("——————————————————————————————————————————————");
+    println!("Use `export config` to write these settings to a file instead.");
+
+    Ok(())
+}
+
+/// File formats of `export config`
+#[derive(ValueEnum, Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub enum ConfigFormat {
+    /// TOML, loaded with `AppConfig::load` or `AppConfig::from_toml`
+    #[default]
+    Toml,
+    /// JSON, loaded with `AppConfig::load` or `AppConfig::from_json`
+    Json,
+    /// `RUSTLOCK_*` variables, loaded with `AppConfig::from_dotenv` or `AppConfig::from_env`
+    Env,
+    /// Rust module with constants and a `config()` function, compiled into the application
+    Rust,
+}
+
+impl ConfigFormat {
+    pub const fn default_output(self) -> &'static str {
+        match self {
+            Self::Toml => "rustlock-config.toml",
+            Self::Json => "rustlock-config.json",
+            Self::Env => "rustlock.env",
+            Self::Rust => "rustlock_config.rs",
+        }
+    }
+}
+
+/// Settings of an application for `rustlock-core`, only the keys that ship with the application
+pub fn app_config(app: &Application) -> AppConfig {
+    AppConfig {
+        application: app.name.clone(),
+        license_key: app.lic_public_key.clone(),
+        machine_key: app.machine_id_key.clone(),
+        info_key: app.info_private_key.clone(),
+        blocked_customers: app.blocked_customer_ids.clone(),
+        version_policy: app.version_policy,
+        features: app.feature_schema().features,
+    }
+}
+
+fn config_to_env(config: &AppConfig) -> String {
+    let quote = |value: &str| format!("\"{}\"", value.replace('"', "\\\""));
+
+    let mut lines = vec![
+        format!("# RustLock settings of {}, generated by rustlock-admin export config", config.application),
+        format!("{ENV_APPLICATION}={}", quote(&config.application)),
+        format!("{ENV_LICENSE_KEY}={}", quote(&config.license_key)),
+        format!("{ENV_MACHINE_KEY}={}", quote(&config.machine_key)),
+        format!("{ENV_INFO_KEY}={}", quote(&config.info_key)),
+        format!("{ENV_BLOCKED_CUSTOMERS}={}", quote(&config.blocked_customers.iter().map(ToString::to_string).collect::<Vec<_>>().join(","))),
+        format!("{ENV_VERSION_POLICY}={}", quote(config.version_policy.as_str())),
+    ];
+    lines.extend(config.features.iter().map(|f| format!("{ENV_FEATURE_PREFIX}{}={}", f.slot, quote(&f.name))));
+    lines.join("\n") + "\n"
+}
+
+fn config_to_rust(config: &AppConfig) -> String {
+    let blocked = config.blocked_customers.iter().map(ToString::to_string).collect::<Vec<_>>().join(", ");
+    let features = config.features.iter().map(|f| format!("({}, {:?})", f.slot, f.name)).collect::<Vec<_>>().join(", ");
+
+    format!(
+        r#"//! RustLock settings of {application:?}, generated by `rustlock-admin export config`
+
+use rustlock_core::config::AppConfig;
+use rustlock_core::features::FeatureSlot;
+use rustlock_core::license::VersionPolicy;
+
+pub const APPLICATION: &str = {application:?};
+pub const LICENSE_KEY: &str = {license_key:?};
+pub const MACHINE_KEY: &str = {machine_key:?};
+pub const INFO_KEY: &str = {info_key:?};
+pub const BLOCKED_CUSTOMERS: &[u64] = &[{blocked}];
+pub const VERSION_POLICY: VersionPolicy = VersionPolicy::{policy:?};
+/// Feature slots and names
+pub const FEATURES: &[(u8, &str)] = &[{features}];
+
+/// The settings above as an [`AppConfig`]
+#[must_use]
+pub fn config() -> AppConfig {{
+    AppConfig {{
+        application: APPLICATION.to_string(),
+        license_key: LICENSE_KEY.to_string(),
+        machine_key: MACHINE_KEY.to_string(),
+        info_key: INFO_KEY.to_string(),
+        blocked_customers: BLOCKED_CUSTOMERS.to_vec(),
+        version_policy: VERSION_POLICY,
+        features: FEATURES.iter().map(|&(slot, name)| FeatureSlot {{ slot, name: name.to_string() }}).collect(),
+    }}
+}}
+"#,
+        application = config.application,
+        license_key = config.license_key,
+        machine_key = config.machine_key,
+        info_key = config.info_key,
+        policy = config.version_policy,
+    )
+}
+
+/// Write an application's settings to a file `rustlock-core` loads with `AppConfig`
+pub async fn export_application_config(pool: &Pool<Any>, application: Option<i64>, format: ConfigFormat, output: Option<PathBuf>) -> Result<(), AdminError> {
+    let apps = crate::db::fetch_applications(pool).await?;
+    let app = select_application(&apps, application, "Select an application to export the config for")?;
+
+    let config = app_config(app);
+    let output = output.unwrap_or_else(|| PathBuf::from(format.default_output()));
+
+    let contents = match format {
+        ConfigFormat::Toml => toml::to_string_pretty(&config).map_err(|e| AdminError::Other(e.to_string()))?,
+        ConfigFormat::Json => serde_json::to_string_pretty(&config)?,
+        ConfigFormat::Env => config_to_env(&config),
+        ConfigFormat::Rust => config_to_rust(&config),
+    };
+    std::fs::write(&output, contents)?;
+
+    // make sure the application can load what was written
+    let loaded = match format {
+        ConfigFormat::Toml | ConfigFormat::Json => Some(AppConfig::load(&output)),
+        ConfigFormat::Env => Some(AppConfig::from_dotenv(&std::fs::read_to_string(&output)?)),
+        ConfigFormat::Rust => None,
+    };
+    if let Some(loaded) = loaded
+        && loaded? != config
+    {
+        return Err(AdminError::Other(format!("{} doesn't load back as the exported config", output.display())));
+    }
 
+    info!("Exported the config of application {}.", app.id);
+    println!("✅ Config written to: {}", output.display());
     Ok(())
 }
 
+/// Prompt the user to select one application, then write its feature schema as JSON for `rustlock-derive`.
+pub async fn export_application_schema(pool: &Pool<Any>, application: Option<i64>, output: &Path) -> Result<(), AdminError> {
+    let apps = crate::db::fetch_applications(pool).await?;
+    let app = select_application(&apps, application, "Select an application to export the schema for")?;
+
+    let schema = app.feature_schema();
+    std::fs::write(output, serde_json::to_string_pretty(&schema)?)?;
+
+    info!("Exported {} feature(s) for application {}.", schema.features.len(), app.id);
+    println!("✅ Schema written to: {}", output.display());
+    Ok(())
+}
+
+/// Per application counts printed by `show applications`
+#[derive(Debug, Serialize, Default)]
+pub struct ApplicationSummary {
+    pub id: i64,
+    pub name: String,
+    pub customer_count: i64,
+    pub license_count: i64,
+    pub archived_at: Option<String>,
+}
+
+/// Settings an application needs to embed, printed by `show applications --config`
+#[derive(Debug, Serialize, Default)]
+pub struct ApplicationConfig {
+    pub id: i64,
+    pub name: String,
+    pub license_public_key: String,
+    pub machine_key: String,
+    pub info_private_key: String,
+    pub blocked_customers: Vec<u64>,
+    pub version_policy: String,
+    pub feature1: Option<String>,
+    pub feature2: Option<String>,
+    pub feature3: Option<String>,
+    pub feature4: Option<String>,
+    pub feature5: Option<String>,
+}
+
 /// Show all applications, displaying each app’s name,
 /// how many distinct customers have licenses for it, and how many licenses exist.
-pub async fn show_applications(pool: &Pool<Sqlite>) -> sqlx::Result<()> {
+/// Archived applications are left out unless `all` is set.
+pub async fn show_applications(pool: &Pool<Any>, format: OutputFormat, all: bool) -> Result<(), AdminError> {
     // Aggregate query: count total licenses and distinct customers per application
     let rows = sqlx::query(
         r"
-        SELECT 
+        SELECT
             a.id,
             a.name,
+            a.archived_at,
             COUNT(l.id) AS license_count,
             COUNT(DISTINCT l.customer_id) AS customer_count
         FROM applications a
         LEFT JOIN licenses l
           ON a.id = l.application_id
+        WHERE $1 OR a.archived_at IS NULL
         GROUP BY a.id, a.name
         ORDER BY a.id
         ",
     )
+    .bind(all)
     .fetch_all(pool)
     .await?;
 
-    println!("{}", "-".repeat(sixty_four()));
-    println!("{:<6} | {:<20} | {:<15} | {:<15}", "ID", "Name", "# Customers", "# Licenses");
-    println!("{}", "-".repeat(sixty_four()));
-
+    let mut summaries = Vec::new();
     for row in rows {
-        let id: i64 = row.try_get("id")?;
-        let name: String = row.try_get("name")?;
-        let license_count: i64 = row.try_get("license_count")?;
-        let customer_count: i64 = row.try_get("customer_count")?;
-
-        println!("{id:<6} | {name:<20} | {customer_count:<15} | {license_count:<15}");
+        summaries.push(ApplicationSummary {
+            id: row.try_get("id")?,
+            name: row.try_get("name")?,
+            customer_count: row.try_get("customer_count")?,
+            license_count: row.try_get("license_count")?,
+            archived_at: row.try_get("archived_at")?,
+        });
     }
 
-    println!("{}", "-".repeat(sixty_four()));
-    Ok(())
+    output::print_records(format, &summaries, |summaries| {
+        println!("{}", "-".repeat(sixty_four()));
+        println!("{:<6} | {:<20} | {:<15} | {:<15}", "ID", "Name", "# Customers", "# Licenses");
+        println!("{}", "-".repeat(sixty_four()));
+
+        for app in summaries {
+            let name = if app.archived_at.is_some() { format!("{} (archived)", app.name) } else { app.name.clone() };
+            println!("{:<6} | {:<20} | {:<15} | {:<15}", app.id, name, app.customer_count, app.license_count);
+        }
+
+        println!("{}", "-".repeat(sixty_four()));
+    })
 }
 
 const fn sixty_four() -> usize {
     64
 }
 
+/// Keys must be the hex encoded secp256k1 keys generated by `add application`
+pub fn validate_application(app: &Application) -> Result<(), AdminError> {
+    if app.name.trim().is_empty() {
+        return Err(AdminError::Invalid("application name must not be empty".to_string()));
+    }
+
+    for (field, key) in [
+        ("lic_public_key", &app.lic_public_key),
+        ("lic_private_key", &app.lic_private_key),
+        ("machine_id_key", &app.machine_id_key),
+        ("info_public_key", &app.info_public_key),
+        ("info_private_key", &app.info_private_key),
+    ] {
+        if hex::decode(key).is_err() {
+            return Err(AdminError::Invalid(format!("{field} is not a hex encoded key")));
+        }
+    }
+
+    if !(0..=4).contains(&app.hardware_changes_allowed) {
+        return Err(AdminError::Invalid("hardware changes allowed must be between 0 and 4".to_string()));
+    }
+
+    Ok(())
+}
+
+/// Check both key pairs of an application belong together by encrypting a probe with one half and decrypting it with the other
+pub fn check_key_pairs(app: &Application) -> Result<(), AdminError> {
+    const PROBE: &[u8] = b"rustlock";
+
+    validate_application(app)?;
+
+    // NOTE the key names are swapped, the "public" keys are the secret halves
+    for (pair, encrypt_key, decrypt_key) in [("license", &app.lic_private_key, &app.lic_public_key), ("info", &app.info_private_key, &app.info_public_key)] {
+        let round_trip = ecies::encrypt(&hex::decode(encrypt_key).unwrap_or_default(), PROBE).and_then(|cipher| ecies::decrypt(&hex::decode(decrypt_key).unwrap_or_default(), &cipher));
+
+        if !matches!(round_trip, Ok(plain) if plain == PROBE) {
+            return Err(AdminError::Invalid(format!("{pair} keys of application '{}' (ID {}) don't decode as a key pair", app.name, app.id)));
+        }
+    }
+
+    Ok(())
+}
+
+/// Update every field of an existing application
+pub async fn update_application(pool: &Pool<Any>, app: &Application) -> Result<(), AdminError> {
+    validate_application(app)?;
+
+    let Some(before) = crate::db::fetch_applications(pool).await?.into_iter().find(|a| a.id == app.id) else {
+        return Err(AdminError::NotFound(format!("application with ID {}", app.id)));
+    };
+
+    let lic_private_key = keystore::seal(pool, app.id, "lic_private_key", &app.lic_private_key).await?;
+    let info_public_key = keystore::seal(pool, app.id, "info_public_key", &app.info_public_key).await?;
+
+    let mut tx = crate::db::repository(pool).begin().await?;
+    let result = sqlx::query(
+        r"
+        UPDATE applications
+        SET
+          name                = $1,
+          lic_public_key      = $2,
+          lic_private_key     = $3,
+          machine_id_key      = $4,
+          info_public_key     = $5,
+          info_private_key    = $6,
+          feature1            = $7,
+          feature2            = $8,
+          feature3            = $9,
+          feature4            = $10,
+          feature5            = $11,
+          version_policy      = $12,
+          blocked_customer_ids = $13,
+          hardware_changes_allowed = $14
+        WHERE id = $15
+        ",
+    )
+    .bind(&app.name)
+    .bind(&app.lic_public_key)
+    .bind(lic_private_key)
+    .bind(&app.machine_id_key)
+    .bind(info_public_key)
+    .bind(&app.info_private_key)
+    .bind(&app.feature1)
+    .bind(&app.feature2)
+    .bind(&app.feature3)
+    .bind(&app.feature4)
+    .bind(&app.feature5)
+    .bind(app.version_policy.as_str())
+    .bind(json_to_string(&app.blocked_customer_ids)?)
+    .bind(app.hardware_changes_allowed)
+    .bind(app.id)
+    .execute(&mut *tx)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AdminError::NotFound(format!("application with ID {}", app.id)));
+    }
+
+    audit::record(&mut tx, "update", "application", Some(app.id), Some(audit::snapshot(&before)?), Some(audit::snapshot(app)?)).await?;
+    tx.commit().await?;
+    Ok(())
+}
+
 /// Allows editing an existing application’s fields (name, keys, features, etc.)
-pub async fn update_application_wizard(pool: &Pool<Sqlite>) -> Result<(), Box<dyn Error>> {
+pub async fn update_application_wizard(pool: &Pool<Any>, args: UpdateApplicationArgs) -> Result<(), AdminError> {
+    let file = args.input.clone();
+    let args = input::merge_input_file(args, file.as_deref())?;
     let theme = ColorfulTheme::default();
 
     // 1) Fetch all applications (the App struct must now include feature1..feature5)
     let apps = crate::db::fetch_applications(pool).await?;
-    if apps.is_empty() {
-        println!("⚠️  No applications found. Please add one first.");
-        return Ok(());
-    }
 
     // 2) Let the user pick which application to update
-    let choices: Vec<String> = apps.iter().map(|app| format!("ID {} – {}", app.id, app.name)).collect();
-    let selection = Select::with_theme(&theme).with_prompt("Select an application to update").default(0).items(&choices).interact()?;
-    let app = &apps[selection];
+    let app = select_application(&apps, args.id, "Select an application to update")?;
 
     // 3) Prompt for each field, prefilled with current values.
     //    Pressing Enter leaves the field unchanged.
 
     // a) Name
-    let new_name: String = Input::with_theme(&theme).with_prompt("Application name").with_initial_text(app.name.clone()).interact_text()?;
+    let new_name = input::text_or_current(args.name, &app.name, || Input::with_theme(&theme).with_prompt("Application name").with_initial_text(app.name.clone()).interact_text())?;
 
     // b) lic_public_key
-    let new_lic_pub: String = Input::with_theme(&theme).with_prompt("License public key").with_initial_text(app.lic_public_key.clone()).interact_text()?;
+    let new_lic_pub = input::text_or_current(args.lic_public_key, &app.lic_public_key, || Input::with_theme(&theme).with_prompt("License public key").with_initial_text(app.lic_public_key.clone()).interact_text())?;
 
     // c) lic_private_key
-    let new_lic_priv: String = Input::with_theme(&theme).with_prompt("License private key").with_initial_text(app.lic_private_key.clone()).interact_text()?;
+    let new_lic_priv = input::text_or_current(args.lic_private_key, &app.lic_private_key, || {
+        Input::with_theme(&theme).with_prompt("License private key").with_initial_text(app.lic_private_key.clone()).interact_text()
+    })?;
 
     // d) machine_id_key
-    let new_machine_key: String = Input::with_theme(&theme).with_prompt("Machine ID key").with_initial_text(app.machine_id_key.clone()).interact_text()?;
+    let new_machine_key = input::text_or_current(args.machine_id_key, &app.machine_id_key, || Input::with_theme(&theme).with_prompt("Machine ID key").with_initial_text(app.machine_id_key.clone()).interact_text())?;
 
     // e) info_public_key
-    let new_info_pub: String = Input::with_theme(&theme).with_prompt("Info public key").with_initial_text(app.info_public_key.clone()).interact_text()?;
+    let new_info_pub = input::text_or_current(args.info_public_key, &app.info_public_key, || Input::with_theme(&theme).with_prompt("Info public key").with_initial_text(app.info_public_key.clone()).interact_text())?;
 
     // f) info_private_key
-    let new_info_priv: String = Input::with_theme(&theme).with_prompt("Info private key").with_initial_text(app.info_private_key.clone()).interact_text()?;
-
-    // g) feature1
-    let new_feature1: String = Input::with_theme(&theme)
-        .with_prompt("Feature1 name (leave blank to keep none)")
-        .with_initial_text(app.feature1.clone().unwrap_or_default())
-        .allow_empty(true)
-        .interact_text()?;
-
-    // h) feature2
-    let new_feature2: String = Input::with_theme(&theme)
-        .with_prompt("Feature2 name (leave blank to keep none)")
-        .with_initial_text(app.feature2.clone().unwrap_or_default())
-        .allow_empty(true)
-        .interact_text()?;
-
-    // i) feature3
-    let new_feature3: String = Input::with_theme(&theme)
-        .with_prompt("Feature3 name (leave blank to keep none)")
-        .with_initial_text(app.feature3.clone().unwrap_or_default())
-        .allow_empty(true)
-        .interact_text()?;
-
-    // j) feature4
-    let new_feature4: String = Input::with_theme(&theme)
-        .with_prompt("Feature4 name (leave blank to keep none)")
-        .with_initial_text(app.feature4.clone().unwrap_or_default())
-        .allow_empty(true)
-        .interact_text()?;
-
-    // k) feature5
-    let new_feature5: String = Input::with_theme(&theme)
-        .with_prompt("Feature5 name (leave blank to keep none)")
-        .with_initial_text(app.feature5.clone().unwrap_or_default())
-        .allow_empty(true)
-        .interact_text()?;
-
-    // Convert empty strings into None so the column becomes NULL
-    let f1_opt: Option<String> = if new_feature1.trim().is_empty() { None } else { Some(new_feature1.clone()) };
-    let f2_opt: Option<String> = if new_feature2.trim().is_empty() { None } else { Some(new_feature2.clone()) };
-    let f3_opt: Option<String> = if new_feature3.trim().is_empty() { None } else { Some(new_feature3.clone()) };
-    let f4_opt: Option<String> = if new_feature4.trim().is_empty() { None } else { Some(new_feature4.clone()) };
-    let f5_opt: Option<String> = if new_feature5.trim().is_empty() { None } else { Some(new_feature5.clone()) };
+    let new_info_priv = input::text_or_current(args.info_private_key, &app.info_private_key, || {
+        Input::with_theme(&theme).with_prompt("Info private key").with_initial_text(app.info_private_key.clone()).interact_text()
+    })?;
+
+    // g..k) feature1..feature5
+    let mut new_features = Vec::new();
+    for (slot, (value, current)) in [(args.feature1, &app.feature1), (args.feature2, &app.feature2), (args.feature3, &app.feature3), (args.feature4, &app.feature4), (args.feature5, &app.feature5)]
+        .into_iter()
+        .enumerate()
+    {
+        let current = current.clone().unwrap_or_default();
+        let feature = input::text_or_current(value, &current, || input::optional_text(&theme, &format!("Feature{} name (leave blank to keep none)", slot + 1), &current))?;
+        new_features.push(input::non_empty(feature));
+    }
+    let [f1_opt, f2_opt, f3_opt, f4_opt, f5_opt]: [Option<String>; 5] = new_features.try_into().unwrap_or_default();
+
+    // l) version policy
+    let new_policy = match args.version_policy {
+        Some(policy) => policy.parse().map_err(AdminError::Invalid)?,
+        None if input::interactive() => select_version_policy(&theme, app.version_policy)?,
+        None => app.version_policy,
+    };
+
+    // m) blocked customers
+    let current_blocked = app.blocked_customer_ids.iter().map(ToString::to_string).collect::<Vec<_>>().join(",");
+    let new_blocked = input::text_or_current(args.blocked_customers, &current_blocked, || input::optional_text(&theme, "Blocked customer IDs (comma separated)", &current_blocked))?;
+    let new_blocked = parse_customer_ids(&new_blocked)?;
+
+    // n) hardware changes allowed on reissue
+    let new_changes_allowed = match args.hardware_changes_allowed {
+        Some(allowed) => allowed,
+        None if input::interactive() => Input::with_theme(&theme).with_prompt("Hardware components that may change on reissue without approval (0-4)").default(app.hardware_changes_allowed).interact_text()?,
+        None => app.hardware_changes_allowed,
+    };
 
     // 4) Run the UPDATE statement (now including feature1..feature5)
-    sqlx::query(
-        r"
-        UPDATE applications
-        SET
-          name                = ?1,
-          lic_public_key      = ?2,
-          lic_private_key     = ?3,
-          machine_id_key      = ?4,
-          info_public_key     = ?5,
-          info_private_key    = ?6,
-          feature1            = ?7,
-          feature2            = ?8,
-          feature3            = ?9,
-          feature4            = ?10,
-          feature5            = ?11
-        WHERE id = ?12
-        ",
+    update_application(
+        pool,
+        &Application {
+            id: app.id,
+            name: new_name,
+            lic_public_key: new_lic_pub,
+            lic_private_key: new_lic_priv,
+            blocked_customer_ids: new_blocked,
+            machine_id_key: new_machine_key,
+            info_public_key: new_info_pub,
+            info_private_key: new_info_priv,
+            feature1: f1_opt,
+            feature2: f2_opt,
+            feature3: f3_opt,
+            feature4: f4_opt,
+            feature5: f5_opt,
+            version_policy: new_policy,
+            hardware_changes_allowed: new_changes_allowed,
+            archived_at: app.archived_at.clone(),
+        },
     )
-    .bind(&new_name)
-    .bind(&new_lic_pub)
-    .bind(&new_lic_priv)
-    .bind(&new_machine_key)
-    .bind(&new_info_pub)
-    .bind(&new_info_priv)
-    .bind(f1_opt)
-    .bind(f2_opt)
-    .bind(f3_opt)
-    .bind(f4_opt)
-    .bind(f5_opt)
-    .bind(app.id)
-    .execute(pool)
     .await?;
 
     info!("Application ID {} updated.", app.id);
@@ -261,6 +592,28 @@ pub async fn update_application_wizard(pool: &Pool<Sqlite>) -> Result<(), Box<dy
     Ok(())
 }
 
+/// Parse a comma separated list of customer IDs, blanks are skipped
+fn parse_customer_ids(list: &str) -> Result<Vec<u64>, AdminError> {
+    list.split(',')
+        .map(str::trim)
+        .filter(|id| !id.is_empty())
+        .map(|id| id.parse().map_err(|_| AdminError::Invalid(format!("blocked customer ID '{id}' is not a number"))))
+        .collect()
+}
+
+/// Ask which versions licenses of this application should cover
+fn select_version_policy(theme: &ColorfulTheme, current: VersionPolicy) -> Result<VersionPolicy, AdminError> {
+    let choices = [
+        "major_minor  – any patch of the licensed major.minor version",
+        "semver_range – any version between a minimum and maximum version",
+        "release_date – any version released before support ends",
+    ];
+    let default = VersionPolicy::ALL.iter().position(|p| *p == current).unwrap_or_default();
+
+    let selection = Select::with_theme(theme).with_prompt("Version policy").default(default).items(&choices).interact()?;
+    Ok(VersionPolicy::ALL[selection])
+}
+
 fn generate_new_secrets() -> (String, String) {
     let (sk, pk) = generate_keypair();
     let (sk, pk) = (&sk.serialize(), &pk.serialize());
@@ -271,65 +624,43 @@ fn generate_new_secrets() -> (String, String) {
     (sk_hex_string, pk_hex_string)
 }
 
-/// Interactive wizard to add a new application (including five optional features)
-pub async fn add_application_wizard(pool: &Pool<Sqlite>) -> Result<(), Box<dyn Error>> {
-    let theme = ColorfulTheme::default();
-
-    let name: String = Input::with_theme(&theme).with_prompt("Application name").interact_text()?;
-
+/// Generate the keys for a new application and insert it, returning the stored record
+pub async fn create_application(pool: &Pool<Any>, new_app: NewApplication) -> Result<Application, AdminError> {
     let (lic_public_key, lic_private_key) = generate_new_secrets();
     let (info_public_key, info_private_key) = generate_new_secrets();
     let (_, machine_id_key) = generate_new_secrets();
 
-    // blocked_customer_ids: start example with [9999]
-    let blocked_customer_ids: Vec<u16> = vec![9999];
-    let blocked_ids_json = json_to_string(&blocked_customer_ids).unwrap();
-
-    let lock = RustLock::new(lic_public_key.clone(), blocked_customer_ids.clone(), "0.0.1".to_string(), machine_id_key.clone(), info_private_key.clone())?;
-
-    let fingerprint = lock.get_system_fingerprint()?;
-
-    // Prompt for feature1..feature5 (each may be left blank)
-    let feature1: String = Input::with_theme(&theme).with_prompt("Feature1 name (leave blank if none)").allow_empty(true).interact_text()?;
-    let feature2: String = Input::with_theme(&theme).with_prompt("Feature2 name (leave blank if none)").allow_empty(true).interact_text()?;
-    let feature3: String = Input::with_theme(&theme).with_prompt("Feature3 name (leave blank if none)").allow_empty(true).interact_text()?;
-    let feature4: String = Input::with_theme(&theme).with_prompt("Feature4 name (leave blank if none)").allow_empty(true).interact_text()?;
-    let feature5: String = Input::with_theme(&theme).with_prompt("Feature5 name (leave blank if none)").allow_empty(true).interact_text()?;
-
-    // Convert empty strings into None so the column becomes NULL
-    let f1_opt: Option<String> = if feature1.trim().is_empty() { None } else { Some(feature1.clone()) };
-    let f2_opt: Option<String> = if feature2.trim().is_empty() { None } else { Some(feature2.clone()) };
-    let f3_opt: Option<String> = if feature3.trim().is_empty() { None } else { Some(feature3.clone()) };
-    let f4_opt: Option<String> = if feature4.trim().is_empty() { None } else { Some(feature4.clone()) };
-    let f5_opt: Option<String> = if feature5.trim().is_empty() { None } else { Some(feature5.clone()) };
-
-    // Show all stub values & ask for confirmation
-    info!("Generated the following stub fields for the new application:");
-    println!("• lic_public_key: {lic_public_key}");
-    println!("• info_public_key: {info_public_key}");
-    println!("• machine_id_key: {machine_id_key}");
-    println!("• blocked_customer_ids: {blocked_customer_ids:?}");
-    println!("• fingerprint test: {fingerprint}");
-    println!();
-
-    // Also display the entered feature names (or indicate "none")
-    println!("• feature1: {}", f1_opt.clone().unwrap_or_else(|| "<none>".to_string()));
-    println!("• feature2: {}", f2_opt.clone().unwrap_or_else(|| "<none>".to_string()));
-    println!("• feature3: {}", f3_opt.clone().unwrap_or_else(|| "<none>".to_string()));
-    println!("• feature4: {}", f4_opt.clone().unwrap_or_else(|| "<none>".to_string()));
-    println!("• feature5: {}", f5_opt.clone().unwrap_or_else(|| "<none>".to_string()));
-    println!();
-
-    let choices = vec!["Save application", "Cancel"];
-    let selection = Select::with_theme(&theme).with_prompt("Would you like to save this application?").default(0).items(&choices).interact().unwrap();
-
-    if selection != 0 {
-        info!("Aborted—no application was created.");
-        return Ok(());
-    }
+    let [feature1, feature2, feature3, feature4, feature5] = new_app.features;
+
+    let mut app = Application {
+        id: 0,
+        name: new_app.name,
+        lic_public_key,
+        lic_private_key,
+        // blocked_customer_ids: start example with [9999]
+        blocked_customer_ids: vec![9999],
+        machine_id_key,
+        info_public_key,
+        info_private_key,
+        feature1,
+        feature2,
+        feature3,
+        feature4,
+        feature5,
+        version_policy: new_app.version_policy,
+        hardware_changes_allowed: 1,
+        archived_at: None,
+    };
+    validate_application(&app)?;
+
+    let blocked_ids_json = json_to_string(&app.blocked_customer_ids)?;
+
+    // The secrets are sealed with the application ID, so they are stored once the row has one
+    keystore::prepare(pool).await?;
+    let mut tx = crate::db::repository(pool).begin().await?;
 
     // Insert into DB, including feature1..feature5
-    sqlx::query(
+    app.id = sqlx::query_scalar(
         r"
         INSERT INTO applications (
             name,
@@ -343,26 +674,102 @@ pub async fn add_application_wizard(pool: &Pool<Sqlite>) -> Result<(), Box<dyn E
             feature2,
             feature3,
             feature4,
-            feature5
+            feature5,
+            version_policy
         )
-        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)
+        RETURNING id
         ",
     )
-    .bind(&name)
-    .bind(&lic_public_key)
-    .bind(&lic_private_key)
+    .bind(&app.name)
+    .bind(&app.lic_public_key)
+    .bind("")
     .bind(&blocked_ids_json)
-    .bind(&machine_id_key)
-    .bind(&info_public_key)
-    .bind(&info_private_key)
-    .bind(f1_opt)
-    .bind(f2_opt)
-    .bind(f3_opt)
-    .bind(f4_opt)
-    .bind(f5_opt)
-    .execute(pool)
+    .bind(&app.machine_id_key)
+    .bind("")
+    .bind(&app.info_private_key)
+    .bind(&app.feature1)
+    .bind(&app.feature2)
+    .bind(&app.feature3)
+    .bind(&app.feature4)
+    .bind(&app.feature5)
+    .bind(app.version_policy.as_str())
+    .fetch_one(&mut *tx)
     .await?;
 
+    sqlx::query("UPDATE applications SET lic_private_key = $1, info_public_key = $2 WHERE id = $3")
+        .bind(keystore::seal(pool, app.id, "lic_private_key", &app.lic_private_key).await?)
+        .bind(keystore::seal(pool, app.id, "info_public_key", &app.info_public_key).await?)
+        .bind(app.id)
+        .execute(&mut *tx)
+        .await?;
+
+    audit::record(&mut tx, "create", "application", Some(app.id), None, Some(audit::snapshot(&app)?)).await?;
+    tx.commit().await?;
+    Ok(app)
+}
+
+/// Interactive wizard to add a new application (including five optional features)
+pub async fn add_application_wizard(pool: &Pool<Any>, args: AddApplicationArgs) -> Result<(), AdminError> {
+    let file = args.input.clone();
+    let args = input::merge_input_file(args, file.as_deref())?;
+    let theme = ColorfulTheme::default();
+
+    let name = input::text(args.name, "name", || Input::with_theme(&theme).with_prompt("Application name").interact_text())?;
+
+    if args.features.len() > 5 {
+        return Err(AdminError::Invalid(format!("an application has at most 5 features, got {}", args.features.len())));
+    }
+
+    // Prompt for feature1..feature5 (each may be left blank) unless they were given up front
+    let mut features: [Option<String>; 5] = Default::default();
+    if args.features.is_empty() && input::interactive() {
+        for (slot, feature) in features.iter_mut().enumerate() {
+            *feature = input::non_empty(input::optional_text(&theme, &format!("Feature{} name (leave blank if none)", slot + 1), "")?);
+        }
+    } else {
+        for (feature, name) in features.iter_mut().zip(args.features) {
+            *feature = input::non_empty(name);
+        }
+    }
+
+    let version_policy = match args.version_policy {
+        Some(policy) => policy.parse().map_err(AdminError::Invalid)?,
+        None if input::interactive() => select_version_policy(&theme, VersionPolicy::default())?,
+        None => VersionPolicy::default(),
+    };
+
+    // Show all stub values & ask for confirmation
+    info!("The new application will be created with:");
+    println!("• name: {name}");
+    for (slot, feature) in features.iter().enumerate() {
+        println!("• feature{}: {}", slot + 1, feature.clone().unwrap_or_else(|| "<none>".to_string()));
+    }
+    println!("• version_policy: {version_policy}");
+    println!();
+
+    if !args.yes && !input::confirm("Would you like to save this application?", true)? {
+        info!("Aborted—no application was created.");
+        return Ok(());
+    }
+
+    let app = create_application(pool, NewApplication { name, features, version_policy }).await?;
+
+    // Check the new keys can fingerprint this machine, the application is saved either way
+    let fingerprint = RustLock::new(app.lic_public_key.clone(), app.blocked_customer_ids.clone(), "0.0.1".to_string(), app.machine_id_key.clone(), app.info_private_key.clone())
+        .map_err(|e| e.to_string())
+        .and_then(|lock| lock.get_system_fingerprint().map_err(|e| e.to_string()))
+        .unwrap_or_else(|e| format!("<failed: {e}>"));
+
+    info!("Generated the following fields for the new application:");
+    println!("• id: {}", app.id);
+    println!("• lic_public_key: {}", app.lic_public_key);
+    println!("• info_public_key: {}", app.info_public_key);
+    println!("• machine_id_key: {}", app.machine_id_key);
+    println!("• blocked_customer_ids: {:?}", app.blocked_customer_ids);
+    println!("• fingerprint test: {fingerprint}");
+    println!();
+
     info!("✅ Application created!");
     Ok(())
 }
@@ -0,0 +1,232 @@
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, Utc};
+use clap::Args;
+use serde::Serialize;
+use serde_json::Value;
+use sqlx::{Any, AnyConnection, Pool, Row};
+
+use crate::crypto;
+use crate::error::AdminError;
+use crate::keystore;
+use crate::output::{self, OutputFormat};
+
+/// Operator name recorded in the audit log, the OS user is used when it isn't set
+pub const OPERATOR_ENV: &str = "RUSTLOCK_OPERATOR";
+
+/// `prev_hash` of the first entry
+const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// Inputs for `show audit`
+#[derive(Args, Debug)]
+pub struct ShowAuditArgs {
+    /// Only entries for this entity: customer, application, license or keystore
+    #[arg(long)]
+    pub entity: Option<String>,
+    /// Only entries for the record with this ID
+    #[arg(long)]
+    pub id: Option<i64>,
+    /// Only entries with this action, e.g. create, update or issue
+    #[arg(long)]
+    pub action: Option<String>,
+    /// Only entries by this actor
+    #[arg(long)]
+    pub actor: Option<String>,
+    /// Only entries on or after this date (YYYY-MM-DD) or UTC time (YYYY-MM-DDTHH:MM:SS, or RFC 3339 with an offset)
+    #[arg(long)]
+    pub since: Option<String>,
+    /// Show at most this many of the newest entries
+    #[arg(long)]
+    pub limit: Option<u32>,
+    /// Output format
+    #[arg(long, value_enum, default_value_t)]
+    pub format: OutputFormat,
+}
+
+/// One row of the `audit_log` table
+#[derive(Debug, Serialize, Default)]
+pub struct AuditEntry {
+    pub id: i64,
+    pub timestamp: String,
+    pub actor: String,
+    pub action: String,
+    pub entity: String,
+    pub entity_id: Option<i64>,
+    /// JSON of the record before the action, secrets are replaced by a hash
+    pub before: Option<String>,
+    /// JSON of the record after the action, secrets are replaced by a hash
+    pub after: Option<String>,
+    pub prev_hash: String,
+    pub hash: String,
+}
+
+impl AuditEntry {
+    fn compute_hash(&self) -> String {
+        let entity_id = self.entity_id.map(|id| id.to_string()).unwrap_or_default();
+        let fields = [
+            self.prev_hash.as_str(),
+            &self.timestamp,
+            &self.actor,
+            &self.action,
+            &self.entity,
+            &entity_id,
+            self.before.as_deref().unwrap_or_default(),
+            self.after.as_deref().unwrap_or_default(),
+        ];
+        crypto::sha256_hex(fields.join("\u{1f}").as_bytes())
+    }
+
+    /// Names of the top level fields that differ between `before` and `after`
+    fn changed_fields(&self) -> Vec<String> {
+        let parse = |json: &Option<String>| json.as_deref().and_then(|j| serde_json::from_str::<Value>(j).ok());
+
+        match (parse(&self.before), parse(&self.after)) {
+            (Some(Value::Object(before)), Some(Value::Object(after))) => after.iter().filter(|(key, value)| before.get(*key) != Some(*value)).map(|(key, _)| key.clone()).collect(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// Operator name from the environment, falling back to the OS user
+pub fn actor() -> String {
+    [OPERATOR_ENV, "USER", "USERNAME"].iter().find_map(|var| std::env::var(var).ok().filter(|v| !v.trim().is_empty())).unwrap_or_else(|| "unknown".to_string())
+}
+
+/// JSON of a record for the audit log, secret key columns are replaced by a short hash so changes still show
+pub fn snapshot<T: Serialize>(record: &T) -> Result<Value, AdminError> {
+    let mut value = serde_json::to_value(record)?;
+
+    if let Value::Object(fields) = &mut value {
+        for column in keystore::SECRET_COLUMNS {
+            if let Some(Value::String(secret)) = fields.get_mut(column) {
+                *secret = format!("redacted:sha256:{}", &crypto::sha256_hex(secret.as_bytes())[..16]);
+            }
+        }
+    }
+    Ok(value)
+}
+
+/// Append an entry to the audit log, chained to the hash of the previous entry.
+/// Runs on the transaction of the action, so the change and its entry are committed together. That transaction
+/// comes from [`Repository::begin`](crate::db::Repository::begin), whose lock keeps the previous entry the last one until it commits
+pub async fn record(conn: &mut AnyConnection, action: &str, entity: &str, entity_id: Option<i64>, before: Option<Value>, after: Option<Value>) -> Result<(), AdminError> {
+    let prev_hash: Option<String> = sqlx::query_scalar("SELECT hash FROM audit_log ORDER BY id DESC LIMIT 1").fetch_optional(&mut *conn).await?;
+
+    let mut entry = AuditEntry {
+        id: 0,
+        timestamp: chrono::Utc::now().to_rfc3339(),
+        actor: actor(),
+        action: action.to_string(),
+        entity: entity.to_string(),
+        entity_id,
+        before: before.map(|v| v.to_string()),
+        after: after.map(|v| v.to_string()),
+        prev_hash: prev_hash.unwrap_or_else(|| GENESIS_HASH.to_string()),
+        hash: String::new(),
+    };
+    entry.hash = entry.compute_hash();
+
+    sqlx::query(
+        r"
+        INSERT INTO audit_log (timestamp, actor, action, entity, entity_id, before, after, prev_hash, hash)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+        ",
+    )
+    .bind(&entry.timestamp)
+    .bind(&entry.actor)
+    .bind(&entry.action)
+    .bind(&entry.entity)
+    .bind(entry.entity_id)
+    .bind(&entry.before)
+    .bind(&entry.after)
+    .bind(&entry.prev_hash)
+    .bind(&entry.hash)
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+async fn fetch_entries(pool: &Pool<Any>) -> sqlx::Result<Vec<AuditEntry>> {
+    let rows = sqlx::query("SELECT id, timestamp, actor, action, entity, entity_id, before, after, prev_hash, hash FROM audit_log ORDER BY id").fetch_all(pool).await?;
+
+    let mut list = Vec::new();
+    for row in rows {
+        list.push(AuditEntry {
+            id: row.try_get("id")?,
+            timestamp: row.try_get("timestamp")?,
+            actor: row.try_get("actor")?,
+            action: row.try_get("action")?,
+            entity: row.try_get("entity")?,
+            entity_id: row.try_get("entity_id")?,
+            before: row.try_get("before")?,
+            after: row.try_get("after")?,
+            prev_hash: row.try_get("prev_hash")?,
+            hash: row.try_get("hash")?,
+        });
+    }
+    Ok(list)
+}
+
+/// ID of the first entry whose hash or link to the previous entry doesn't match
+fn first_broken_link(entries: &[AuditEntry]) -> Option<i64> {
+    let mut prev_hash = GENESIS_HASH;
+
+    for entry in entries {
+        if entry.prev_hash != prev_hash || entry.hash != entry.compute_hash() {
+            return Some(entry.id);
+        }
+        prev_hash = &entry.hash;
+    }
+    None
+}
+
+/// `--since` as a date, taken as midnight UTC, or a time, taken as UTC unless it carries an offset
+fn parse_since(since: &str) -> Result<DateTime<Utc>, AdminError> {
+    if let Ok(time) = DateTime::parse_from_rfc3339(since) {
+        return Ok(time.with_timezone(&Utc));
+    }
+    for format in ["%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M", "%Y-%m-%d %H:%M"] {
+        if let Ok(time) = NaiveDateTime::parse_from_str(since, format) {
+            return Ok(time.and_utc());
+        }
+    }
+    NaiveDate::parse_from_str(since, "%Y-%m-%d")
+        .map(|date| date.and_time(NaiveTime::MIN).and_utc())
+        .map_err(|_| AdminError::Invalid(format!("--since {since} is not a date (YYYY-MM-DD) or time (YYYY-MM-DDTHH:MM:SS)")))
+}
+
+/// Show the audit log after checking its hash chain, a broken chain is reported after the entries
+pub async fn show_audit(pool: &Pool<Any>, args: ShowAuditArgs) -> Result<(), AdminError> {
+    let since = args.since.as_deref().map(parse_since).transpose()?;
+    let entries = fetch_entries(pool).await?;
+    let broken = first_broken_link(&entries);
+
+    let mut shown: Vec<AuditEntry> = entries
+        .into_iter()
+        .filter(|e| args.entity.as_ref().is_none_or(|entity| e.entity == *entity))
+        .filter(|e| args.id.is_none_or(|id| e.entity_id == Some(id)))
+        .filter(|e| args.action.as_ref().is_none_or(|action| e.action == *action))
+        .filter(|e| args.actor.as_ref().is_none_or(|actor| e.actor == *actor))
+        .filter(|e| since.is_none_or(|since| DateTime::parse_from_rfc3339(&e.timestamp).is_ok_and(|time| time >= since)))
+        .collect();
+
+    if let Some(limit) = args.limit {
+        shown.drain(..shown.len().saturating_sub(limit as usize));
+    }
+
+    output::print_records(args.format, &shown, |entries| {
+        println!("{}", "-".repeat(110));
+        println!("{:<6} | {:<20} | {:<12} | {:<18} | {:<16} | {:<20}", "ID", "Time", "Actor", "Action", "Entity", "Changed");
+        println!("{}", "-".repeat(110));
+
+        for e in entries {
+            let entity = e.entity_id.map_or_else(|| e.entity.clone(), |id| format!("{} {id}", e.entity));
+            let time = e.timestamp.get(..19).unwrap_or(&e.timestamp).replace('T', " ");
+            println!("{:<6} | {:<20} | {:<12} | {:<18} | {:<16} | {}", e.id, time, e.actor, e.action, entity, e.changed_fields().join(", "));
+        }
+    })?;
+
+    match broken {
+        Some(id) => Err(AdminError::Invalid(format!("audit log hash chain is broken at entry {id}, the log was modified"))),
+        None => Ok(()),
+    }
+}
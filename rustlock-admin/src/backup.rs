@@ -0,0 +1,476 @@
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{self, Cursor, Read, Write};
+use std::path::{Path, PathBuf};
+
+use clap::Args;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use sqlx::any::AnyPoolOptions;
+use sqlx::{Any, Pool, Row};
+use walkdir::WalkDir;
+use zip::ZipArchive;
+use zip::write::FileOptions;
+
+use crate::crypto::{self, SecretKey};
+use crate::delivery::TEMPLATE_DIR;
+use crate::error::AdminError;
+use crate::profile::Location;
+use crate::{applications, db, input};
+
+/// Name of the database file inside the data directory and the backup archives
+pub const DB_FILE: &str = "rustlock.db";
+
+/// Passphrase for backups and restores when no terminal is attached
+pub const PASSPHRASE_ENV: &str = "RUSTLOCK_BACKUP_PASSPHRASE";
+
+/// Encrypted backups start with these bytes, a format version and the key derivation salt
+const MAGIC: &[u8; 4] = b"RLBK";
+const FORMAT_VERSION: u8 = 1;
+const HEADER_LEN: usize = MAGIC.len() + 1 + crypto::SALT_LEN;
+const EXTENSION: &str = "rlbk";
+
+/// Hashes of every file in a backup, stored inside the encrypted ZIP
+const MANIFEST: &str = "manifest.json";
+
+/// Decrypted backup, held in memory
+type Archive = ZipArchive<Cursor<Vec<u8>>>;
+
+#[derive(Serialize, Deserialize)]
+struct Manifest {
+    format: u8,
+    created_at: String,
+    /// Path inside the archive to its hex encoded SHA-256
+    files: BTreeMap<String, String>,
+}
+
+/// Restores unpack into a directory with this prefix inside the data directory
+const STAGING_PREFIX: &str = ".restore-";
+
+/// Directory inside the data directory for backups taken before schema migrations, left out of backups themselves
+const MIGRATION_BACKUP_DIR: &str = "backups";
+
+/// Columns of schema version 1 that every backup has, later changes are applied by [`db::Repository::migrate`]
+const REQUIRED_COLUMNS: [(&str, &[&str]); 3] = [
+    ("customers", &["id", "name", "contact_email", "mobile"]),
+    (
+        "applications",
+        &[
+            "id",
+            "name",
+            "lic_public_key",
+            "lic_private_key",
+            "blocked_customer_ids",
+            "machine_id_key",
+            "info_public_key",
+            "info_private_key",
+            "feature1",
+            "feature2",
+            "feature3",
+            "feature4",
+            "feature5",
+        ],
+    ),
+    ("licenses", &["id", "hwid", "support_years", "customer_id", "application_id", "issued_license"]),
+];
+
+/// Inputs for `restore`
+#[derive(Args, Debug)]
+pub struct RestoreArgs {
+    /// Backup archive created by `backup`
+    pub archive: PathBuf,
+    /// Restore without asking for confirmation, required when no terminal is attached
+    #[arg(long)]
+    pub yes: bool,
+}
+
+/// Copy the `SQLite` database file (and anything else in its data directory) into an encrypted archive
+pub fn backup_database(location: &Location) -> Result<PathBuf, AdminError> {
+    location.sqlite_file("backup")?;
+    let passphrase = crypto::passphrase(PASSPHRASE_ENV, "Backup passphrase", true)?;
+
+    // Create a timestamped backup in the current working directory
+    let timestamp = chrono::Utc::now().format("%Y%m%d_%H%M%S");
+    let backup_path = std::env::current_dir()?.join(format!("rustlock-backup-{timestamp}.{EXTENSION}"));
+
+    write_archive(location, &backup_path, &passphrase)?;
+
+    println!("✅ Backup created at: {}", backup_path.display());
+    Ok(backup_path)
+}
+
+/// Encrypted backup of the database before its schema is migrated, kept in the data directory
+pub fn pre_migration_backup(location: &Location, from_version: i64) -> Result<PathBuf, AdminError> {
+    let passphrase = crypto::passphrase(PASSPHRASE_ENV, "Passphrase for the pre-migration backup", true)?;
+
+    let dir = location.data_dir.join(MIGRATION_BACKUP_DIR);
+    fs::create_dir_all(&dir)?;
+    let backup_path = dir.join(format!("rustlock-pre-migration-v{from_version}-{}.{EXTENSION}", chrono::Utc::now().format("%Y%m%d_%H%M%S")));
+
+    write_archive(location, &backup_path, &passphrase)?;
+    Ok(backup_path)
+}
+
+/// Files next to the database that belong to it, stored as these suffixes of [`DB_FILE`] in the archive
+const DB_SUFFIXES: [&str; 4] = ["", "-wal", "-shm", "-journal"];
+
+/// Name of `name` inside the archive. The database is always stored as [`DB_FILE`], `None` for databases of other profiles sharing the directory
+fn archive_name(name: &str, db_name: &str) -> Option<String> {
+    if let Some(suffix) = name.strip_prefix(db_name).filter(|suffix| DB_SUFFIXES.contains(suffix)) {
+        return Some(format!("{DB_FILE}{suffix}"));
+    }
+    if DB_SUFFIXES.iter().any(|suffix| name.ends_with(&format!(".db{suffix}"))) {
+        return None;
+    }
+    Some(name.to_string())
+}
+
+/// ZIP the data directory with a manifest of per-file hashes, then encrypt the whole ZIP with a key derived from `passphrase`.
+///
+/// A database outside the default data directory can share its directory with anything, so only the database, its
+/// journal files and the delivery templates are taken from there
+fn write_archive(location: &Location, backup_path: &Path, passphrase: &str) -> Result<(), AdminError> {
+    let data_dir = &location.data_dir;
+    let everything = location.is_default();
+    let db_name = location.sqlite_file("backup")?.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+    let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+    let mut manifest = Manifest {
+        format: FORMAT_VERSION,
+        created_at: chrono::Utc::now().to_rfc3339(),
+        files: BTreeMap::new(),
+    };
+
+    let options = FileOptions::default().compression_method(zip::CompressionMethod::Deflated).unix_permissions(0o600);
+
+    // Recursively walk data_dir and add every file, skipping the staging directory of a running restore and older backups
+    for entry in WalkDir::new(data_dir).into_iter().filter_entry(|e| {
+        let name = e.file_name().to_string_lossy();
+        if name.starts_with(STAGING_PREFIX) || (e.depth() == 1 && name == MIGRATION_BACKUP_DIR) {
+            return false;
+        }
+        everything || e.depth() != 1 || !e.file_type().is_dir() || name == TEMPLATE_DIR
+    }) {
+        let entry = entry.map_err(io::Error::other)?;
+        let path = entry.path();
+        if path.is_file() {
+            // Derive a relative path inside the ZIP
+            let mut rel_path = path.strip_prefix(data_dir).unwrap().to_string_lossy().replace('\\', "/");
+            if entry.depth() == 1 {
+                let Some(name) = archive_name(&rel_path, &db_name).filter(|name| everything || name.strip_prefix(DB_FILE).is_some_and(|suffix| DB_SUFFIXES.contains(&suffix))) else {
+                    continue;
+                };
+                rel_path = name;
+            }
+
+            let buffer = fs::read(path)?;
+            manifest.files.insert(rel_path.clone(), crypto::sha256_hex(&buffer));
+
+            // Start a new file entry in the ZIP and write the file’s bytes into it
+            zip.start_file(rel_path, options).map_err(io::Error::other)?;
+            zip.write_all(&buffer)?;
+        }
+    }
+
+    zip.start_file(MANIFEST, options).map_err(io::Error::other)?;
+    zip.write_all(&serde_json::to_vec_pretty(&manifest)?)?;
+
+    // Finish writing the ZIP (returns a zip::result::ZipError if something went wrong)
+    let plain = zip.finish().map_err(io::Error::other)?.into_inner();
+
+    // Header is authenticated with the contents so the salt and version can't be swapped either
+    let mut header = MAGIC.to_vec();
+    header.push(FORMAT_VERSION);
+    header.extend_from_slice(&crypto::random_salt());
+
+    let sealed = SecretKey::derive(passphrase, &header[HEADER_LEN - crypto::SALT_LEN..])?.seal(&plain, &header)?;
+
+    let mut file = File::create(backup_path)?;
+    file.write_all(&header)?;
+    file.write_all(&sealed)?;
+    Ok(())
+}
+
+/// Decrypt a backup and check every file against its manifest.
+///
+/// Plain ZIPs written before backups were encrypted are still accepted, with a warning. Returns the
+/// archive and the passphrase that opened it.
+fn read_archive(path: &Path) -> Result<(Archive, Option<String>), AdminError> {
+    let bytes = fs::read(path)?;
+
+    if !bytes.starts_with(MAGIC) {
+        warn!("{} is an unencrypted backup from an older version, its contents can't be authenticated", path.display());
+        let mut archive = ZipArchive::new(Cursor::new(bytes)).map_err(|e| AdminError::Invalid(format!("{} is not a backup archive: {e}", path.display())))?;
+        if archive.file_names().any(|name| name == MANIFEST) {
+            check_manifest(&mut archive)?;
+        }
+        return Ok((archive, None));
+    }
+
+    if bytes.len() < HEADER_LEN {
+        return Err(AdminError::Invalid(format!("{} is truncated", path.display())));
+    }
+    let (header, sealed) = bytes.split_at(HEADER_LEN);
+    if header[MAGIC.len()] != FORMAT_VERSION {
+        return Err(AdminError::Invalid(format!("{} uses backup format {}, this version reads format {FORMAT_VERSION}", path.display(), header[MAGIC.len()])));
+    }
+
+    let passphrase = crypto::passphrase(PASSPHRASE_ENV, "Backup passphrase", false)?;
+    let plain = SecretKey::derive(&passphrase, &header[HEADER_LEN - crypto::SALT_LEN..])?.open(sealed, header)?;
+
+    let mut archive = ZipArchive::new(Cursor::new(plain)).map_err(|e| AdminError::Invalid(format!("{} doesn't contain a ZIP: {e}", path.display())))?;
+    check_manifest(&mut archive)?;
+    Ok((archive, Some(passphrase)))
+}
+
+/// Every file must be listed in the manifest with a matching SHA-256 and every listed file must be present
+fn check_manifest(archive: &mut Archive) -> Result<Manifest, AdminError> {
+    let manifest: Manifest = {
+        let file = archive.by_name(MANIFEST).map_err(|_| AdminError::Invalid(format!("backup has no {MANIFEST}")))?;
+        serde_json::from_reader(file)?
+    };
+
+    let mut seen = 0;
+    for i in 0..archive.len() {
+        let mut file = archive.by_index(i).map_err(io::Error::other)?;
+        let name = file.name().to_string();
+        if name == MANIFEST || file.is_dir() {
+            continue;
+        }
+
+        let mut buffer = Vec::new();
+        file.read_to_end(&mut buffer)?;
+
+        match manifest.files.get(&name) {
+            Some(hash) if *hash == crypto::sha256_hex(&buffer) => seen += 1,
+            Some(_) => return Err(AdminError::Invalid(format!("{name} doesn't match its hash in the manifest"))),
+            None => return Err(AdminError::Invalid(format!("{name} isn't listed in the manifest"))),
+        }
+    }
+
+    if seen != manifest.files.len() {
+        return Err(AdminError::Invalid("files listed in the manifest are missing from the backup".to_string()));
+    }
+    Ok(manifest)
+}
+
+/// Directory inside the data directory an archive is unpacked into, removed again when dropped
+struct Staging(PathBuf);
+
+impl Staging {
+    fn new(data_dir: &Path) -> io::Result<Self> {
+        let staging = data_dir.join(format!("{STAGING_PREFIX}{}", chrono::Utc::now().format("%Y%m%d_%H%M%S")));
+        fs::create_dir_all(&staging)?;
+        Ok(Self(staging))
+    }
+
+    /// Unpack the archive and open its database, entries are sanitized so nothing lands outside the staging directory
+    async fn unpack(&self, archive: &mut Archive) -> Result<Pool<Any>, AdminError> {
+        archive.extract(&self.0).map_err(|e| AdminError::Invalid(format!("failed to unpack the backup: {e}")))?;
+
+        let staged_db = self.database();
+        if !staged_db.is_file() {
+            return Err(AdminError::Invalid(format!("backup doesn't contain {DB_FILE}")));
+        }
+
+        // Switching the copy to a rollback journal folds any WAL into the file itself
+        let pool = AnyPoolOptions::new().max_connections(1).connect(&format!("sqlite://{}", staged_db.display())).await?;
+        sqlx::query("PRAGMA journal_mode = DELETE").execute(&pool).await?;
+        Ok(pool)
+    }
+
+    fn database(&self) -> PathBuf {
+        self.0.join(DB_FILE)
+    }
+
+    fn templates(&self) -> PathBuf {
+        self.0.join(TEMPLATE_DIR)
+    }
+}
+
+impl Drop for Staging {
+    fn drop(&mut self) {
+        if let Err(e) = fs::remove_dir_all(&self.0) {
+            warn!("Failed to remove staging directory {}: {e}", self.0.display());
+        }
+    }
+}
+
+/// Check a backup's passphrase, manifest and database without restoring it
+pub async fn verify_backup(location: &Location, path: &Path) -> Result<(), AdminError> {
+    let (mut archive, passphrase) = read_archive(path)?;
+    let staging = Staging::new(&location.data_dir)?;
+    let staged = staging.unpack(&mut archive).await?;
+
+    let checked = validate_database(&staged).await;
+    let counts = match checked {
+        Ok(()) => table_counts(&staged).await,
+        Err(e) => Err(e),
+    };
+    staged.close().await;
+    let counts = counts?;
+
+    for (table, count) in counts {
+        println!("{table:<14} | {count:>10}");
+    }
+    if passphrase.is_some() {
+        println!("✅ {} is intact and decrypts with the given passphrase", path.display());
+    } else {
+        println!("✅ {} is intact but unencrypted, create a new backup to encrypt it", path.display());
+    }
+    Ok(())
+}
+
+/// Replace the current database with the one in a backup archive.
+///
+/// The archive is unpacked into a staging directory next to the live database and checked first,
+/// the current database is backed up, then the staged file and templates are renamed over the live ones so a failure
+/// part way never leaves a half written database behind.
+pub async fn restore_database(pool: Pool<Any>, location: &Location, args: RestoreArgs) -> Result<(), AdminError> {
+    let db_path = location.sqlite_file("restore")?;
+
+    // 1) Decrypt, check the manifest and unpack
+    let (mut archive, passphrase) = read_archive(&args.archive)?;
+    let staging = Staging::new(&location.data_dir)?;
+    let staged = staging.unpack(&mut archive).await?;
+
+    // 2) Validate the staged copy
+    let checked = validate_database(&staged).await;
+    let summary = match checked {
+        Ok(()) => summarize(&pool, &staged).await,
+        Err(e) => Err(e),
+    };
+    staged.close().await;
+    let summary = summary?;
+
+    // 3) Show what changes and let the operator back out
+    print_summary(&summary);
+
+    if !args.yes {
+        if !input::interactive() {
+            return Err(AdminError::MissingInput("--yes".to_string()));
+        }
+        if !input::confirm("Replace the current database with this backup?", false)? {
+            println!("Restore cancelled, nothing was changed.");
+            return Ok(());
+        }
+    }
+
+    // 4) Safety backup of the current database, taken with every connection closed so the file is consistent.
+    //    It is encrypted with the passphrase of the restored backup, or a new one when that was unencrypted
+    let passphrase = match passphrase {
+        Some(passphrase) => passphrase,
+        None => crypto::passphrase(PASSPHRASE_ENV, "Passphrase for the safety backup", true)?,
+    };
+    pool.close().await;
+
+    let safety_dir = location.data_dir.join(MIGRATION_BACKUP_DIR);
+    fs::create_dir_all(&safety_dir)?;
+    let safety_path = safety_dir.join(format!("rustlock-pre-restore-{}.{EXTENSION}", chrono::Utc::now().format("%Y%m%d_%H%M%S")));
+    write_archive(location, &safety_path, &passphrase)?;
+    info!("Safety backup of the current database written to {}", safety_path.display());
+
+    // 5) Swap the database and the delivery templates in, rename is atomic within the data directory
+    for suffix in &DB_SUFFIXES[1..] {
+        let mut stale = db_path.as_os_str().to_os_string();
+        stale.push(suffix);
+        if Path::new(&stale).exists() {
+            fs::remove_file(stale)?;
+        }
+    }
+    fs::rename(staging.database(), db_path)?;
+
+    // Backups without templates leave the current ones in place
+    let staged_templates = staging.templates();
+    if staged_templates.is_dir() {
+        let templates = location.data_dir.join(TEMPLATE_DIR);
+        if templates.exists() {
+            fs::remove_dir_all(&templates)?;
+        }
+        fs::rename(staged_templates, templates)?;
+    }
+
+    println!("✅ Database restored from {} to {}", args.archive.display(), db_path.display());
+    println!("   Previous database saved to {}", safety_path.display());
+    Ok(())
+}
+
+/// Integrity check, required tables and columns, then upgrade to the current schema and check every application's keys
+async fn validate_database(pool: &Pool<Any>) -> Result<(), AdminError> {
+    let integrity: Vec<String> = sqlx::query_scalar("PRAGMA integrity_check").fetch_all(pool).await?;
+    if integrity != ["ok"] {
+        return Err(AdminError::Invalid(format!("backup failed the integrity check: {}", integrity.join("; "))));
+    }
+
+    for (table, required) in REQUIRED_COLUMNS {
+        let columns: Vec<String> = sqlx::query(&format!("PRAGMA table_info({table})")).fetch_all(pool).await?.iter().map(|row| row.try_get("name")).collect::<Result<_, _>>()?;
+
+        if columns.is_empty() {
+            return Err(AdminError::Invalid(format!("backup has no {table} table")));
+        }
+        if let Some(missing) = required.iter().find(|column| !columns.iter().any(|c| c == *column)) {
+            return Err(AdminError::Invalid(format!("backup table {table} has no {missing} column")));
+        }
+    }
+
+    db::repository(pool).migrate().await?;
+
+    for app in db::fetch_applications(pool).await? {
+        applications::check_key_pairs(&app)?;
+    }
+
+    Ok(())
+}
+
+/// Row counts per table and the records that only exist in one of the databases
+struct RestoreSummary {
+    counts: Vec<(&'static str, i64, i64)>,
+    dropped: Vec<String>,
+    added: Vec<String>,
+}
+
+async fn table_counts(pool: &Pool<Any>) -> Result<Vec<(&'static str, i64)>, AdminError> {
+    let mut counts = Vec::new();
+    for (table, _) in REQUIRED_COLUMNS {
+        counts.push((table, sqlx::query_scalar(&format!("SELECT COUNT(*) FROM {table}")).fetch_one(pool).await?));
+    }
+    Ok(counts)
+}
+
+async fn summarize(current: &Pool<Any>, backup: &Pool<Any>) -> Result<RestoreSummary, AdminError> {
+    let counts = table_counts(current).await?.into_iter().zip(table_counts(backup).await?).map(|((table, now), (_, then))| (table, now, then)).collect();
+
+    let mut dropped = Vec::new();
+    let mut added = Vec::new();
+    for (kind, query) in [("customer", "SELECT id, name FROM customers"), ("application", "SELECT id, name FROM applications")] {
+        let now: BTreeMap<i64, String> = sqlx::query_as(query).fetch_all(current).await?.into_iter().collect();
+        let then: BTreeMap<i64, String> = sqlx::query_as(query).fetch_all(backup).await?.into_iter().collect();
+
+        dropped.extend(now.iter().filter(|(id, name)| then.get(*id) != Some(*name)).map(|(id, name)| format!("{kind} {id} – {name}")));
+        added.extend(then.iter().filter(|(id, name)| now.get(*id) != Some(*name)).map(|(id, name)| format!("{kind} {id} – {name}")));
+    }
+
+    Ok(RestoreSummary { counts, dropped, added })
+}
+
+fn print_summary(summary: &RestoreSummary) {
+    println!("{}", "-".repeat(40));
+    println!("{:<14} | {:>10} | {:>10}", "Table", "Current", "Backup");
+    println!("{}", "-".repeat(40));
+    for (table, current, backup) in &summary.counts {
+        println!("{table:<14} | {current:>10} | {backup:>10}");
+    }
+    println!();
+
+    if !summary.dropped.is_empty() {
+        println!("Replaced or removed by the restore:");
+        for record in &summary.dropped {
+            println!("  - {record}");
+        }
+    }
+    if !summary.added.is_empty() {
+        println!("Brought back by the restore:");
+        for record in &summary.added {
+            println!("  + {record}");
+        }
+    }
+}
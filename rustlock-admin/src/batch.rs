@@ -0,0 +1,191 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use clap::Args;
+use log::{error, info};
+use rustlock_core::{license::License, sysinfo::SysInfo};
+use serde::Deserialize;
+use sqlx::{Any, Pool};
+
+use crate::audit;
+use crate::db::{Application, Customer};
+use crate::error::AdminError;
+use crate::input;
+use crate::license::{self, IssueRequest, IssuedLicense, IssuedLicenseRecord};
+use crate::output;
+
+/// Inputs for `issue-batch`
+#[derive(Args, Debug)]
+pub struct IssueBatchArgs {
+    /// CSV or JSON file with one license per row
+    pub file: PathBuf,
+    /// CSV file the issued licenses are written to
+    #[arg(long, default_value = "rustlock-issued.csv")]
+    pub output: PathBuf,
+    /// Check every row without issuing anything
+    #[arg(long)]
+    pub dry_run: bool,
+    /// Skip the confirmation, required when no terminal is attached
+    #[arg(long)]
+    pub yes: bool,
+}
+
+/// One row of a batch file. Features are a list in JSON and names separated by semicolons in CSV, as in `show licenses`
+#[derive(Debug, Deserialize)]
+struct BatchRow {
+    customer: i64,
+    application: i64,
+    hwid: String,
+    version: String,
+    #[serde(default)]
+    max_version: Option<String>,
+    /// Defaults to 1 year
+    #[serde(default)]
+    support_years: Option<i32>,
+    #[serde(default)]
+    features: Option<FeatureNames>,
+}
+
+/// `features` of a batch row
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum FeatureNames {
+    List(Vec<String>),
+    Joined(String),
+}
+
+impl FeatureNames {
+    fn into_names(self) -> Vec<String> {
+        let names = match self {
+            Self::List(names) => names,
+            Self::Joined(joined) => joined.split(output::CSV_LIST_SEPARATOR).map(str::to_string).collect(),
+        };
+        names.into_iter().map(|name| name.trim().to_string()).filter(|name| !name.is_empty()).collect()
+    }
+}
+
+/// A row that passed validation, with its license built but not stored
+struct ValidRow<'a> {
+    app: &'a Application,
+    customer: &'a Customer,
+    request: IssueRequest,
+    license: License,
+    fingerprint: SysInfo,
+    issued_license: String,
+}
+
+/// Resolve a row against the database and build its license, which decodes the fingerprint
+fn validate_row<'a>(row: BatchRow, apps: &'a [Application], customers: &'a [Customer]) -> Result<ValidRow<'a>, AdminError> {
+    let Some(app) = apps.iter().find(|a| a.id == row.application) else {
+        return Err(AdminError::NotFound(format!("application with ID {}", row.application)));
+    };
+    let Some(customer) = customers.iter().find(|c| c.id == row.customer) else {
+        return Err(AdminError::NotFound(format!("customer with ID {}", row.customer)));
+    };
+
+    let names = row.features.map(FeatureNames::into_names).unwrap_or_default();
+    let request = IssueRequest {
+        hwid: row.hwid,
+        support_years: row.support_years.unwrap_or(1),
+        version: row.version,
+        max_version: row.max_version.and_then(input::non_empty),
+        features: license::features_by_name(app, &names)?,
+        end: None,
+    };
+
+    let (license, fingerprint, issued_license) = license::build_license(app, customer, &request)?;
+    Ok(ValidRow {
+        app,
+        customer,
+        request,
+        license,
+        fingerprint,
+        issued_license,
+    })
+}
+
+/// Issue a license for every row of a CSV or JSON file. Every row is checked first and nothing is stored unless all of them are valid
+pub async fn issue_batch(pool: &Pool<Any>, args: IssueBatchArgs) -> Result<(), AdminError> {
+    let rows: Vec<BatchRow> = input::read_rows(&args.file)?;
+    if rows.is_empty() {
+        return Err(AdminError::Invalid(format!("{} has no rows", args.file.display())));
+    }
+
+    let apps = crate::db::fetch_applications(pool).await?;
+    let customers = crate::db::fetch_customers(pool).await?;
+
+    // 1) Validate every row before anything is written
+    let total = rows.len();
+    let mut valid = Vec::new();
+    let mut invalid = 0;
+    let mut seen: HashMap<(i64, String), usize> = HashMap::new();
+
+    for (i, row) in rows.into_iter().enumerate() {
+        let number = i + 1;
+        let duplicate = seen.insert((row.application, row.hwid.clone()), number);
+        if let Some(first) = duplicate {
+            error!("row {number}: same application and HWID as row {first}");
+        }
+
+        match validate_row(row, &apps, &customers) {
+            Ok(row) if duplicate.is_none() => valid.push(row),
+            Ok(_) => invalid += 1,
+            Err(e) => {
+                error!("row {number}: {e}");
+                invalid += 1;
+            }
+        }
+    }
+
+    if invalid > 0 {
+        return Err(AdminError::Invalid(format!("{invalid} of {total} rows are invalid, no licenses were issued")));
+    }
+
+    info!("All {total} rows are valid.");
+    if args.dry_run {
+        println!("✅ Dry run, no licenses were issued.");
+        return Ok(());
+    }
+
+    if !args.yes && !input::confirm(&format!("Issue {total} licenses?"), true)? {
+        info!("Batch issue cancelled.");
+        return Ok(());
+    }
+
+    // 2) Store the licenses and write the output. It is written next to the output and renamed into place once the
+    //    licenses are stored, so a bad path fails before anything is stored and a failed batch leaves no output behind
+    let mut partial = args.output.as_os_str().to_os_string();
+    partial.push(".part");
+    let partial = PathBuf::from(partial);
+    let out = std::fs::File::create(&partial).map_err(|e| AdminError::Other(format!("{}: {e}", partial.display())))?;
+
+    if let Err(e) = store_and_write(pool, valid, out).await {
+        let _ = std::fs::remove_file(&partial);
+        return Err(e);
+    }
+    std::fs::rename(&partial, &args.output).map_err(|e| AdminError::Other(format!("{}: {e}", args.output.display())))?;
+
+    info!("Issued {total} license(s) from {}.", args.file.display());
+    println!("✅ Licenses written to: {}", args.output.display());
+    Ok(())
+}
+
+/// Store every license with its audit entry in one transaction, committed only once the output was written
+async fn store_and_write(pool: &Pool<Any>, valid: Vec<ValidRow<'_>>, out: std::fs::File) -> Result<(), AdminError> {
+    let mut tx = crate::db::repository(pool).begin().await?;
+    let mut records = Vec::new();
+    for row in valid {
+        let id = license::insert_license(&mut tx, row.app, row.customer, &row.request, &row.license, &row.fingerprint, &row.issued_license).await?;
+        audit::record(&mut tx, "issue", "license", Some(id), None, Some(audit::snapshot(&row.license)?)).await?;
+
+        let issued = IssuedLicense {
+            id,
+            license: row.license,
+            issued_license: row.issued_license,
+        };
+        records.push(IssuedLicenseRecord::new(row.app, row.customer, &row.request.hwid, &issued));
+    }
+    output::write_csv(out, &records)?;
+    tx.commit().await?;
+    Ok(())
+}
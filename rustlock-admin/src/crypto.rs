@@ -0,0 +1,86 @@
+use argon2::Argon2;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload, rand_core::RngCore};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use dialoguer::{Password, theme::ColorfulTheme};
+use sha2::{Digest, Sha256};
+
+use crate::error::AdminError;
+use crate::input;
+
+/// Length of the random salt stored next to everything encrypted with a passphrase
+pub const SALT_LEN: usize = 16;
+
+const NONCE_LEN: usize = 24;
+
+/// Passphrases shorter than this are refused when they are first set
+const MIN_PASSPHRASE_LEN: usize = 8;
+
+/// Symmetric key derived from a passphrase
+#[derive(Clone)]
+pub struct SecretKey([u8; 32]);
+
+impl SecretKey {
+    /// Argon2id with the crate's default cost parameters
+    pub fn derive(passphrase: &str, salt: &[u8]) -> Result<Self, AdminError> {
+        let mut key = [0u8; 32];
+        Argon2::default().hash_password_into(passphrase.as_bytes(), salt, &mut key).map_err(|e| AdminError::Other(format!("key derivation failed: {e}")))?;
+        Ok(Self(key))
+    }
+
+    /// Encrypt and authenticate `plaintext`, `aad` is authenticated but not stored. Returns nonce + ciphertext
+    pub fn seal(&self, plaintext: &[u8], aad: &[u8]) -> Result<Vec<u8>, AdminError> {
+        let cipher = XChaCha20Poly1305::new((&self.0).into());
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let sealed = cipher.encrypt(&nonce, Payload { msg: plaintext, aad }).map_err(|_| AdminError::Other("encryption failed".to_string()))?;
+
+        let mut out = nonce.to_vec();
+        out.extend_from_slice(&sealed);
+        Ok(out)
+    }
+
+    /// Reverse of [`Self::seal`], fails when the key is wrong or anything was tampered with
+    pub fn open(&self, sealed: &[u8], aad: &[u8]) -> Result<Vec<u8>, AdminError> {
+        if sealed.len() < NONCE_LEN {
+            return Err(AdminError::Invalid("encrypted data is truncated".to_string()));
+        }
+
+        let (nonce, msg) = sealed.split_at(NONCE_LEN);
+        let cipher = XChaCha20Poly1305::new((&self.0).into());
+        cipher.decrypt(XNonce::from_slice(nonce), Payload { msg, aad }).map_err(|_| AdminError::Invalid("wrong passphrase or the data was modified".to_string()))
+    }
+}
+
+/// Fresh random salt
+pub fn random_salt() -> [u8; SALT_LEN] {
+    let mut salt = [0u8; SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+    salt
+}
+
+/// Hex encoded SHA-256 of `data`
+pub fn sha256_hex(data: &[u8]) -> String {
+    hex::encode(Sha256::digest(data))
+}
+
+/// Read a passphrase from `env_var`, otherwise prompt for it when a terminal is attached.
+/// New passphrases are asked for twice and must have a minimum length.
+pub fn passphrase(env_var: &str, prompt: &str, new: bool) -> Result<String, AdminError> {
+    let passphrase = match std::env::var(env_var) {
+        Ok(value) => value,
+        Err(_) if input::interactive() => {
+            let theme = ColorfulTheme::default();
+            let mut password = Password::with_theme(&theme);
+            password = password.with_prompt(prompt);
+            if new {
+                password = password.with_confirmation("Repeat passphrase", "Passphrases don't match");
+            }
+            password.interact()?
+        }
+        Err(_) => return Err(AdminError::MissingInput(env_var.to_string())),
+    };
+
+    if new && passphrase.chars().count() < MIN_PASSPHRASE_LEN {
+        return Err(AdminError::Invalid(format!("passphrase must be at least {MIN_PASSPHRASE_LEN} characters")));
+    }
+    Ok(passphrase)
+}
@@ -1,112 +1,395 @@
-use dialoguer::{Input, Select, theme::ColorfulTheme};
-use log::info;
-use sqlx::{Pool, Row, Sqlite};
-
-/// Show all customers in a simple table
-pub async fn show_customers(pool: &Pool<Sqlite>) -> sqlx::Result<()> {
-    let rows = sqlx::query("SELECT id, name, contact_email, mobile FROM customers").fetch_all(pool).await?;
-
-    println!("{}", "-".repeat(80));
-    println!("{:<6} | {:<20} | {:<30} | {:<15}", "ID", "Name", "Email", "Mobile");
-    println!("{}", "-".repeat(80));
-
-    for row in rows {
-        let id: i64 = row.try_get("id")?;
-        let name: String = row.try_get("name")?;
-        let email: String = row.try_get("contact_email")?;
-        let mobile: String = row.try_get("mobile")?;
-        println!("{id:<6} | {name:<20} | {email:<30} | {mobile:<15}");
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use clap::Args;
+use dialoguer::{Input, theme::ColorfulTheme};
+use log::{error, info};
+use serde::{Deserialize, Serialize};
+use sqlx::{Any, AnyConnection, Pool};
+
+use crate::audit;
+use crate::db::Customer;
+use crate::error::AdminError;
+use crate::input;
+use crate::output::{self, OutputFormat};
+
+/// Inputs for `add customer`, missing values are prompted for when a terminal is attached
+#[derive(Args, Serialize, Deserialize, Default, Debug)]
+pub struct AddCustomerArgs {
+    /// Customer name
+    #[arg(long)]
+    pub name: Option<String>,
+    /// Contact email
+    #[arg(long)]
+    pub email: Option<String>,
+    /// Mobile number
+    #[arg(long)]
+    pub mobile: Option<String>,
+    /// Read the fields above from a JSON or TOML file
+    #[arg(long)]
+    #[serde(skip)]
+    pub input: Option<PathBuf>,
+}
+
+/// Inputs for `update customer`, fields left out keep their current value when no terminal is attached
+#[derive(Args, Serialize, Deserialize, Default, Debug)]
+pub struct UpdateCustomerArgs {
+    /// ID of the customer to update
+    #[arg(long)]
+    pub id: Option<i64>,
+    /// Customer name
+    #[arg(long)]
+    pub name: Option<String>,
+    /// Contact email
+    #[arg(long)]
+    pub email: Option<String>,
+    /// Mobile number
+    #[arg(long)]
+    pub mobile: Option<String>,
+    /// Read the fields above from a JSON or TOML file
+    #[arg(long)]
+    #[serde(skip)]
+    pub input: Option<PathBuf>,
+}
+
+/// Inputs for `import customers`
+#[derive(Args, Debug)]
+pub struct ImportCustomersArgs {
+    /// CSV or JSON file with `name`, `contact_email` and `mobile` columns, as written by `export customers`
+    pub file: PathBuf,
+    /// Update the name and mobile of customers whose email already exists instead of skipping them
+    #[arg(long)]
+    pub update: bool,
+    /// Show what would change without writing anything
+    #[arg(long)]
+    pub dry_run: bool,
+    /// Skip the confirmation, required when no terminal is attached
+    #[arg(long)]
+    pub yes: bool,
+}
+
+/// One row of a customer import, an `id` column from `export customers` is ignored
+#[derive(Debug, Deserialize)]
+struct ImportRow {
+    name: String,
+    #[serde(alias = "email")]
+    contact_email: String,
+    #[serde(default)]
+    mobile: String,
+}
+
+/// What `import customers` does with a row
+#[derive(Debug, Serialize)]
+pub struct ImportAction {
+    pub row: usize,
+    /// `create`, `update`, `unchanged` or `skip`
+    pub action: &'static str,
+    /// ID of the existing customer with the same email
+    pub id: Option<i64>,
+    pub name: String,
+    pub contact_email: String,
+    pub mobile: String,
+}
+
+/// A customer that hasn't been stored yet
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NewCustomer {
+    pub name: String,
+    pub contact_email: String,
+    pub mobile: String,
+}
+
+/// Pick a customer by `--customer`, or prompt for one when a terminal is attached. Archived customers are only found by ID
+pub fn select_customer<'a>(customers: &'a [Customer], id: Option<i64>, prompt: &str) -> Result<&'a Customer, AdminError> {
+    let items: Vec<(i64, String)> = customers.iter().filter(|c| c.archived_at.is_none() || Some(c.id) == id).map(|c| (c.id, c.name.clone())).collect();
+    let selection = input::select_id(id, &items, "customer", prompt)?;
+    customers.iter().find(|c| c.id == items[selection].0).ok_or_else(|| AdminError::NotFound(format!("customer with ID {}", items[selection].0)))
+}
+
+/// Show all customers in a simple table, or as JSON/CSV. Archived customers are left out unless `all` is set
+pub async fn show_customers(pool: &Pool<Any>, format: OutputFormat, all: bool) -> Result<(), AdminError> {
+    let customers: Vec<Customer> = crate::db::fetch_customers(pool).await?.into_iter().filter(|c| all || c.archived_at.is_none()).collect();
+
+    output::print_records(format, &customers, |customers| {
+        println!("{}", "-".repeat(80));
+        println!("{:<6} | {:<20} | {:<30} | {:<15}", "ID", "Name", "Email", "Mobile");
+        println!("{}", "-".repeat(80));
+
+        for c in customers {
+            let name = if c.archived_at.is_some() { format!("{} (archived)", c.name) } else { c.name.clone() };
+            println!("{:<6} | {:<20} | {:<30} | {:<15}", c.id, name, c.contact_email, c.mobile);
+        }
+    })
+}
+
+fn validate_email(input: &str) -> Result<(), &'static str> {
+    if input.contains('@') { Ok(()) } else { Err("Must be a valid email address") }
+}
+
+/// Check the fields the wizards validate while prompting
+pub fn validate_customer(name: &str, contact_email: &str) -> Result<(), AdminError> {
+    if name.trim().is_empty() {
+        return Err(AdminError::Invalid("customer name must not be empty".to_string()));
     }
+    validate_email(contact_email).map_err(|e| AdminError::Invalid(format!("{e}: {contact_email}")))
+}
+
+/// Insert a validated customer, returning its new ID
+pub async fn create_customer(pool: &Pool<Any>, customer: &NewCustomer) -> Result<i64, AdminError> {
+    validate_customer(&customer.name, &customer.contact_email)?;
+
+    let mut tx = crate::db::repository(pool).begin().await?;
+    let id = insert_customer(&mut tx, customer).await?;
+    tx.commit().await?;
+    Ok(id)
+}
+
+/// Insert and audit a customer on `conn`, the caller validates it and owns the transaction
+async fn insert_customer(conn: &mut AnyConnection, customer: &NewCustomer) -> Result<i64, AdminError> {
+    let id: i64 = sqlx::query_scalar(
+        r"
+        INSERT INTO customers (name, contact_email, mobile)
+        VALUES ($1, $2, $3)
+        RETURNING id
+        ",
+    )
+    .bind(&customer.name)
+    .bind(&customer.contact_email)
+    .bind(&customer.mobile)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    audit::record(conn, "create", "customer", Some(id), None, Some(audit::snapshot(customer)?)).await?;
+    Ok(id)
+}
 
+/// Update every field of an existing customer
+pub async fn update_customer(pool: &Pool<Any>, customer: &Customer) -> Result<(), AdminError> {
+    validate_customer(&customer.name, &customer.contact_email)?;
+
+    let Some(before) = crate::db::fetch_customers(pool).await?.into_iter().find(|c| c.id == customer.id) else {
+        return Err(AdminError::NotFound(format!("customer with ID {}", customer.id)));
+    };
+
+    let mut tx = crate::db::repository(pool).begin().await?;
+    write_customer(&mut tx, &before, customer).await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Update and audit a customer on `conn`, the caller validates it and owns the transaction
+async fn write_customer(conn: &mut AnyConnection, before: &Customer, customer: &Customer) -> Result<(), AdminError> {
+    let result = sqlx::query(
+        r"
+        UPDATE customers
+        SET name = $1,
+            contact_email = $2,
+            mobile = $3
+        WHERE id = $4
+        ",
+    )
+    .bind(&customer.name)
+    .bind(&customer.contact_email)
+    .bind(&customer.mobile)
+    .bind(customer.id)
+    .execute(&mut *conn)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AdminError::NotFound(format!("customer with ID {}", customer.id)));
+    }
+
+    audit::record(conn, "update", "customer", Some(customer.id), Some(audit::snapshot(before)?), Some(audit::snapshot(customer)?)).await?;
     Ok(())
 }
 
 /// Interactive wizard to add a new customer
-pub async fn add_customer_wizard(pool: &Pool<Sqlite>) -> sqlx::Result<()> {
+pub async fn add_customer_wizard(pool: &Pool<Any>, args: AddCustomerArgs) -> Result<(), AdminError> {
+    let file = args.input.clone();
+    let args = input::merge_input_file(args, file.as_deref())?;
     let theme = ColorfulTheme::default();
 
     // 1) Prompt for customer name
-    let name: String = Input::with_theme(&theme).with_prompt("Customer name").interact_text().unwrap();
+    let name = input::text(args.name, "name", || Input::with_theme(&theme).with_prompt("Customer name").interact_text())?;
 
     // 2) Prompt for contact email (simple validation)
-    let contact_email: String = Input::with_theme(&theme)
-        .with_prompt("Contact email")
-        .validate_with(|input: &String| if input.contains('@') { Ok(()) } else { Err("Must be a valid email address") })
-        .interact_text()
-        .unwrap();
+    let contact_email = input::text(args.email, "email", || Input::with_theme(&theme).with_prompt("Contact email").validate_with(|i: &String| validate_email(i)).interact_text())?;
 
     // 3) Prompt for mobile
-    let mobile: String = Input::with_theme(&theme).with_prompt("Mobile number").interact_text().unwrap();
+    let mobile = input::text(args.mobile, "mobile", || Input::with_theme(&theme).with_prompt("Mobile number").interact_text())?;
 
     // 4) Insert into database
-    sqlx::query(
-        r"
-        INSERT INTO customers (name, contact_email, mobile)
-        VALUES (?1, ?2, ?3)
-        ",
-    )
-    .bind(&name)
-    .bind(&contact_email)
-    .bind(&mobile)
-    .execute(pool)
-    .await?;
+    let id = create_customer(pool, &NewCustomer { name, contact_email, mobile }).await?;
 
-    info!("New customer added successfully.");
+    info!("New customer {id} added successfully.");
     info!("✅ Customer added!");
     Ok(())
 }
 
 /// Interactive wizard to update an existing customer
-pub async fn update_customer_wizard(pool: &Pool<Sqlite>) -> sqlx::Result<()> {
+pub async fn update_customer_wizard(pool: &Pool<Any>, args: UpdateCustomerArgs) -> Result<(), AdminError> {
+    let file = args.input.clone();
+    let args = input::merge_input_file(args, file.as_deref())?;
     let theme = ColorfulTheme::default();
 
     // 1) Fetch all customers
     let customers = crate::db::fetch_customers(pool).await?;
-    if customers.is_empty() {
-        println!("⚠️  No customers found. Please add one first.");
-        return Ok(());
-    }
 
     // 2) Prompt the user to select which customer to update
-    let choices: Vec<String> = customers.iter().map(|c| format!("ID {} – {}", c.id, c.name)).collect();
-    let selection = Select::with_theme(&theme).with_prompt("Select a customer to update").default(0).items(&choices).interact().unwrap();
-    let cust = &customers[selection];
+    let cust = select_customer(&customers, args.id, "Select a customer to update")?;
 
     // 3) Prompt for each field, prefilled with current values.
     //    If the user just hits “Enter,” the default (current value) is used.
 
     // Name
-    let new_name: String = Input::with_theme(&theme).with_prompt("Customer name").with_initial_text(cust.name.clone()).interact_text().unwrap();
+    let new_name = input::text_or_current(args.name, &cust.name, || Input::with_theme(&theme).with_prompt("Customer name").with_initial_text(cust.name.clone()).interact_text())?;
 
     // Contact email
-    let new_email: String = Input::with_theme(&theme)
-        .with_prompt("Contact email")
-        .with_initial_text(cust.contact_email.clone())
-        .validate_with(|input: &String| if input.contains('@') { Ok(()) } else { Err("Must be a valid email address") })
-        .interact_text()
-        .unwrap();
+    let new_email = input::text_or_current(args.email, &cust.contact_email, || {
+        Input::with_theme(&theme).with_prompt("Contact email").with_initial_text(cust.contact_email.clone()).validate_with(|i: &String| validate_email(i)).interact_text()
+    })?;
 
     // Mobile
-    let new_mobile: String = Input::with_theme(&theme).with_prompt("Mobile number").with_initial_text(cust.mobile.clone()).interact_text().unwrap();
+    let new_mobile = input::text_or_current(args.mobile, &cust.mobile, || Input::with_theme(&theme).with_prompt("Mobile number").with_initial_text(cust.mobile.clone()).interact_text())?;
 
     // 4) Run the UPDATE query
-    sqlx::query(
-        r"
-        UPDATE customers
-        SET name = ?1,
-            contact_email = ?2,
-            mobile = ?3
-        WHERE id = ?4
-        ",
+    update_customer(
+        pool,
+        &Customer {
+            id: cust.id,
+            name: new_name,
+            contact_email: new_email,
+            mobile: new_mobile,
+            archived_at: cust.archived_at.clone(),
+        },
     )
-    .bind(&new_name)
-    .bind(&new_email)
-    .bind(&new_mobile)
-    .bind(cust.id)
-    .execute(pool)
     .await?;
 
     info!("Customer ID {} updated.", cust.id);
     println!("✅ Customer updated successfully!");
     Ok(())
 }
+
+/// Emails are compared trimmed and case-insensitively to find duplicates
+fn email_key(email: &str) -> String {
+    email.trim().to_lowercase()
+}
+
+/// Import customers from a CSV or JSON file, matching existing customers by email. Every row is checked before anything is written
+pub async fn import_customers(pool: &Pool<Any>, args: ImportCustomersArgs) -> Result<(), AdminError> {
+    let rows: Vec<ImportRow> = input::read_rows(&args.file)?;
+    let existing = crate::db::fetch_customers(pool).await?;
+
+    let mut by_email: HashMap<String, &Customer> = HashMap::new();
+    for customer in existing.iter().rev() {
+        by_email.insert(email_key(&customer.contact_email), customer);
+    }
+
+    // 1) Validate every row and decide what to do with it
+    let mut actions = Vec::new();
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    let mut invalid = 0;
+
+    for (i, row) in rows.into_iter().enumerate() {
+        let number = i + 1;
+        let name = row.name.trim().to_string();
+        let contact_email = row.contact_email.trim().to_string();
+        let mobile = row.mobile.trim().to_string();
+
+        if let Err(e) = validate_customer(&name, &contact_email) {
+            error!("row {number}: {e}");
+            invalid += 1;
+            continue;
+        }
+        if let Some(first) = seen.insert(email_key(&contact_email), number) {
+            error!("row {number}: same email as row {first}");
+            invalid += 1;
+            continue;
+        }
+
+        let current = by_email.get(&email_key(&contact_email));
+        let action = match current {
+            None => "create",
+            Some(c) if c.name == name && c.mobile == mobile => "unchanged",
+            Some(_) if args.update => "update",
+            Some(_) => "skip",
+        };
+
+        actions.push(ImportAction {
+            row: number,
+            action,
+            id: current.map(|c| c.id),
+            name,
+            contact_email,
+            mobile,
+        });
+    }
+
+    if invalid > 0 {
+        return Err(AdminError::Invalid(format!("{invalid} row(s) are invalid, no customers were imported")));
+    }
+
+    // 2) Preview
+    println!("{}", "-".repeat(90));
+    println!("{:<5} | {:<10} | {:<6} | {:<20} | {:<30}", "Row", "Action", "ID", "Name", "Email");
+    println!("{}", "-".repeat(90));
+    for a in &actions {
+        let id = a.id.map(|id| id.to_string()).unwrap_or_default();
+        println!("{:<5} | {:<10} | {:<6} | {:<20} | {:<30}", a.row, a.action, id, a.name, a.contact_email);
+    }
+
+    let count = |action: &str| actions.iter().filter(|a| a.action == action).count();
+    let (created, updated) = (count("create"), count("update"));
+    println!();
+    println!("{created} to create, {updated} to update, {} unchanged, {} skipped (email exists, pass --update to overwrite)", count("unchanged"), count("skip"));
+
+    if args.dry_run {
+        println!("✅ Dry run, no customers were imported.");
+        return Ok(());
+    }
+    if created + updated == 0 {
+        println!("✅ Nothing to import.");
+        return Ok(());
+    }
+    if !args.yes && !input::confirm(&format!("Create {created} and update {updated} customer(s)?"), true)? {
+        info!("Import cancelled.");
+        return Ok(());
+    }
+
+    // 3) Write, all or nothing
+    let mut tx = crate::db::repository(pool).begin().await?;
+    for a in actions {
+        let ImportAction { action, id, name, contact_email, mobile, .. } = a;
+        match (action, id.and_then(|id| existing.iter().find(|c| c.id == id))) {
+            ("create", _) => {
+                insert_customer(&mut tx, &NewCustomer { name, contact_email, mobile }).await?;
+            }
+            ("update", Some(before)) => {
+                let customer = Customer {
+                    id: before.id,
+                    name,
+                    contact_email,
+                    mobile,
+                    archived_at: before.archived_at.clone(),
+                };
+                write_customer(&mut tx, before, &customer).await?;
+            }
+            _ => {}
+        }
+    }
+    tx.commit().await?;
+
+    info!("Imported customers from {}.", args.file.display());
+    println!("✅ {created} customer(s) created, {updated} updated.");
+    Ok(())
+}
+
+/// Write every customer to a CSV or JSON file
+pub async fn export_customers(pool: &Pool<Any>, output: &Path) -> Result<(), AdminError> {
+    let customers = crate::db::fetch_customers(pool).await?;
+    output::write_records(output, &customers)?;
+
+    info!("Exported {} customer(s).", customers.len());
+    println!("✅ Customers written to: {}", output.display());
+    Ok(())
+}
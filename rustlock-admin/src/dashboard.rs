@@ -0,0 +1,365 @@
+use std::net::SocketAddr;
+use std::sync::OnceLock;
+
+use axum::Router;
+use axum::extract::{Form, Path, Query, Request, State};
+use axum::http::{StatusCode, header};
+use axum::middleware::{self, Next};
+use axum::response::{Html, IntoResponse, Redirect, Response};
+use axum::routing::{get, post};
+use clap::Args;
+use handlebars::Handlebars;
+use log::{info, warn};
+use serde::Deserialize;
+use serde_json::{Value, json};
+use sqlx::{Any, Pool};
+use uuid::Uuid;
+
+use crate::db::{fetch_application, fetch_customer};
+use crate::delivery::license_file_name;
+use crate::error::AdminError;
+use crate::input;
+use crate::keystore;
+use crate::license::{self, IssueRequest, LicenseRecord, STATUS_ACTIVE, STATUS_REVOKED, STATUS_SUPERSEDED};
+use crate::renewal;
+
+/// Pages, rendered with the `layout` partial
+const TEMPLATES: [(&str, &str); 7] = [
+    ("layout", include_str!("dashboard/layout.hbs")),
+    ("customers", include_str!("dashboard/customers.hbs")),
+    ("applications", include_str!("dashboard/applications.hbs")),
+    ("licenses", include_str!("dashboard/licenses.hbs")),
+    ("license", include_str!("dashboard/license.hbs")),
+    ("issue", include_str!("dashboard/issue.hbs")),
+    ("error", include_str!("dashboard/error.hbs")),
+];
+
+/// Registered once at startup, error pages are rendered outside of a handler's state
+static PAGES: OnceLock<Handlebars<'static>> = OnceLock::new();
+
+/// Inputs for `dashboard`
+#[derive(Args, Debug)]
+pub struct DashboardArgs {
+    /// Address to listen on. The dashboard has no login, only bind it to other addresses on a trusted network
+    #[arg(long, default_value = "127.0.0.1:8081")]
+    pub listen: SocketAddr,
+}
+
+#[derive(Clone)]
+struct DashboardState {
+    pool: Pool<Any>,
+    /// Sent with every form, so other sites open in the same browser can't post to the dashboard
+    csrf: String,
+    /// `Host` headers accepted while bound to a loopback address, against DNS rebinding
+    hosts: Option<Vec<String>>,
+}
+
+/// An [`AdminError`] as an HTML page
+struct PageError(AdminError);
+
+impl<E: Into<AdminError>> From<E> for PageError {
+    fn from(e: E) -> Self {
+        Self(e.into())
+    }
+}
+
+impl IntoResponse for PageError {
+    fn into_response(self) -> Response {
+        let status = crate::api::status_of(&self.0);
+        match render("error", json!({ "title": "Something went wrong", "message": self.0.to_string() })) {
+            Ok(page) => (status, page).into_response(),
+            Err(_) => (status, self.0.to_string()).into_response(),
+        }
+    }
+}
+
+type PageResult<T = Response> = Result<T, PageError>;
+
+fn render(page: &str, data: Value) -> Result<Html<String>, AdminError> {
+    let pages = PAGES.get().ok_or_else(|| AdminError::Other("dashboard templates aren't loaded".to_string()))?;
+    pages.render(page, &data).map(Html).map_err(|e| AdminError::Other(format!("template {page}: {e}")))
+}
+
+fn register_pages() -> Result<(), AdminError> {
+    let mut pages = Handlebars::new();
+    pages.set_strict_mode(true);
+    for (name, template) in TEMPLATES {
+        pages.register_template_string(name, template).map_err(|e| AdminError::Other(format!("template {name}: {e}")))?;
+    }
+    let _ = PAGES.set(pages);
+    Ok(())
+}
+
+async fn check_host(State(state): State<DashboardState>, request: Request, next: Next) -> Response {
+    if let Some(hosts) = &state.hosts {
+        let host = request.headers().get(header::HOST).and_then(|value| value.to_str().ok()).unwrap_or_default();
+        if !hosts.iter().any(|allowed| allowed == host) {
+            return (StatusCode::FORBIDDEN, "unexpected Host header").into_response();
+        }
+    }
+    next.run(request).await
+}
+
+fn check_csrf(state: &DashboardState, csrf: &str) -> Result<(), AdminError> {
+    if csrf == state.csrf { Ok(()) } else { Err(AdminError::Invalid("the form has expired, reload the page and try again".to_string())) }
+}
+
+/// Form selects send an empty string for "any"
+fn optional_id<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Option<i64>, D::Error> {
+    let value = String::deserialize(deserializer)?;
+    if value.trim().is_empty() { Ok(None) } else { value.trim().parse().map(Some).map_err(serde::de::Error::custom) }
+}
+
+/// Number of active licenses per application or customer ID
+async fn active_counts(pool: &Pool<Any>, column: &str) -> Result<Vec<(i64, i64)>, AdminError> {
+    Ok(sqlx::query_as(&format!("SELECT {column}, COUNT(*) FROM licenses WHERE status = $1 GROUP BY {column}")).bind(STATUS_ACTIVE).fetch_all(pool).await?)
+}
+
+fn count_of(counts: &[(i64, i64)], id: i64) -> i64 {
+    counts.iter().find(|(counted, _)| *counted == id).map_or(0, |(_, count)| *count)
+}
+
+async fn customers_page(State(state): State<DashboardState>) -> PageResult<Html<String>> {
+    let counts = active_counts(&state.pool, "customer_id").await?;
+    let customers: Vec<Value> = crate::db::fetch_customers(&state.pool)
+        .await?
+        .into_iter()
+        .map(|c| json!({ "id": c.id, "name": c.name, "contact_email": c.contact_email, "mobile": c.mobile, "archived_at": c.archived_at, "active": count_of(&counts, c.id) }))
+        .collect();
+
+    Ok(render("customers", json!({ "title": "Customers", "customers": customers }))?)
+}
+
+async fn applications_page(State(state): State<DashboardState>) -> PageResult<Html<String>> {
+    let counts = active_counts(&state.pool, "application_id").await?;
+    let applications: Vec<Value> = crate::db::fetch_applications(&state.pool)
+        .await?
+        .iter()
+        .map(|a| {
+            json!({
+                "id": a.id,
+                "name": a.name,
+                "version_policy": a.version_policy.as_str(),
+                "features": a.feature_schema().features,
+                "archived_at": a.archived_at,
+                "active": count_of(&counts, a.id),
+            })
+        })
+        .collect();
+
+    Ok(render("applications", json!({ "title": "Applications", "applications": applications }))?)
+}
+
+#[derive(Deserialize)]
+struct LicenseFilter {
+    #[serde(default, deserialize_with = "optional_id")]
+    application: Option<i64>,
+    #[serde(default, deserialize_with = "optional_id")]
+    customer: Option<i64>,
+    #[serde(default)]
+    status: String,
+}
+
+async fn licenses_page(State(state): State<DashboardState>, Query(filter): Query<LicenseFilter>) -> PageResult<Html<String>> {
+    let apps = crate::db::fetch_applications(&state.pool).await?;
+    let customers = crate::db::fetch_customers(&state.pool).await?;
+    let customer_name = |id: i64| customers.iter().find(|c| c.id == id).map(|c| c.name.clone()).unwrap_or_default();
+
+    let mut licenses = Vec::new();
+    for app in apps.iter().filter(|a| filter.application.is_none_or(|id| id == a.id)) {
+        for record in license::fetch_licenses(&state.pool, app, filter.customer).await? {
+            if filter.status.is_empty() || record.status == filter.status {
+                licenses.push(json!({
+                    "id": record.id,
+                    "application": app.name,
+                    "customer": customer_name(record.customer_id),
+                    "hostname": record.hostname,
+                    "version": record.version,
+                    "start": record.start,
+                    "end": record.end,
+                    "status": record.status,
+                }));
+            }
+        }
+    }
+
+    let applications: Vec<Value> = apps.iter().map(|a| json!({ "id": a.id, "name": a.name, "selected": filter.application == Some(a.id) })).collect();
+    let customer_options: Vec<Value> = customers.iter().map(|c| json!({ "id": c.id, "name": c.name, "selected": filter.customer == Some(c.id) })).collect();
+    let statuses: Vec<Value> = [STATUS_ACTIVE, STATUS_REVOKED, STATUS_SUPERSEDED].iter().map(|s| json!({ "name": s, "selected": filter.status == *s })).collect();
+
+    Ok(render("licenses", json!({ "title": "Licenses", "licenses": licenses, "applications": applications, "customers": customer_options, "statuses": statuses }))?)
+}
+
+/// License `id` with the name of its application
+async fn find_license(pool: &Pool<Any>, id: i64) -> Result<(String, LicenseRecord), AdminError> {
+    let Some(application_id) = sqlx::query_scalar::<_, i64>("SELECT application_id FROM licenses WHERE id = $1").bind(id).fetch_optional(pool).await? else {
+        return Err(AdminError::NotFound(format!("license with ID {id}")));
+    };
+
+    let app = fetch_application(pool, application_id).await?;
+    let record = license::fetch_licenses(pool, &app, None).await?.into_iter().find(|l| l.id == id).ok_or_else(|| AdminError::NotFound(format!("license with ID {id}")))?;
+    Ok((app.name, record))
+}
+
+async fn license_page(State(state): State<DashboardState>, Path(id): Path<i64>) -> PageResult<Html<String>> {
+    let (application, record) = find_license(&state.pool, id).await?;
+    let customer = fetch_customer(&state.pool, record.customer_id).await?;
+    let replaced_by: Option<i64> = sqlx::query_scalar("SELECT id FROM licenses WHERE renewed_from = $1 OR reissued_from = $1 ORDER BY id DESC LIMIT 1").bind(id).fetch_optional(&state.pool).await?;
+
+    Ok(render(
+        "license",
+        json!({
+            "title": format!("License {id}"),
+            "application": application,
+            "customer": customer.name,
+            "features": record.features.join(", "),
+            "file_name": license_file_name(&application, id),
+            "active": record.status == STATUS_ACTIVE,
+            "replaced_by": replaced_by,
+            "csrf": state.csrf,
+            "license": record,
+        }),
+    )?)
+}
+
+async fn download_license(State(state): State<DashboardState>, Path(id): Path<i64>) -> PageResult {
+    let (application, record) = find_license(&state.pool, id).await?;
+    if record.issued_license.is_empty() {
+        return Err(AdminError::Invalid(format!("license {id} has no license string")).into());
+    }
+
+    let disposition = format!("attachment; filename=\"{}\"", license_file_name(&application, id));
+    Ok(([(header::CONTENT_TYPE, "text/plain; charset=utf-8".to_string()), (header::CONTENT_DISPOSITION, disposition)], record.issued_license).into_response())
+}
+
+#[derive(Deserialize)]
+struct RenewForm {
+    csrf: String,
+    years: i32,
+    #[serde(default)]
+    version: String,
+}
+
+async fn renew(State(state): State<DashboardState>, Path(id): Path<i64>, Form(form): Form<RenewForm>) -> PageResult<Redirect> {
+    check_csrf(&state, &form.csrf)?;
+
+    let record = renewal::renew_license(&state.pool, id, form.years, input::non_empty(form.version), None).await?;
+    info!("Renewed license {id} as license {} from the dashboard", record.id);
+    Ok(Redirect::to(&format!("/licenses/{}", record.id)))
+}
+
+#[derive(Deserialize)]
+struct IssueQuery {
+    #[serde(default, deserialize_with = "optional_id")]
+    application: Option<i64>,
+}
+
+async fn issue_page(State(state): State<DashboardState>, Query(query): Query<IssueQuery>) -> PageResult<Html<String>> {
+    let apps: Vec<_> = crate::db::fetch_applications(&state.pool).await?.into_iter().filter(|a| a.archived_at.is_none()).collect();
+    let customers: Vec<Value> = crate::db::fetch_customers(&state.pool)
+        .await?
+        .into_iter()
+        .filter(|c| c.archived_at.is_none())
+        .map(|c| json!({ "id": c.id, "name": c.name, "contact_email": c.contact_email }))
+        .collect();
+
+    let chosen = query.application.and_then(|id| apps.iter().find(|a| a.id == id));
+    let applications: Vec<Value> = apps.iter().map(|a| json!({ "id": a.id, "name": a.name, "selected": Some(a.id) == query.application })).collect();
+
+    Ok(render(
+        "issue",
+        json!({
+            "title": "Issue License",
+            "applications": applications,
+            "application": chosen.map(|a| json!({ "id": a.id, "features": a.feature_schema().features })),
+            "semver_range": chosen.is_some_and(|a| a.version_policy == rustlock_core::license::VersionPolicy::SemverRange),
+            "customers": customers,
+            "csrf": state.csrf,
+        }),
+    )?)
+}
+
+/// Fields of the issue form, checked features are sent as `f1` to `f5`
+#[derive(Deserialize)]
+struct IssueForm {
+    csrf: String,
+    application: i64,
+    customer: i64,
+    hwid: String,
+    version: String,
+    #[serde(default)]
+    max_version: String,
+    support_years: i32,
+    f1: Option<String>,
+    f2: Option<String>,
+    f3: Option<String>,
+    f4: Option<String>,
+    f5: Option<String>,
+}
+
+async fn issue(State(state): State<DashboardState>, Form(form): Form<IssueForm>) -> PageResult<Redirect> {
+    check_csrf(&state, &form.csrf)?;
+
+    let app = fetch_application(&state.pool, form.application).await?;
+    let customer = fetch_customer(&state.pool, form.customer).await?;
+
+    let request = IssueRequest {
+        hwid: form.hwid.split_whitespace().collect(),
+        support_years: form.support_years,
+        version: form.version.trim().to_string(),
+        max_version: input::non_empty(form.max_version.trim().to_string()),
+        features: [form.f1.is_some(), form.f2.is_some(), form.f3.is_some(), form.f4.is_some(), form.f5.is_some()],
+        end: None,
+    };
+    let issued = license::issue_license(&state.pool, &app, &customer, &request).await?;
+    info!("Issued license {} for app {} to customer {} from the dashboard", issued.id, app.id, customer.id);
+
+    Ok(Redirect::to(&format!("/licenses/{}", issued.id)))
+}
+
+fn router(state: DashboardState) -> Router {
+    Router::new()
+        .route("/", get(|| async { Redirect::to("/licenses") }))
+        .route("/customers", get(customers_page))
+        .route("/applications", get(applications_page))
+        .route("/licenses", get(licenses_page))
+        .route("/licenses/{id}", get(license_page))
+        .route("/licenses/{id}/download", get(download_license))
+        .route("/licenses/{id}/renew", post(renew))
+        .route("/issue", get(issue_page).post(issue))
+        .layer(middleware::from_fn_with_state(state.clone(), check_host))
+        .with_state(state)
+}
+
+/// Serve the web dashboard until Ctrl-C
+pub async fn dashboard(pool: Pool<Any>, args: DashboardArgs) -> Result<(), AdminError> {
+    register_pages()?;
+
+    // Ask for the master passphrase now, pages must never prompt
+    keystore::unlock(&pool).await?;
+    input::disable_prompts();
+
+    let listener = tokio::net::TcpListener::bind(args.listen).await?;
+    let addr = listener.local_addr()?;
+    let hosts = if addr.ip().is_loopback() {
+        Some(vec![addr.to_string(), format!("localhost:{}", addr.port())])
+    } else {
+        warn!("The dashboard has no login and is reachable from other machines on {addr}");
+        None
+    };
+
+    info!("Dashboard running on http://{addr}/");
+    let state = DashboardState {
+        pool,
+        csrf: Uuid::new_v4().simple().to_string(),
+        hosts,
+    };
+    axum::serve(listener, router(state))
+        .with_graceful_shutdown(async {
+            let _ = tokio::signal::ctrl_c().await;
+        })
+        .await?;
+
+    info!("Dashboard stopped");
+    Ok(())
+}
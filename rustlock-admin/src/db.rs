@@ -1,12 +1,23 @@
+use async_trait::async_trait;
+use log::info;
+use rustlock_core::features::{FeatureSchema, FeatureSlot};
+use rustlock_core::license::VersionPolicy;
 use serde::{Deserialize, Serialize};
-use sqlx::{Pool, Row, Sqlite};
+use sqlx::any::AnyRow;
+use sqlx::{Any, Pool, Row, Transaction};
 
-#[derive(Debug)]
+use crate::error::AdminError;
+use crate::keystore;
+
+#[derive(Debug, Serialize, Deserialize, Default)]
 pub struct Customer {
-    pub id: u16,
+    pub id: i64,
     pub name: String,
     pub contact_email: String,
     pub mobile: String,
+    /// Set when the customer was archived, archived customers are hidden from pickers
+    #[serde(default)]
+    pub archived_at: Option<String>,
 }
 
 /// Application table representation
@@ -16,7 +27,7 @@ pub struct Application {
     pub name: String,
     pub lic_public_key: String,
     pub lic_private_key: String,
-    pub blocked_customer_ids: Vec<u16>,
+    pub blocked_customer_ids: Vec<u64>,
     pub machine_id_key: String,
     pub info_public_key: String,
     pub info_private_key: String,
@@ -25,125 +36,475 @@ pub struct Application {
     pub feature3: Option<String>,
     pub feature4: Option<String>,
     pub feature5: Option<String>,
+    pub version_policy: VersionPolicy,
+    /// Lock components (OS, CPU, storage, network) a fingerprint may change on `reissue` without approval
+    pub hardware_changes_allowed: i64,
+    /// Set when the application was archived, archived applications are hidden from pickers
+    #[serde(default)]
+    pub archived_at: Option<String>,
+}
+
+impl Application {
+    /// Named features of this application and the license slot each one uses
+    pub fn feature_schema(&self) -> FeatureSchema {
+        let slots = [&self.feature1, &self.feature2, &self.feature3, &self.feature4, &self.feature5];
+
+        FeatureSchema {
+            application: self.name.clone(),
+            features: (1..).zip(slots).filter_map(|(slot, name)| name.as_ref().map(|name| FeatureSlot { slot, name: name.clone() })).collect(),
+        }
+    }
+}
+
+/// A schema change, applied in order and recorded in `schema_version`
+pub struct Migration {
+    pub version: i64,
+    pub description: &'static str,
+    /// Written for SQLite, see [`Repository::statements`] for PostgreSQL
+    statements: &'static [&'static str],
+    /// Run on PostgreSQL instead of `statements` where those can't be translated
+    postgres: Option<&'static [&'static str]>,
+}
+
+/// Every schema change in order. Released migrations must never be edited, add a new one instead
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "customers, applications and licenses",
+        statements: &[
+            r"
+            CREATE TABLE customers (
+                id            INTEGER PRIMARY KEY AUTOINCREMENT,
+                name          TEXT NOT NULL,
+                contact_email TEXT NOT NULL,
+                mobile        TEXT NOT NULL
+            )",
+            r"
+            CREATE TABLE applications (
+                id                    INTEGER PRIMARY KEY AUTOINCREMENT,
+                name                  TEXT NOT NULL,
+                lic_public_key        TEXT NOT NULL,
+                lic_private_key       TEXT NOT NULL,
+                blocked_customer_ids  TEXT NOT NULL,
+                machine_id_key        TEXT NOT NULL,
+                info_public_key       TEXT NOT NULL,
+                info_private_key      TEXT NOT NULL,
+                feature1              TEXT,
+                feature2              TEXT,
+                feature3              TEXT,
+                feature4              TEXT,
+                feature5              TEXT
+            )",
+            r"
+            CREATE TABLE licenses (
+                id              INTEGER PRIMARY KEY AUTOINCREMENT,
+                hwid            TEXT NOT NULL,
+                support_years   INTEGER NOT NULL,
+                customer_id     INTEGER NOT NULL,
+                application_id  INTEGER NOT NULL,
+                issued_license  TEXT,
+                FOREIGN KEY(customer_id) REFERENCES customers(id),
+                FOREIGN KEY(application_id) REFERENCES applications(id)
+            )",
+        ],
+        postgres: None,
+    },
+    Migration {
+        version: 2,
+        description: "application version policy",
+        statements: &["ALTER TABLE applications ADD COLUMN version_policy TEXT NOT NULL DEFAULT 'major_minor'"],
+        postgres: None,
+    },
+    Migration {
+        version: 3,
+        description: "keystore for the master key of the secret application columns",
+        statements: &[r"
+            CREATE TABLE keystore (
+                id        INTEGER PRIMARY KEY CHECK (id = 1),
+                salt      TEXT NOT NULL,
+                verifier  TEXT NOT NULL
+            )"],
+        postgres: None,
+    },
+    Migration {
+        version: 4,
+        description: "hash chained audit log",
+        statements: &[
+            r"
+            CREATE TABLE audit_log (
+                id          INTEGER PRIMARY KEY AUTOINCREMENT,
+                timestamp   TEXT NOT NULL,
+                actor       TEXT NOT NULL,
+                action      TEXT NOT NULL,
+                entity      TEXT NOT NULL,
+                entity_id   INTEGER,
+                before      TEXT,
+                after       TEXT,
+                prev_hash   TEXT NOT NULL,
+                hash        TEXT NOT NULL
+            )",
+            r"
+            CREATE TRIGGER audit_log_no_update BEFORE UPDATE ON audit_log
+            BEGIN
+                SELECT RAISE(ABORT, 'audit_log is append-only');
+            END",
+            r"
+            CREATE TRIGGER audit_log_no_delete BEFORE DELETE ON audit_log
+            BEGIN
+                SELECT RAISE(ABORT, 'audit_log is append-only');
+            END",
+        ],
+        postgres: Some(&[
+            r"
+            CREATE TABLE audit_log (
+                id          BIGSERIAL PRIMARY KEY,
+                timestamp   TEXT NOT NULL,
+                actor       TEXT NOT NULL,
+                action      TEXT NOT NULL,
+                entity      TEXT NOT NULL,
+                entity_id   BIGINT,
+                before      TEXT,
+                after       TEXT,
+                prev_hash   TEXT NOT NULL,
+                hash        TEXT NOT NULL
+            )",
+            r"
+            CREATE FUNCTION audit_log_append_only() RETURNS trigger LANGUAGE plpgsql AS $$
+            BEGIN
+                RAISE EXCEPTION 'audit_log is append-only';
+            END
+            $$",
+            r"
+            CREATE TRIGGER audit_log_append_only BEFORE UPDATE OR DELETE ON audit_log
+            FOR EACH ROW EXECUTE FUNCTION audit_log_append_only()",
+        ]),
+    },
+    Migration {
+        version: 5,
+        description: "decoded license and fingerprint details",
+        statements: &[
+            "ALTER TABLE licenses ADD COLUMN license_uid TEXT",
+            "ALTER TABLE licenses ADD COLUMN licensed_name TEXT",
+            "ALTER TABLE licenses ADD COLUMN version_policy TEXT",
+            "ALTER TABLE licenses ADD COLUMN version TEXT",
+            "ALTER TABLE licenses ADD COLUMN min_version TEXT",
+            "ALTER TABLE licenses ADD COLUMN start_date TEXT",
+            "ALTER TABLE licenses ADD COLUMN end_date TEXT",
+            "ALTER TABLE licenses ADD COLUMN f1 INTEGER",
+            "ALTER TABLE licenses ADD COLUMN f2 INTEGER",
+            "ALTER TABLE licenses ADD COLUMN f3 INTEGER",
+            "ALTER TABLE licenses ADD COLUMN f4 INTEGER",
+            "ALTER TABLE licenses ADD COLUMN f5 INTEGER",
+            "ALTER TABLE licenses ADD COLUMN hostname TEXT",
+            "ALTER TABLE licenses ADD COLUMN os_name TEXT",
+            "ALTER TABLE licenses ADD COLUMN os_version TEXT",
+            "ALTER TABLE licenses ADD COLUMN cpu TEXT",
+            "ALTER TABLE licenses ADD COLUMN memory INTEGER",
+            "ALTER TABLE licenses ADD COLUMN mac_address TEXT",
+            "ALTER TABLE licenses ADD COLUMN storage_name TEXT",
+            "ALTER TABLE licenses ADD COLUMN storage_type TEXT",
+            "ALTER TABLE licenses ADD COLUMN cpu_hash TEXT",
+            "ALTER TABLE licenses ADD COLUMN os_hash TEXT",
+            "ALTER TABLE licenses ADD COLUMN network_hash TEXT",
+            "ALTER TABLE licenses ADD COLUMN storage_hash TEXT",
+            "ALTER TABLE licenses ADD COLUMN issued_at TEXT",
+            "CREATE INDEX licenses_end_date ON licenses (end_date)",
+        ],
+        postgres: None,
+    },
+    Migration {
+        version: 6,
+        description: "license revocation status",
+        statements: &[
+            "ALTER TABLE licenses ADD COLUMN status TEXT NOT NULL DEFAULT 'active'",
+            "ALTER TABLE licenses ADD COLUMN revoked_at TEXT",
+            "ALTER TABLE licenses ADD COLUMN revoked_reason TEXT",
+            "ALTER TABLE licenses ADD COLUMN status_before_revoke TEXT",
+        ],
+        postgres: None,
+    },
+    Migration {
+        version: 7,
+        description: "archived customers and applications",
+        statements: &["ALTER TABLE customers ADD COLUMN archived_at TEXT", "ALTER TABLE applications ADD COLUMN archived_at TEXT"],
+        postgres: None,
+    },
+    Migration {
+        version: 8,
+        description: "license renewals",
+        statements: &["ALTER TABLE licenses ADD COLUMN renewed_from INTEGER REFERENCES licenses(id)"],
+        postgres: None,
+    },
+    Migration {
+        version: 9,
+        description: "hardware change reissues",
+        statements: &[
+            "ALTER TABLE applications ADD COLUMN hardware_changes_allowed INTEGER NOT NULL DEFAULT 1",
+            "ALTER TABLE licenses ADD COLUMN reissued_from INTEGER REFERENCES licenses(id)",
+            "ALTER TABLE licenses ADD COLUMN reissue_count INTEGER NOT NULL DEFAULT 0",
+        ],
+        postgres: None,
+    },
+];
+
+/// Schema version this build writes
+pub const LATEST_VERSION: i64 = MIGRATIONS[MIGRATIONS.len() - 1].version;
+
+/// Storage of the admin data, picked by the scheme of the database URL with [`repository`].
+///
+/// Records are read and written with the same SQL on every engine, an engine only differs in how it locks for writes,
+/// looks at its own schema and spells migrations
+#[async_trait]
+pub trait Repository: Send + Sync {
+    fn pool(&self) -> &Pool<Any>;
+
+    /// Start a transaction that writes records and their audit entries. It holds the write lock of the audit log
+    /// from the start, so two operators writing at once can't both chain their entry to the same previous one
+    async fn begin(&self) -> sqlx::Result<Transaction<'static, Any>>;
+
+    async fn table_exists(&self, table: &str) -> sqlx::Result<bool>;
+
+    /// Databases from before `schema_version` existed, worked out from the changes they already have
+    async fn legacy_version(&self) -> sqlx::Result<i64>;
+
+    /// Statements of `migration` for this engine
+    fn statements(&self, migration: &Migration) -> Vec<String>;
+
+    /// Version of the database schema, 0 for a new database
+    async fn schema_version(&self) -> sqlx::Result<i64> {
+        if self.table_exists("schema_version").await? {
+            sqlx::query_scalar("SELECT COALESCE(MAX(version), 0) FROM schema_version").fetch_one(self.pool()).await
+        } else {
+            self.legacy_version().await
+        }
+    }
+
+    /// Migrations the database hasn't had yet, fails for databases written by a newer version
+    async fn pending_migrations(&self) -> Result<Vec<&'static Migration>, AdminError> {
+        let current = self.schema_version().await?;
+        if current > LATEST_VERSION {
+            return Err(AdminError::Invalid(format!("database schema version {current} is newer than this rustlock-admin supports ({LATEST_VERSION}), please upgrade")));
+        }
+
+        Ok(MIGRATIONS.iter().filter(|m| m.version > current).collect())
+    }
+
+    /// Apply every pending migration, each in its own transaction together with its `schema_version` row
+    async fn migrate(&self) -> Result<(), AdminError> {
+        let pool = self.pool();
+        let current = self.schema_version().await?;
+        let pending = self.pending_migrations().await?;
+
+        if !self.table_exists("schema_version").await? {
+            sqlx::query(
+                r"
+                CREATE TABLE schema_version (
+                    version     INTEGER PRIMARY KEY,
+                    description TEXT NOT NULL,
+                    applied_at  TEXT NOT NULL
+                )",
+            )
+            .execute(pool)
+            .await?;
+
+            // record what a database from before versioning already has
+            for migration in MIGRATIONS.iter().filter(|m| m.version <= current) {
+                record_migration(pool, migration).await?;
+            }
+        }
+
+        // Not `begin`, the audit log it locks may not exist yet
+        for migration in pending {
+            let mut tx = pool.begin().await?;
+            for statement in self.statements(migration) {
+                sqlx::query(&statement).execute(&mut *tx).await?;
+            }
+            record_migration(&mut *tx, migration).await?;
+            tx.commit().await?;
+
+            info!("Database migrated to version {} ({})", migration.version, migration.description);
+        }
+
+        Ok(())
+    }
+}
+
+/// The repository for the engine `pool` connects to
+pub fn repository(pool: &Pool<Any>) -> Box<dyn Repository> {
+    let pool = pool.clone();
+    if pool.connect_options().database_url.scheme().starts_with("postgres") {
+        Box::new(PostgresRepository { pool })
+    } else {
+        Box::new(SqliteRepository { pool })
+    }
+}
+
+pub struct SqliteRepository {
+    pool: Pool<Any>,
+}
+
+#[async_trait]
+impl Repository for SqliteRepository {
+    fn pool(&self) -> &Pool<Any> {
+        &self.pool
+    }
+
+    /// `BEGIN IMMEDIATE` takes the database's write lock before anything is read
+    async fn begin(&self) -> sqlx::Result<Transaction<'static, Any>> {
+        self.pool.begin_with("BEGIN IMMEDIATE").await
+    }
+
+    async fn table_exists(&self, table: &str) -> sqlx::Result<bool> {
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = $1").bind(table).fetch_one(&self.pool).await?;
+        Ok(count > 0)
+    }
+
+    async fn legacy_version(&self) -> sqlx::Result<i64> {
+        if !self.table_exists("customers").await? {
+            return Ok(0);
+        }
+        if self.table_exists("keystore").await? {
+            return Ok(3);
+        }
+
+        let columns: Vec<String> = sqlx::query_scalar("SELECT name FROM pragma_table_info('applications')").fetch_all(&self.pool).await?;
+        Ok(if columns.iter().any(|c| c == "version_policy") { 2 } else { 1 })
+    }
+
+    fn statements(&self, migration: &Migration) -> Vec<String> {
+        migration.statements.iter().map(ToString::to_string).collect()
+    }
 }
 
-/// Create tables if they do not exist yet
-pub async fn initialize_schema(pool: &Pool<Sqlite>) -> sqlx::Result<()> {
-    // customers
-    sqlx::query(
-        r"
-        CREATE TABLE IF NOT EXISTS customers (
-            id            INTEGER PRIMARY KEY AUTOINCREMENT,
-            name          TEXT NOT NULL,
-            contact_email TEXT NOT NULL,
-            mobile        TEXT NOT NULL
-        )",
-    )
-    .execute(pool)
-    .await?;
-
-    // applications
-    sqlx::query(
-        r"
-        CREATE TABLE IF NOT EXISTS applications (
-            id                    INTEGER PRIMARY KEY AUTOINCREMENT,
-            name                  TEXT NOT NULL,
-            lic_public_key        TEXT NOT NULL,
-            lic_private_key       TEXT NOT NULL,
-            blocked_customer_ids  TEXT NOT NULL,
-            machine_id_key        TEXT NOT NULL,
-            info_public_key       TEXT NOT NULL,
-            info_private_key      TEXT NOT NULL,
-            feature1              TEXT,
-            feature2              TEXT,
-            feature3              TEXT,
-            feature4              TEXT,
-            feature5              TEXT
-        )",
-    )
-    .execute(pool)
-    .await?;
-
-    // licenses (placeholder)
-    sqlx::query(
-        r"
-        CREATE TABLE IF NOT EXISTS licenses (
-            id              INTEGER PRIMARY KEY AUTOINCREMENT,
-            hwid            TEXT NOT NULL,
-            support_years   INTEGER NOT NULL,
-            customer_id     INTEGER NOT NULL,
-            application_id  INTEGER NOT NULL,
-            issued_license  TEXT, 
-            FOREIGN KEY(customer_id) REFERENCES customers(id),
-            FOREIGN KEY(application_id) REFERENCES applications(id)
-        )",
-    )
-    .execute(pool)
-    .await?;
+pub struct PostgresRepository {
+    pool: Pool<Any>,
+}
 
+#[async_trait]
+impl Repository for PostgresRepository {
+    fn pool(&self) -> &Pool<Any> {
+        &self.pool
+    }
+
+    /// Other writers wait for the lock on `audit_log`, reads go on
+    async fn begin(&self) -> sqlx::Result<Transaction<'static, Any>> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("LOCK TABLE audit_log IN EXCLUSIVE MODE").execute(&mut *tx).await?;
+        Ok(tx)
+    }
+
+    async fn table_exists(&self, table: &str) -> sqlx::Result<bool> {
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM information_schema.tables WHERE table_schema = current_schema() AND table_name = $1").bind(table).fetch_one(&self.pool).await?;
+        Ok(count > 0)
+    }
+
+    /// PostgreSQL databases are versioned from the start
+    async fn legacy_version(&self) -> sqlx::Result<i64> {
+        Ok(0)
+    }
+
+    /// Written for SQLite, run with 64 bit integers and identity keys unless the migration has its own
+    fn statements(&self, migration: &Migration) -> Vec<String> {
+        match migration.postgres {
+            Some(statements) => statements.iter().map(ToString::to_string).collect(),
+            None => migration.statements.iter().map(|s| s.replace("INTEGER PRIMARY KEY AUTOINCREMENT", "BIGSERIAL PRIMARY KEY").replace("INTEGER", "BIGINT")).collect(),
+        }
+    }
+}
+
+async fn record_migration<'e, E: sqlx::Executor<'e, Database = Any>>(executor: E, migration: &Migration) -> sqlx::Result<()> {
+    sqlx::query("INSERT INTO schema_version (version, description, applied_at) VALUES ($1, $2, $3)")
+        .bind(migration.version)
+        .bind(migration.description)
+        .bind(chrono::Utc::now().to_rfc3339())
+        .execute(executor)
+        .await?;
     Ok(())
 }
 
+const CUSTOMER_COLUMNS: &str = "id, name, contact_email, mobile, archived_at";
+
+const APPLICATION_COLUMNS: &str = r"
+    id,
+    name,
+    lic_public_key,
+    lic_private_key,
+    blocked_customer_ids,
+    machine_id_key,
+    info_public_key,
+    info_private_key,
+    feature1,
+    feature2,
+    feature3,
+    feature4,
+    feature5,
+    version_policy,
+    hardware_changes_allowed,
+    archived_at
+";
+
+fn customer_from_row(row: &AnyRow) -> sqlx::Result<Customer> {
+    Ok(Customer {
+        id: row.try_get("id")?,
+        name: row.try_get("name")?,
+        contact_email: row.try_get("contact_email")?,
+        mobile: row.try_get("mobile")?,
+        archived_at: row.try_get("archived_at")?,
+    })
+}
+
+async fn application_from_row(pool: &Pool<Any>, row: &AnyRow) -> Result<Application, AdminError> {
+    let blocked_json: String = row.try_get("blocked_customer_ids")?;
+    let blocked_ids: Vec<u64> = serde_json::from_str(&blocked_json).unwrap_or_default();
+    let id: i64 = row.try_get("id")?;
+    Ok(Application {
+        id,
+        name: row.try_get("name")?,
+        lic_public_key: row.try_get("lic_public_key")?,
+        lic_private_key: keystore::open(pool, id, "lic_private_key", row.try_get("lic_private_key")?).await?,
+        blocked_customer_ids: blocked_ids,
+        machine_id_key: row.try_get("machine_id_key")?,
+        info_public_key: keystore::open(pool, id, "info_public_key", row.try_get("info_public_key")?).await?,
+        info_private_key: row.try_get("info_private_key")?,
+
+        feature1: row.try_get("feature1")?,
+        feature2: row.try_get("feature2")?,
+        feature3: row.try_get("feature3")?,
+        feature4: row.try_get("feature4")?,
+        feature5: row.try_get("feature5")?,
+        version_policy: row.try_get::<String, _>("version_policy")?.parse().unwrap_or_default(),
+        hardware_changes_allowed: row.try_get("hardware_changes_allowed")?,
+        archived_at: row.try_get("archived_at")?,
+    })
+}
+
 /// Fetch all customers from the database
-pub async fn fetch_customers(pool: &Pool<Sqlite>) -> sqlx::Result<Vec<Customer>> {
-    let rows = sqlx::query("SELECT id, name, contact_email, mobile FROM customers").fetch_all(pool).await?;
+pub async fn fetch_customers(pool: &Pool<Any>) -> sqlx::Result<Vec<Customer>> {
+    let rows = sqlx::query(&format!("SELECT {CUSTOMER_COLUMNS} FROM customers ORDER BY id")).fetch_all(pool).await?;
+    rows.iter().map(customer_from_row).collect()
+}
+
+/// Fetch all applications from the database, decrypting their secret keys
+pub async fn fetch_applications(pool: &Pool<Any>) -> Result<Vec<Application>, AdminError> {
+    let rows = sqlx::query(&format!("SELECT {APPLICATION_COLUMNS} FROM applications ORDER BY id")).fetch_all(pool).await?;
 
     let mut list = Vec::new();
-    for row in rows {
-        list.push(Customer {
-            id: row.try_get("id")?,
-            name: row.try_get("name")?,
-            contact_email: row.try_get("contact_email")?,
-            mobile: row.try_get("mobile")?,
-        });
+    for row in &rows {
+        list.push(application_from_row(pool, row).await?);
     }
     Ok(list)
 }
 
-/// Fetch all applications from the database
-pub async fn fetch_applications(pool: &Pool<Sqlite>) -> sqlx::Result<Vec<Application>> {
-    let rows = sqlx::query(
-        r"
-        SELECT 
-            id, 
-            name,
-            lic_public_key, 
-            lic_private_key, 
-            blocked_customer_ids,
-            machine_id_key, 
-            info_public_key, 
-            info_private_key,
-            feature1,
-            feature2,
-            feature3,
-            feature4,
-            feature5
-        FROM applications
-        ",
-    )
-    .fetch_all(pool)
-    .await?;
+/// Fetch one customer by ID
+pub async fn fetch_customer(pool: &Pool<Any>, id: i64) -> Result<Customer, AdminError> {
+    let row = sqlx::query(&format!("SELECT {CUSTOMER_COLUMNS} FROM customers WHERE id = $1")).bind(id).fetch_optional(pool).await?;
+    let row = row.ok_or_else(|| AdminError::NotFound(format!("customer with ID {id}")))?;
+    Ok(customer_from_row(&row)?)
+}
 
-    let mut list = Vec::new();
-    for row in rows {
-        let blocked_json: String = row.try_get("blocked_customer_ids")?;
-        let blocked_ids: Vec<u16> = serde_json::from_str(&blocked_json).unwrap_or_default();
-        list.push(Application {
-            id: row.try_get("id")?,
-            name: row.try_get("name")?,
-            lic_public_key: row.try_get("lic_public_key")?,
-            lic_private_key: row.try_get("lic_private_key")?,
-            blocked_customer_ids: blocked_ids,
-            machine_id_key: row.try_get("machine_id_key")?,
-            info_public_key: row.try_get("info_public_key")?,
-            info_private_key: row.try_get("info_private_key")?,
-
-            feature1: row.try_get("feature1")?,
-            feature2: row.try_get("feature2")?,
-            feature3: row.try_get("feature3")?,
-            feature4: row.try_get("feature4")?,
-            feature5: row.try_get("feature5")?,
-        });
-    }
-    Ok(list)
+/// Fetch one application by ID, decrypting only its secret keys
+pub async fn fetch_application(pool: &Pool<Any>, id: i64) -> Result<Application, AdminError> {
+    let row = sqlx::query(&format!("SELECT {APPLICATION_COLUMNS} FROM applications WHERE id = $1")).bind(id).fetch_optional(pool).await?;
+    let row = row.ok_or_else(|| AdminError::NotFound(format!("application with ID {id}")))?;
+    application_from_row(pool, &row).await
 }
@@ -0,0 +1,55 @@
+use rustlock_core::error::RustLockErrors;
+use thiserror::Error;
+
+/// Errors surfaced by admin commands, each maps onto a process exit code
+#[derive(Error, Debug)]
+pub enum AdminError {
+    #[error("Missing input '{0}' and no terminal is attached to prompt for it")]
+    MissingInput(String),
+    #[error("Invalid input: {0}")]
+    Invalid(String),
+    #[error("Not found: {0}")]
+    NotFound(String),
+    #[error("Database: {0}")]
+    Database(#[from] sqlx::Error),
+    #[error("IO: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Prompt: {0}")]
+    Prompt(#[from] dialoguer::Error),
+    #[error("License: {0}")]
+    License(#[from] RustLockErrors),
+    #[error("{0}")]
+    Other(String),
+}
+
+impl AdminError {
+    /// Exit code documented in the README, 2 matches clap's own usage errors
+    pub const fn exit_code(&self) -> i32 {
+        match self {
+            Self::Other(_) | Self::Prompt(_) => 1,
+            Self::MissingInput(_) => 2,
+            Self::Invalid(_) | Self::License(_) => 3,
+            Self::NotFound(_) => 4,
+            Self::Database(_) => 5,
+            Self::Io(_) => 6,
+        }
+    }
+}
+
+impl From<serde_json::Error> for AdminError {
+    fn from(e: serde_json::Error) -> Self {
+        Self::Invalid(e.to_string())
+    }
+}
+
+impl From<toml::de::Error> for AdminError {
+    fn from(e: toml::de::Error) -> Self {
+        Self::Invalid(e.to_string())
+    }
+}
+
+impl From<Box<dyn std::error::Error>> for AdminError {
+    fn from(e: Box<dyn std::error::Error>) -> Self {
+        Self::Other(e.to_string())
+    }
+}
@@ -0,0 +1,113 @@
+use std::io::IsTerminal;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use dialoguer::{Input, Select, theme::ColorfulTheme};
+use serde::{Serialize, de::DeserializeOwned};
+
+use crate::error::AdminError;
+
+/// Set by `serve`, requests must never wait on a prompt in the server's terminal
+static PROMPTS_DISABLED: AtomicBool = AtomicBool::new(false);
+
+/// Prompts are only shown when a user can answer them
+pub fn interactive() -> bool {
+    !PROMPTS_DISABLED.load(Ordering::Relaxed) && std::io::stdin().is_terminal() && std::io::stderr().is_terminal()
+}
+
+/// Treat the rest of the run as if no terminal was attached
+pub fn disable_prompts() {
+    PROMPTS_DISABLED.store(true, Ordering::Relaxed);
+}
+
+/// Fill every field the command line left empty from a JSON or TOML input file, flags win over the file.
+/// Flags that weren't given have to serialize as null or not at all, e.g. lists with `skip_serializing_if = "Vec::is_empty"`
+/// and switches with `skip_serializing_if = "std::ops::Not::not"`
+pub fn merge_input_file<T: Serialize + DeserializeOwned>(args: T, file: Option<&Path>) -> Result<T, AdminError> {
+    let Some(file) = file else {
+        return Ok(args);
+    };
+
+    let contents = std::fs::read_to_string(file)?;
+    let mut merged: serde_json::Value = match file.extension().and_then(|e| e.to_str()) {
+        Some("toml") => toml::from_str(&contents)?,
+        Some("json") => serde_json::from_str(&contents)?,
+        _ => return Err(AdminError::Invalid(format!("input file {} must be .json or .toml", file.display()))),
+    };
+
+    if let (serde_json::Value::Object(merged), serde_json::Value::Object(flags)) = (&mut merged, serde_json::to_value(args)?) {
+        for (key, value) in flags {
+            if !value.is_null() {
+                merged.insert(key, value);
+            }
+        }
+    }
+
+    Ok(serde_json::from_value(merged)?)
+}
+
+/// Rows of a CSV file with a header, or of a JSON array. Errors name the 1-based row
+pub fn read_rows<T: DeserializeOwned>(file: &Path) -> Result<Vec<T>, AdminError> {
+    match file.extension().and_then(|e| e.to_str()) {
+        Some("json") => Ok(serde_json::from_str(&std::fs::read_to_string(file)?)?),
+        Some("csv") => {
+            let mut reader = csv::ReaderBuilder::new().trim(csv::Trim::All).from_path(file).map_err(|e| AdminError::Invalid(format!("{}: {e}", file.display())))?;
+            reader.deserialize().enumerate().map(|(i, row)| row.map_err(|e| AdminError::Invalid(format!("row {}: {e}", i + 1)))).collect()
+        }
+        _ => Err(AdminError::Invalid(format!("{} must be .csv or .json", file.display()))),
+    }
+}
+
+/// Use the given value, otherwise prompt for it when a terminal is attached
+pub fn text(value: Option<String>, field: &str, prompt: impl FnOnce() -> dialoguer::Result<String>) -> Result<String, AdminError> {
+    match value {
+        Some(value) => Ok(value),
+        None if interactive() => Ok(prompt()?),
+        None => Err(AdminError::MissingInput(field.to_string())),
+    }
+}
+
+/// Like [`text`] but keeps `current` when nothing was given and no terminal is attached, used by the update commands
+pub fn text_or_current(value: Option<String>, current: &str, prompt: impl FnOnce() -> dialoguer::Result<String>) -> Result<String, AdminError> {
+    match value {
+        Some(value) => Ok(value),
+        None if interactive() => Ok(prompt()?),
+        None => Ok(current.to_string()),
+    }
+}
+
+/// Pick a record by id, or let the user select one from `items` when a terminal is attached
+pub fn select_id(value: Option<i64>, items: &[(i64, String)], field: &str, prompt: &str) -> Result<usize, AdminError> {
+    if items.is_empty() {
+        return Err(AdminError::NotFound(format!("no {field} records, please add one first")));
+    }
+
+    match value {
+        Some(id) => items.iter().position(|(item_id, _)| *item_id == id).ok_or_else(|| AdminError::NotFound(format!("{field} with ID {id}"))),
+        None if interactive() => {
+            let choices: Vec<String> = items.iter().map(|(id, name)| format!("ID {id} – {name}")).collect();
+            Ok(Select::with_theme(&ColorfulTheme::default()).with_prompt(prompt).default(0).items(&choices).interact()?)
+        }
+        None => Err(AdminError::MissingInput(field.to_string())),
+    }
+}
+
+/// Yes/No question. Without a terminal nothing is assumed, the command has to be run with `--yes`
+pub fn confirm(prompt: &str, default: bool) -> Result<bool, AdminError> {
+    if !interactive() {
+        return Err(AdminError::MissingInput("--yes".to_string()));
+    }
+
+    let ans = Select::with_theme(&ColorfulTheme::default()).with_prompt(prompt).default(usize::from(default)).items(&["No", "Yes"]).interact()?;
+    Ok(ans == 1)
+}
+
+/// Prompt for a value that may be left blank
+pub fn optional_text(theme: &ColorfulTheme, prompt: &str, initial: &str) -> dialoguer::Result<String> {
+    Input::with_theme(theme).with_prompt(prompt).with_initial_text(initial).allow_empty(true).interact_text()
+}
+
+/// Empty strings become `None` so the column becomes NULL
+pub fn non_empty(value: String) -> Option<String> {
+    if value.trim().is_empty() { None } else { Some(value) }
+}
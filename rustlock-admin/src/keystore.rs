@@ -0,0 +1,240 @@
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+
+use log::info;
+use sqlx::{Any, AnyConnection, Pool, Row};
+
+use crate::audit;
+use crate::crypto::{self, SecretKey};
+use crate::error::AdminError;
+
+/// Columns of `applications` that are encrypted with the master key.
+///
+/// NOTE the key names are swapped, `lic_private_key` mints licenses and `info_public_key` decrypts fingerprints
+pub const SECRET_COLUMNS: [&str; 2] = ["lic_private_key", "info_public_key"];
+
+/// Master passphrase when no terminal is attached
+pub const PASSPHRASE_ENV: &str = "RUSTLOCK_MASTER_PASSPHRASE";
+/// File holding the master passphrase, used before [`PASSPHRASE_ENV`]
+pub const KEY_FILE_ENV: &str = "RUSTLOCK_MASTER_KEY_FILE";
+/// New master passphrase for `change-passphrase` when no terminal is attached
+pub const NEW_PASSPHRASE_ENV: &str = "RUSTLOCK_NEW_MASTER_PASSPHRASE";
+
+/// Encrypted values carry this prefix, anything else is plaintext from before the migration
+const PREFIX: &str = "enc:v2:";
+
+/// Encrypted with the master key so a wrong passphrase is caught before anything is decrypted
+const VERIFIER: &[u8] = b"rustlock-keystore";
+
+/// Keys unlocked this session by keystore salt, a staged backup may have a keystore of its own
+static UNLOCKED: OnceLock<Mutex<HashMap<String, SecretKey>>> = OnceLock::new();
+
+/// The passphrase that unlocked the first keystore, tried before prompting again
+static PASSPHRASE: OnceLock<String> = OnceLock::new();
+
+struct Keystore {
+    salt: String,
+    verifier: String,
+}
+
+fn unlocked() -> &'static Mutex<HashMap<String, SecretKey>> {
+    UNLOCKED.get_or_init(Mutex::default)
+}
+
+fn remember(salt: &str, key: &SecretKey, passphrase: String) {
+    unlocked().lock().unwrap_or_else(std::sync::PoisonError::into_inner).insert(salt.to_string(), key.clone());
+    let _ = PASSPHRASE.set(passphrase);
+}
+
+/// Key file first, then the environment, then a prompt
+fn master_passphrase(new: bool) -> Result<String, AdminError> {
+    if let Ok(path) = std::env::var(KEY_FILE_ENV) {
+        let passphrase = std::fs::read_to_string(&path)?.trim().to_string();
+        if passphrase.is_empty() {
+            return Err(AdminError::Invalid(format!("key file {path} is empty")));
+        }
+        return Ok(passphrase);
+    }
+
+    crypto::passphrase(PASSPHRASE_ENV, if new { "New master passphrase" } else { "Master passphrase" }, new)
+}
+
+async fn load(pool: &Pool<Any>) -> Result<Option<Keystore>, AdminError> {
+    let row = sqlx::query("SELECT salt, verifier FROM keystore WHERE id = 1").fetch_optional(pool).await?;
+
+    Ok(match row {
+        Some(row) => Some(Keystore {
+            salt: row.try_get("salt")?,
+            verifier: row.try_get("verifier")?,
+        }),
+        None => None,
+    })
+}
+
+/// Derive a key from `passphrase` and check it against the keystore's verifier
+fn try_unlock(keystore: &Keystore, passphrase: &str) -> Result<Option<SecretKey>, AdminError> {
+    let salt = hex::decode(&keystore.salt).map_err(|e| AdminError::Invalid(format!("keystore salt: {e}")))?;
+    let verifier = hex::decode(&keystore.verifier).map_err(|e| AdminError::Invalid(format!("keystore verifier: {e}")))?;
+
+    let key = SecretKey::derive(passphrase, &salt)?;
+    Ok(key.open(&verifier, b"keystore").ok().filter(|plain| plain == VERIFIER).map(|_| key))
+}
+
+/// Master key of the database, unlocked once per session. `None` until the database has been migrated
+async fn key(pool: &Pool<Any>) -> Result<Option<SecretKey>, AdminError> {
+    let Some(keystore) = load(pool).await? else {
+        return Ok(None);
+    };
+
+    if let Some(key) = unlocked().lock().unwrap_or_else(std::sync::PoisonError::into_inner).get(&keystore.salt) {
+        return Ok(Some(key.clone()));
+    }
+
+    if let Some(passphrase) = PASSPHRASE.get()
+        && let Some(key) = try_unlock(&keystore, passphrase)?
+    {
+        remember(&keystore.salt, &key, passphrase.clone());
+        return Ok(Some(key));
+    }
+
+    let passphrase = master_passphrase(false)?;
+    let Some(key) = try_unlock(&keystore, &passphrase)? else {
+        return Err(AdminError::Invalid("wrong master passphrase".to_string()));
+    };
+    remember(&keystore.salt, &key, passphrase);
+    Ok(Some(key))
+}
+
+/// Unlock the master key now, so a prompt for the passphrase doesn't come up later
+pub async fn unlock(pool: &Pool<Any>) -> Result<(), AdminError> {
+    key(pool).await.map(|_| ())
+}
+
+fn is_encrypted(stored: &str) -> bool {
+    stored.starts_with(PREFIX)
+}
+
+/// Binds a secret to its row as well as its column, so it can't be swapped into another application
+fn associated_data(application_id: i64, column: &str) -> Vec<u8> {
+    format!("{column}:{application_id}").into_bytes()
+}
+
+fn encrypt_value(key: &SecretKey, application_id: i64, column: &str, value: &str) -> Result<String, AdminError> {
+    Ok(format!("{PREFIX}{}", hex::encode(key.seal(value.as_bytes(), &associated_data(application_id, column))?)))
+}
+
+fn decrypt_value(key: &SecretKey, application_id: i64, column: &str, stored: &str) -> Result<String, AdminError> {
+    let Some(sealed) = stored.strip_prefix(PREFIX) else {
+        return Ok(stored.to_string());
+    };
+
+    let sealed = hex::decode(sealed).map_err(|e| AdminError::Invalid(format!("{column}: {e}")))?;
+    String::from_utf8(key.open(&sealed, &associated_data(application_id, column))?).map_err(|e| AdminError::Invalid(format!("{column}: {e}")))
+}
+
+/// Unlock the master key, the first secret stored sets up the keystore. Call before a transaction that seals secrets
+pub async fn prepare(pool: &Pool<Any>) -> Result<(), AdminError> {
+    if key(pool).await?.is_none() {
+        create(pool).await?;
+    }
+    Ok(())
+}
+
+/// Encrypt a secret column value of application `application_id` for storage
+pub async fn seal(pool: &Pool<Any>, application_id: i64, column: &str, value: &str) -> Result<String, AdminError> {
+    let Some(key) = key(pool).await? else {
+        return Err(AdminError::Other("the keystore has not been set up".to_string()));
+    };
+    encrypt_value(&key, application_id, column, value)
+}
+
+/// Decrypt a stored secret column value of application `application_id`, plaintext values are returned as they are
+pub async fn open(pool: &Pool<Any>, application_id: i64, column: &str, stored: &str) -> Result<String, AdminError> {
+    if !is_encrypted(stored) {
+        return Ok(stored.to_string());
+    }
+
+    let Some(key) = key(pool).await? else {
+        return Err(AdminError::Invalid(format!("{column} is encrypted but the database has no keystore")));
+    };
+    decrypt_value(&key, application_id, column, stored)
+}
+
+/// Re-encrypt every secret column with `new_key`, plaintext values are encrypted on the way
+async fn reseal(conn: &mut AnyConnection, old_key: &SecretKey, new_key: &SecretKey) -> Result<(), AdminError> {
+    for column in SECRET_COLUMNS {
+        let rows: Vec<(i64, String)> = sqlx::query_as(&format!("SELECT id, {column} FROM applications")).fetch_all(&mut *conn).await?;
+
+        for (id, stored) in rows {
+            let value = decrypt_value(old_key, id, column, &stored)?;
+            sqlx::query(&format!("UPDATE applications SET {column} = $1 WHERE id = $2")).bind(encrypt_value(new_key, id, column, &value)?).bind(id).execute(&mut *conn).await?;
+        }
+    }
+    Ok(())
+}
+
+/// One-time migration run at startup: encrypt the secrets of databases from before the keystore
+pub async fn migrate(pool: &Pool<Any>) -> Result<(), AdminError> {
+    let applications: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM applications").fetch_one(pool).await?;
+
+    if applications > 0 && load(pool).await?.is_none() {
+        info!("Application keys are stored unencrypted, choose a master passphrase to encrypt them");
+        create(pool).await?;
+        info!("✅ Application keys encrypted");
+    }
+    Ok(())
+}
+
+/// Set up the keystore with a new passphrase and encrypt every plaintext secret in the same transaction
+async fn create(pool: &Pool<Any>) -> Result<SecretKey, AdminError> {
+    let passphrase = master_passphrase(true)?;
+    let salt = crypto::random_salt();
+    let key = SecretKey::derive(&passphrase, &salt)?;
+
+    let mut tx = crate::db::repository(pool).begin().await?;
+
+    sqlx::query("INSERT INTO keystore (id, salt, verifier) VALUES (1, $1, $2)")
+        .bind(hex::encode(salt))
+        .bind(hex::encode(key.seal(VERIFIER, b"keystore")?))
+        .execute(&mut *tx)
+        .await?;
+
+    // Nothing is encrypted yet, so the new key only ever sees plaintext here
+    reseal(&mut tx, &key, &key).await?;
+
+    audit::record(&mut tx, "set_passphrase", "keystore", None, None, None).await?;
+    tx.commit().await?;
+
+    remember(&hex::encode(salt), &key, passphrase);
+    Ok(key)
+}
+
+/// Re-encrypt every secret with a new master passphrase
+pub async fn change_passphrase(pool: &Pool<Any>) -> Result<(), AdminError> {
+    let Some(old_key) = key(pool).await? else {
+        create(pool).await?;
+        println!("✅ Master passphrase set");
+        return Ok(());
+    };
+
+    let passphrase = crypto::passphrase(NEW_PASSPHRASE_ENV, "New master passphrase", true)?;
+    let salt = crypto::random_salt();
+    let new_key = SecretKey::derive(&passphrase, &salt)?;
+
+    let mut tx = crate::db::repository(pool).begin().await?;
+
+    reseal(&mut tx, &old_key, &new_key).await?;
+
+    sqlx::query("UPDATE keystore SET salt = $1, verifier = $2 WHERE id = 1")
+        .bind(hex::encode(salt))
+        .bind(hex::encode(new_key.seal(VERIFIER, b"keystore")?))
+        .execute(&mut *tx)
+        .await?;
+
+    audit::record(&mut tx, "change_passphrase", "keystore", None, None, None).await?;
+    tx.commit().await?;
+
+    remember(&hex::encode(salt), &new_key, passphrase);
+    println!("✅ Master passphrase changed");
+    Ok(())
+}
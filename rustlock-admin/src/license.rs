@@ -1,191 +1,610 @@
-use std::error::Error;
+use std::path::PathBuf;
 
 use chrono::{Datelike, Utc};
+use clap::Args;
 use dialoguer::{Input, Select, theme::ColorfulTheme};
 use ecies::{decrypt, encrypt};
-use log::{error, info};
-use rustlock_core::{RustLock, license::License, sysinfo::SysInfo};
-use sqlx::{Pool, Row, Sqlite};
+use log::{error, info, warn};
+use rustlock_core::{
+    RustLock,
+    license::{License, VersionPolicy},
+    sysinfo::SysInfo,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::{Any, AnyConnection, Pool, Row};
+use uuid::Uuid;
 use version_compare::Version;
 
-#[allow(clippy::too_many_lines)]
-/// Interactive wizard to issue a license
-pub async fn issue_license_wizard(pool: &Pool<Sqlite>) -> Result<(), Box<dyn Error>> {
-    let theme = ColorfulTheme::default();
+use crate::applications::select_application;
+use crate::audit;
+use crate::customers::select_customer;
+use crate::db::{Application, Customer};
+use crate::error::AdminError;
+use crate::input;
+use crate::output::{self, OutputFormat};
+
+/// `licenses.status` of a license the shipped application should accept
+pub const STATUS_ACTIVE: &str = "active";
+/// `licenses.status` of a revoked license
+pub const STATUS_REVOKED: &str = "revoked";
+/// `licenses.status` of a license replaced by a renewal or reissue
+pub const STATUS_SUPERSEDED: &str = "superseded";
+
+/// Inputs for `issue`, missing values are prompted for when a terminal is attached
+#[derive(Args, Serialize, Deserialize, Default, Debug)]
+pub struct IssueLicenseArgs {
+    /// ID of the application to issue the license for
+    #[arg(long)]
+    pub application: Option<i64>,
+    /// ID of the customer to link the license to
+    #[arg(long)]
+    pub customer: Option<i64>,
+    /// Fingerprint string sent by the customer
+    #[arg(long)]
+    pub hwid: Option<String>,
+    /// Years of support from today
+    #[arg(long)]
+    pub support_years: Option<i32>,
+    /// License version (semver)
+    #[arg(long)]
+    pub version: Option<String>,
+    /// Highest licensed version, only used by the `semver_range` policy
+    #[arg(long)]
+    pub max_version: Option<String>,
+    /// Feature to enable, by name, repeat for each feature
+    #[arg(long = "feature")]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub features: Vec<String>,
+    /// Read the fields above from a JSON or TOML file
+    #[arg(long)]
+    #[serde(skip)]
+    pub input: Option<PathBuf>,
+    /// Output format of the issued license record
+    #[arg(long, value_enum, default_value_t)]
+    #[serde(skip)]
+    pub format: OutputFormat,
+}
 
-    // 1) Select an application
-    let apps = crate::db::fetch_applications(pool).await?;
-    if apps.is_empty() {
-        println!("⚠️  No applications found. Please add one first.");
-        return Ok(());
-    }
-    let app_choices: Vec<String> = apps.iter().map(|app| format!("ID {} – {}", app.id, app.name)).collect();
-    let app_selection = Select::with_theme(&theme).with_prompt("Select application to issue license for").default(0).items(&app_choices).interact().unwrap();
-    let chosen_app = &apps[app_selection];
+/// Inputs for `validate`
+#[derive(Args, Serialize, Deserialize, Default, Debug)]
+pub struct ValidateLicenseArgs {
+    /// ID of the application the license belongs to
+    #[arg(long)]
+    pub application: Option<i64>,
+    /// License string to validate
+    #[arg(long)]
+    pub license: Option<String>,
+    /// Application version to validate against
+    #[arg(long)]
+    pub version: Option<String>,
+    /// Read the fields above from a JSON or TOML file
+    #[arg(long)]
+    #[serde(skip)]
+    pub input: Option<PathBuf>,
+}
 
-    // 2) Select a customer
-    let customers = crate::db::fetch_customers(pool).await?;
-    if customers.is_empty() {
-        println!("⚠️  No customers found. Please add one first.");
-        return Ok(());
+/// Inputs for `show licenses`
+#[derive(Args, Default, Debug)]
+pub struct ShowLicensesArgs {
+    /// ID of the application to show licenses for
+    #[arg(long)]
+    pub application: Option<i64>,
+    /// ID of the customer to show licenses for
+    #[arg(long)]
+    pub customer: Option<i64>,
+    /// Output format
+    #[arg(long, value_enum, default_value_t)]
+    pub format: OutputFormat,
+}
+
+/// A row of the `licenses` table as printed by `show licenses`, details are empty for licenses that couldn't be decoded
+#[derive(Debug, Serialize, Default)]
+pub struct LicenseRecord {
+    pub id: i64,
+    pub application_id: i64,
+    pub customer_id: i64,
+    pub support_years: i32,
+    /// `active`, `revoked` or `superseded`
+    pub status: String,
+    pub revoked_at: Option<String>,
+    pub revoked_reason: Option<String>,
+    /// License this one renewed
+    pub renewed_from: Option<i64>,
+    /// License this one replaced after a hardware change
+    pub reissued_from: Option<i64>,
+    /// Hardware change reissues of this license and the licenses it replaced
+    pub reissue_count: i64,
+    pub license_uid: Option<String>,
+    pub licensed_name: Option<String>,
+    pub version_policy: Option<String>,
+    pub version: Option<String>,
+    pub min_version: Option<String>,
+    /// `YYYY-MM`
+    pub start: Option<String>,
+    /// `YYYY-MM`, the last month the license is valid
+    pub end: Option<String>,
+    /// Names of the enabled features
+    pub features: Vec<String>,
+    pub hostname: Option<String>,
+    pub os_name: Option<String>,
+    pub os_version: Option<String>,
+    pub cpu: Option<String>,
+    pub memory: Option<i64>,
+    pub mac_address: Option<String>,
+    pub issued_at: Option<String>,
+    pub hwid: String,
+    pub issued_license: String,
+}
+
+/// The license created by `issue`
+#[derive(Debug, Serialize, Default)]
+pub struct IssuedLicenseRecord {
+    pub id: i64,
+    pub application_id: i64,
+    pub customer_id: i64,
+    pub customer_name: String,
+    pub version_policy: String,
+    pub version: String,
+    pub min_version: Option<String>,
+    /// `YYYY-MM`
+    pub start: String,
+    /// `YYYY-MM`
+    pub end: String,
+    /// Names of the enabled features
+    pub features: Vec<String>,
+    pub hwid: String,
+    pub issued_license: String,
+}
+
+impl IssuedLicenseRecord {
+    pub fn new(app: &Application, customer: &Customer, hwid: &str, issued: &IssuedLicense) -> Self {
+        let lic = &issued.license;
+        let features: Vec<String> = app.feature_schema().features.into_iter().filter(|f| lic.feature(f.slot)).map(|f| f.name).collect();
+
+        Self {
+            id: issued.id,
+            application_id: app.id,
+            customer_id: customer.id,
+            customer_name: customer.name.clone(),
+            version_policy: lic.version_policy.to_string(),
+            version: lic.version.clone(),
+            min_version: input::non_empty(lic.min_version.clone()),
+            start: format!("{}-{:02}", lic.start_year, lic.start_month),
+            end: format!("{}-{:02}", lic.end_year, lic.end_month),
+            features,
+            hwid: hwid.to_string(),
+            issued_license: issued.issued_license.clone(),
+        }
     }
-    let cust_choices: Vec<String> = customers.iter().map(|c| format!("ID {} – {}", c.id, c.name)).collect();
-    let cust_selection = Select::with_theme(&theme).with_prompt("Select customer to link license to").default(0).items(&cust_choices).interact().unwrap();
-    let chosen_cust = &customers[cust_selection];
+}
 
-    // 3) HWID input
-    let hwid: String = Input::with_theme(&theme).with_prompt("Enter HWID string").interact_text().unwrap();
+/// Everything needed to issue one license, already resolved against the database
+#[derive(Debug)]
+pub struct IssueRequest {
+    pub hwid: String,
+    pub support_years: i32,
+    pub version: String,
+    pub max_version: Option<String>,
+    pub features: [bool; 5],
+    /// Last month of support (year, month), `support_years` from today when not set
+    pub end: Option<(i32, u32)>,
+}
 
-    // 4) Support years (default = 1)
-    let support_years: i32 = Input::with_theme(&theme).with_prompt("Support years").default(1).interact_text().unwrap();
+/// A license stored in the `licenses` table
+#[derive(Debug)]
+pub struct IssuedLicense {
+    pub id: i64,
+    pub license: License,
+    pub issued_license: String,
+}
 
-    let version: String = Input::with_theme(&theme)
-        .with_prompt("License version (semver, e.g., 1.0.3)")
-        .with_initial_text("1.0.0")
-        .validate_with(|input: &String| -> Result<(), &str> {
-            if Version::from(input).is_some() { Ok(()) } else { Err("Invalid version format; expected semver (e.g., 1.2.3)") }
-        })
-        .interact_text()?;
+fn validate_version(input: &str) -> Result<(), &'static str> {
+    if Version::from(input).is_some() { Ok(()) } else { Err("Invalid version format; expected semver (e.g., 1.2.3)") }
+}
 
-    // 5) For each non-null feature name on the application, ask Yes/No
-    //    to include that feature in this license.
-    let mut include_feature1 = false;
-    let mut include_feature2 = false;
-    let mut include_feature3 = false;
-    let mut include_feature4 = false;
-    let mut include_feature5 = false;
-
-    if let Some(feat1_name) = &chosen_app.feature1 {
-        let ans = Select::with_theme(&theme).with_prompt(format!("Include feature '{feat1_name}'?")).default(0).items(&["No", "Yes"]).interact().unwrap();
-        include_feature1 = ans == 1;
-    }
-    if let Some(feat2_name) = &chosen_app.feature2 {
-        let ans = Select::with_theme(&theme).with_prompt(format!("Include feature '{feat2_name}'?")).default(0).items(&["No", "Yes"]).interact().unwrap();
-        include_feature2 = ans == 1;
-    }
-    if let Some(feat3_name) = &chosen_app.feature3 {
-        let ans = Select::with_theme(&theme).with_prompt(format!("Include feature '{feat3_name}'?")).default(0).items(&["No", "Yes"]).interact().unwrap();
-        include_feature3 = ans == 1;
+/// Build and encrypt the license for `request`, without storing it
+pub fn build_license(app: &Application, customer: &Customer, request: &IssueRequest) -> Result<(License, SysInfo, String), AdminError> {
+    let Some(current_version) = Version::from(&request.version) else {
+        return Err(AdminError::Invalid(format!("invalid version {}, expected semver (e.g., 1.2.3)", request.version)));
+    };
+    if request.support_years < 0 {
+        return Err(AdminError::Invalid("support years must not be negative".to_string()));
     }
-    if let Some(feat4_name) = &chosen_app.feature4 {
-        let ans = Select::with_theme(&theme).with_prompt(format!("Include feature '{feat4_name}'?")).default(0).items(&["No", "Yes"]).interact().unwrap();
-        include_feature4 = ans == 1;
+    if app.archived_at.is_some() {
+        return Err(AdminError::Invalid(format!("application {} is archived, unarchive it to issue licenses", app.id)));
     }
-    if let Some(feat5_name) = &chosen_app.feature5 {
-        let ans = Select::with_theme(&theme).with_prompt(format!("Include feature '{feat5_name}'?")).default(0).items(&["No", "Yes"]).interact().unwrap();
-        include_feature5 = ans == 1;
+    if customer.archived_at.is_some() {
+        return Err(AdminError::Invalid(format!("customer {} is archived, unarchive it to issue licenses", customer.id)));
     }
 
-    let fingerprint = decode_hwinfo_from_string(&hwid, &chosen_app.info_public_key.clone()).unwrap();
-    println!();
-    info!("HW Info:\n{fingerprint:#?}");
-    println!();
+    let Some(fingerprint) = decode_hwinfo_from_string(&request.hwid, &app.info_public_key) else {
+        return Err(AdminError::Invalid(format!("HWID could not be decoded with the keys of application {}", app.id)));
+    };
 
     let mut lic = License::default();
 
-    let current_version = Version::from(&version).unwrap();
-    // set max version
-    lic.version = current_version.part(0).unwrap().to_string() + "." + &current_version.part(1).unwrap().to_string() + ".9999";
+    let major = current_version.part(0).map(|p| p.to_string()).unwrap_or_default();
+    let minor = current_version.part(1).map_or_else(|_| "0".to_string(), |p| p.to_string());
+
+    lic.version_policy = app.version_policy;
+    match app.version_policy {
+        VersionPolicy::MajorMinor => {
+            // set max version
+            lic.version = major + "." + &minor + ".9999";
+        }
+        VersionPolicy::SemverRange => {
+            let max_version = request.max_version.clone().unwrap_or_else(|| major + ".9999.9999");
+            match Version::from(&max_version) {
+                Some(max) if max >= current_version => {}
+                Some(_) => return Err(AdminError::Invalid(format!("maximum version {max_version} is lower than {}", request.version))),
+                None => return Err(AdminError::Invalid(format!("invalid maximum version {max_version}"))),
+            }
+            lic.min_version.clone_from(&request.version);
+            lic.version = max_version;
+        }
+        VersionPolicy::ReleaseDate => {
+            // any build released before support ends is covered, the version is informational
+            lic.version.clone_from(&request.version);
+        }
+    }
 
-    lic.name.clone_from(&chosen_cust.name);
+    lic.name.clone_from(&customer.name);
 
     let date = Utc::now();
 
-    lic.customer = chosen_cust.id;
+    lic.customer = customer.id.cast_unsigned();
     lic.start_month = date.month();
     lic.start_year = date.year();
 
-    lic.end_month = date.month();
-    lic.end_year = date.year() + support_years;
+    (lic.end_year, lic.end_month) = request.end.unwrap_or((date.year() + request.support_years, date.month()));
 
-    lic.c1 = fingerprint.o_hash;
-    lic.c2 = fingerprint.c_hash;
-    lic.c3 = fingerprint.s_hash;
-    lic.c4 = fingerprint.n_hash;
+    lic.c1.clone_from(&fingerprint.o_hash);
+    lic.c2.clone_from(&fingerprint.c_hash);
+    lic.c3.clone_from(&fingerprint.s_hash);
+    lic.c4.clone_from(&fingerprint.n_hash);
 
-    lic.f1 = include_feature1;
-    lic.f2 = include_feature2;
-    lic.f3 = include_feature3;
-    lic.f4 = include_feature4;
-    lic.f5 = include_feature5;
+    lic.id = Uuid::new_v4().to_string();
 
-    let lic_pk = hex::decode(chosen_app.lic_private_key.clone()).unwrap();
-    let msg = rmp_serde::to_vec(&lic).unwrap();
+    [lic.f1, lic.f2, lic.f3, lic.f4, lic.f5] = request.features;
 
-    let encrypted = encrypt(&lic_pk, &msg).unwrap();
+    let Ok(lic_pk) = hex::decode(&app.lic_private_key) else {
+        return Err(AdminError::Invalid(format!("license private key of application {} is not hex", app.id)));
+    };
+    let msg = rmp_serde::to_vec(&lic).map_err(|e| AdminError::Other(e.to_string()))?;
+
+    let encrypted = encrypt(&lic_pk, &msg).map_err(|e| AdminError::Other(e.to_string()))?;
     let encrypted_string = hex::encode_upper(encrypted);
 
-    println!();
-    info!("Generated License: {encrypted_string}");
-    println!();
+    // make sure the application can read back what was issued
+    let valid_lic = rustlock_core::read_license(&app.lic_public_key, &encrypted_string)?;
 
-    let lock = RustLock::new(chosen_app.lic_public_key.clone(), chosen_app.blocked_customer_ids.clone(), version, chosen_app.machine_id_key.clone(), chosen_app.info_private_key.clone())?;
+    Ok((valid_lic, fingerprint, encrypted_string))
+}
 
-    let valid_lic = lock.read_license(&encrypted_string)?;
+/// Pick a license with `status`, or use `--license` as given
+pub async fn select_license(pool: &Pool<Any>, license: Option<i64>, status: &str, prompt: &str) -> Result<i64, AdminError> {
+    if let Some(id) = license {
+        return Ok(id);
+    }
 
-    println!();
-    info!("License: {valid_lic:#?}");
-    println!();
+    let rows = sqlx::query(
+        r"
+        SELECT l.id, c.name, a.name AS application, l.hostname, l.end_date
+        FROM licenses l
+        JOIN customers c ON c.id = l.customer_id
+        JOIN applications a ON a.id = l.application_id
+        WHERE l.status = $1
+        ORDER BY l.id
+        ",
+    )
+    .bind(status)
+    .fetch_all(pool)
+    .await?;
+
+    let mut items = Vec::new();
+    for row in &rows {
+        let hostname: Option<String> = row.try_get("hostname")?;
+        let end: Option<String> = row.try_get("end_date")?;
+        let label = format!(
+            "{} – {} – {} – until {}",
+            row.try_get::<String, _>("application")?,
+            row.try_get::<String, _>("name")?,
+            hostname.as_deref().unwrap_or("-"),
+            end.as_deref().unwrap_or("-")
+        );
+        items.push((row.try_get("id")?, label));
+    }
 
+    let index = input::select_id(None, &items, "license", prompt)?;
+    Ok(items[index].0)
+}
+
+/// Issue a license and store it in the `licenses` table
+pub async fn issue_license(pool: &Pool<Any>, app: &Application, customer: &Customer, request: &IssueRequest) -> Result<IssuedLicense, AdminError> {
+    let (license, fingerprint, issued_license) = build_license(app, customer, request)?;
+
+    let mut tx = crate::db::repository(pool).begin().await?;
+    let id = insert_license(&mut tx, app, customer, request, &license, &fingerprint, &issued_license).await?;
+    audit::record(&mut tx, "issue", "license", Some(id), None, Some(audit::snapshot(&license)?)).await?;
+    tx.commit().await?;
+
+    Ok(IssuedLicense { id, license, issued_license })
+}
+
+/// Insert a license built by [`build_license`] with its details, the caller commits the transaction
+pub async fn insert_license(conn: &mut AnyConnection, app: &Application, customer: &Customer, request: &IssueRequest, license: &License, fingerprint: &SysInfo, issued_license: &str) -> sqlx::Result<i64> {
     // Insert into licenses
-    sqlx::query(
+    let id: i64 = sqlx::query_scalar(
         r"
         INSERT INTO licenses (
             hwid,
             support_years,
             customer_id,
             application_id,
-            issued_license
+            issued_license,
+            issued_at
         )
-        VALUES (?1, ?2, ?3, ?4, ?5)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        RETURNING id
         ",
     )
-    .bind(&hwid)
-    .bind(support_years)
-    .bind(chosen_cust.id)
-    .bind(chosen_app.id)
-    .bind(encrypted_string)
-    .execute(pool)
+    .bind(&request.hwid)
+    .bind(request.support_years)
+    .bind(customer.id)
+    .bind(app.id)
+    .bind(issued_license)
+    .bind(Utc::now().to_rfc3339())
+    .fetch_one(&mut *conn)
     .await?;
 
-    info!(
-        "Issued new license for app {} to customer {} (features: {}, {}, {}, {}, {})",
-        chosen_app.id, chosen_cust.id, include_feature1, include_feature2, include_feature3, include_feature4, include_feature5,
-    );
+    store_details(&mut *conn, id, license, fingerprint).await?;
+    Ok(id)
+}
+
+/// Write the decoded license and fingerprint into the structured columns of a license row
+async fn store_details<'e, E: sqlx::Executor<'e, Database = Any>>(executor: E, id: i64, lic: &License, info: &SysInfo) -> sqlx::Result<()> {
+    sqlx::query(
+        r"
+        UPDATE licenses
+        SET license_uid    = $1,
+            licensed_name  = $2,
+            version_policy = $3,
+            version        = $4,
+            min_version    = $5,
+            start_date     = $6,
+            end_date       = $7,
+            f1             = $8,
+            f2             = $9,
+            f3             = $10,
+            f4             = $11,
+            f5             = $12,
+            hostname       = $13,
+            os_name        = $14,
+            os_version     = $15,
+            cpu            = $16,
+            memory         = $17,
+            mac_address    = $18,
+            storage_name   = $19,
+            storage_type   = $20,
+            cpu_hash       = $21,
+            os_hash        = $22,
+            network_hash   = $23,
+            storage_hash   = $24
+        WHERE id = $25
+        ",
+    )
+    .bind(input::non_empty(lic.id.clone()))
+    .bind(&lic.name)
+    .bind(lic.version_policy.as_str())
+    .bind(&lic.version)
+    .bind(input::non_empty(lic.min_version.clone()))
+    .bind(format!("{}-{:02}", lic.start_year, lic.start_month))
+    .bind(format!("{}-{:02}", lic.end_year, lic.end_month))
+    // feature columns are integers, PostgreSQL won't store a bool in them
+    .bind(i64::from(lic.f1))
+    .bind(i64::from(lic.f2))
+    .bind(i64::from(lic.f3))
+    .bind(i64::from(lic.f4))
+    .bind(i64::from(lic.f5))
+    .bind(&info.hostname)
+    .bind(&info.name)
+    .bind(&info.version)
+    .bind(&info.cpu)
+    .bind(info.mem.cast_signed())
+    .bind(&info.net)
+    .bind(&info.storage_name)
+    .bind(&info.storage_type)
+    .bind(&info.c_hash)
+    .bind(&info.o_hash)
+    .bind(&info.n_hash)
+    .bind(&info.s_hash)
+    .bind(id)
+    .execute(executor)
+    .await?;
+    Ok(())
+}
+
+/// Decode licenses issued before their details were stored, run at startup
+pub async fn backfill_details(pool: &Pool<Any>) -> Result<(), AdminError> {
+    let pending: Vec<(i64, i64, String, String)> = sqlx::query_as("SELECT id, application_id, hwid, issued_license FROM licenses WHERE end_date IS NULL AND issued_license IS NOT NULL").fetch_all(pool).await?;
+    if pending.is_empty() {
+        return Ok(());
+    }
+
+    info!("Decoding {} license(s) issued before license details were stored", pending.len());
+    let apps = crate::db::fetch_applications(pool).await?;
+
+    let mut decoded = 0;
+    for (id, application_id, hwid, issued_license) in pending {
+        let app = apps.iter().find(|a| a.id == application_id);
+        let lic = app.and_then(|app| rustlock_core::read_license(&app.lic_public_key, &issued_license).ok());
+        let info = app.and_then(|app| decode_hwinfo_from_string(&hwid, &app.info_public_key));
+
+        if let (Some(lic), Some(info)) = (lic, info) {
+            store_details(pool, id, &lic, &info).await?;
+            decoded += 1;
+        } else {
+            warn!("License {id} could not be decoded with the keys of application {application_id}, its details stay empty");
+        }
+    }
+
+    info!("Stored the details of {decoded} license(s)");
+    Ok(())
+}
+
+/// Map feature names given on the command line onto the application's feature slots
+pub fn features_by_name(app: &Application, names: &[String]) -> Result<[bool; 5], AdminError> {
+    let schema = app.feature_schema();
+    let mut features = [false; 5];
+
+    for name in names {
+        let Some(slot) = schema.slot_of(name) else {
+            let known: Vec<&str> = schema.features.iter().map(|f| f.name.as_str()).collect();
+            return Err(AdminError::Invalid(format!("unknown feature '{name}' for application {}, expected one of: {}", app.id, known.join(", "))));
+        };
+        features[usize::from(slot - 1)] = true;
+    }
+
+    Ok(features)
+}
+
+/// Interactive wizard to issue a license
+pub async fn issue_license_wizard(pool: &Pool<Any>, args: IssueLicenseArgs) -> Result<(), AdminError> {
+    let file = args.input.clone();
+    let args = input::merge_input_file(args, file.as_deref())?;
+    let theme = ColorfulTheme::default();
+
+    // 1) Select an application
+    let apps = crate::db::fetch_applications(pool).await?;
+    let chosen_app = select_application(&apps, args.application, "Select application to issue license for")?;
+
+    // 2) Select a customer
+    let customers = crate::db::fetch_customers(pool).await?;
+    let chosen_cust = select_customer(&customers, args.customer, "Select customer to link license to")?;
+
+    // 3) HWID input
+    let hwid = input::text(args.hwid, "hwid", || Input::with_theme(&theme).with_prompt("Enter HWID string").interact_text())?;
+
+    // 4) Support years (default = 1)
+    let support_years = match args.support_years {
+        Some(years) => years,
+        None if input::interactive() => Input::with_theme(&theme).with_prompt("Support years").default(1).interact_text()?,
+        None => 1,
+    };
+
+    let version = input::text(args.version, "version", || {
+        Input::with_theme(&theme)
+            .with_prompt("License version (semver, e.g., 1.0.3)")
+            .with_initial_text("1.0.0")
+            .validate_with(|input: &String| validate_version(input))
+            .interact_text()
+    })?;
+
+    let max_version = match args.max_version {
+        Some(max) => Some(max),
+        None if chosen_app.version_policy == VersionPolicy::SemverRange && input::interactive() => {
+            let major = Version::from(&version).and_then(|v| v.part(0).ok()).map(|p| p.to_string()).unwrap_or_default();
+            Some(
+                Input::with_theme(&theme)
+                    .with_prompt("Maximum licensed version (semver)")
+                    .with_initial_text(major + ".9999.9999")
+                    .validate_with(|input: &String| validate_version(input))
+                    .interact_text()?,
+            )
+        }
+        None => None,
+    };
+
+    // 5) For each non-null feature name on the application, ask Yes/No
+    //    to include that feature in this license.
+    let features = if args.features.is_empty() && input::interactive() {
+        let mut features = [false; 5];
+        let names = [&chosen_app.feature1, &chosen_app.feature2, &chosen_app.feature3, &chosen_app.feature4, &chosen_app.feature5];
+        for (include, name) in features.iter_mut().zip(names) {
+            if let Some(name) = name {
+                let ans = Select::with_theme(&theme).with_prompt(format!("Include feature '{name}'?")).default(0).items(&["No", "Yes"]).interact()?;
+                *include = ans == 1;
+            }
+        }
+        features
+    } else {
+        features_by_name(chosen_app, &args.features)?
+    };
+
+    let format = args.format;
+    if format.is_table()
+        && let Some(fingerprint) = decode_hwinfo_from_string(&hwid, &chosen_app.info_public_key)
+    {
+        println!();
+        info!("HW Info:\n{fingerprint:#?}");
+        println!();
+    }
+
+    let request = IssueRequest {
+        hwid,
+        support_years,
+        version,
+        max_version,
+        features,
+        end: None,
+    };
+    let issued = issue_license(pool, chosen_app, chosen_cust, &request).await?;
+
+    info!("Issued new license {} for app {} to customer {} (features: {:?})", issued.id, chosen_app.id, chosen_cust.id, request.features);
+
+    if !format.is_table() {
+        return output::print_records(format, &[IssuedLicenseRecord::new(chosen_app, chosen_cust, &request.hwid, &issued)], |_| {});
+    }
+
+    println!();
+    info!("Generated License: {}", issued.issued_license);
+    println!();
+
+    println!();
+    info!("License: {:#?}", issued.license);
+    println!();
+
     println!("✅ License record created.");
     Ok(())
 }
 
 /// Interactive wizard to validate a license
-pub async fn validate_license_wizard(pool: &Pool<Sqlite>) -> Result<(), Box<dyn std::error::Error>> {
+pub async fn validate_license_wizard(pool: &Pool<Any>, args: ValidateLicenseArgs) -> Result<(), AdminError> {
+    let file = args.input.clone();
+    let args = input::merge_input_file(args, file.as_deref())?;
     let theme = ColorfulTheme::default();
 
     // 1) Select application context
     let apps = crate::db::fetch_applications(pool).await?;
-    if apps.is_empty() {
-        println!("⚠️  No applications found.");
-        return Ok(());
-    }
-    let app_choices: Vec<String> = apps.iter().map(|app| format!("ID {} – {}", app.id, app.name)).collect();
-    let app_selection = Select::with_theme(&theme).with_prompt("Select application context for validation").default(0).items(&app_choices).interact().unwrap();
-    let chosen_app = &apps[app_selection];
+    let chosen_app = select_application(&apps, args.application, "Select application context for validation")?;
 
     // 2) Enter license string
-    let lic_str: String = Input::with_theme(&theme).with_prompt("Paste license string to validate").interact_text().unwrap();
+    let lic_str = input::text(args.license, "license", || Input::with_theme(&theme).with_prompt("Paste license string to validate").interact_text())?;
 
-    let version_str: String = Input::with_theme(&theme).with_prompt("Enter app version validate").interact_text().unwrap();
+    let version_str = input::text(args.version, "version", || Input::with_theme(&theme).with_prompt("Enter app version validate").interact_text())?;
 
     let lock = RustLock::new(chosen_app.lic_public_key.clone(), chosen_app.blocked_customer_ids.clone(), version_str, chosen_app.machine_id_key.clone(), chosen_app.info_private_key.clone())?;
 
     match lock.read_license(&lic_str) {
         Ok(_) => println!("✅ License string is VALID but not Validated."),
-        Err(_) => println!("❌ License is INVALID."),
+        Err(e) => {
+            println!("❌ License is INVALID.");
+            return Err(e.into());
+        }
     }
 
     Ok(())
 }
 
-fn decode_hwinfo_from_string(input: &str, public_key: &str) -> Option<SysInfo> {
+/// The version `issue` was given for `lic`, and its maximum version for `semver_range`
+pub fn issued_version(lic: &License) -> (String, Option<String>) {
+    match lic.version_policy {
+        VersionPolicy::SemverRange => (lic.min_version.clone(), Some(lic.version.clone())),
+        VersionPolicy::MajorMinor | VersionPolicy::ReleaseDate => (lic.version.clone(), None),
+    }
+}
+
+pub fn decode_hwinfo_from_string(input: &str, public_key: &str) -> Option<SysInfo> {
     // Customer has private, we have public
     let Ok(sk) = hex::decode(public_key) else {
         error!("Failed to Decode Public Key");
@@ -205,76 +624,132 @@ fn decode_hwinfo_from_string(input: &str, public_key: &str) -> Option<SysInfo> {
     rmp_serde::from_read::<&[u8], SysInfo>(&*decrypted).ok()
 }
 
-/// Show all licenses for a selected application and customer.
-/// Since HWID and `issued_license` strings can be very long, each record is printed in full without a table.
-pub async fn show_licenses(pool: &Pool<Sqlite>) -> Result<(), Box<dyn Error>> {
-    let theme = ColorfulTheme::default();
-
-    // 1) Select an application
-    let apps = crate::db::fetch_applications(pool).await?;
-    if apps.is_empty() {
-        println!("⚠️  No applications found.");
-        return Ok(());
-    }
-    let app_choices: Vec<String> = apps.iter().map(|app| format!("ID {} – {}", app.id, app.name)).collect();
-    let app_selection = Select::with_theme(&theme).with_prompt("Select application to view licenses for").default(0).items(&app_choices).interact()?;
-    let chosen_app = &apps[app_selection];
-
-    // 2) Select a customer
-    let customers = crate::db::fetch_customers(pool).await?;
-    if customers.is_empty() {
-        println!("⚠️  No customers found.");
-        return Ok(());
-    }
-    let cust_choices: Vec<String> = customers.iter().map(|c| format!("ID {} – {}", c.id, c.name)).collect();
-    let cust_selection = Select::with_theme(&theme).with_prompt("Select customer to view licenses for").default(0).items(&cust_choices).interact()?;
-    let chosen_cust = &customers[cust_selection];
-
-    // 3) Query licenses for the chosen app/customer
+/// Licenses of `app`, of one customer when `customer` is set, oldest first
+pub async fn fetch_licenses(pool: &Pool<Any>, app: &Application, customer: Option<i64>) -> Result<Vec<LicenseRecord>, AdminError> {
     let rows = sqlx::query(
         r"
         SELECT
-          id,
-          hwid,
-          support_years,
-          issued_license
+          id, customer_id, hwid, support_years, issued_license, status, revoked_at, revoked_reason, renewed_from, reissued_from, reissue_count, license_uid, licensed_name,
+          version_policy, version, min_version, start_date, end_date,
+          f1, f2, f3, f4, f5,
+          hostname, os_name, os_version, cpu, memory, mac_address, issued_at
         FROM licenses
-        WHERE application_id = ?1
-          AND customer_id = ?2
+        WHERE application_id = $1
+          AND ($2 IS NULL OR customer_id = $2)
         ORDER BY id
         ",
     )
-    .bind(chosen_app.id)
-    .bind(chosen_cust.id)
+    .bind(app.id)
+    .bind(customer)
     .fetch_all(pool)
     .await?;
 
-    if rows.is_empty() {
-        println!("⚠️  No licenses found for application '{}' (ID {}) and customer '{}' (ID {}).", chosen_app.name, chosen_app.id, chosen_cust.name, chosen_cust.id);
-        return Ok(());
+    let schema = app.feature_schema();
+    let mut records = Vec::new();
+    for row in &rows {
+        let mut features = Vec::new();
+        for feature in &schema.features {
+            if row.try_get::<Option<i64>, _>(format!("f{}", feature.slot).as_str())?.is_some_and(|f| f != 0) {
+                features.push(feature.name.clone());
+            }
+        }
+
+        records.push(LicenseRecord {
+            id: row.try_get("id")?,
+            application_id: app.id,
+            customer_id: row.try_get("customer_id")?,
+            support_years: row.try_get("support_years")?,
+            status: row.try_get("status")?,
+            revoked_at: row.try_get("revoked_at")?,
+            revoked_reason: row.try_get("revoked_reason")?,
+            renewed_from: row.try_get("renewed_from")?,
+            reissued_from: row.try_get("reissued_from")?,
+            reissue_count: row.try_get("reissue_count")?,
+            license_uid: row.try_get("license_uid")?,
+            licensed_name: row.try_get("licensed_name")?,
+            version_policy: row.try_get("version_policy")?,
+            version: row.try_get("version")?,
+            min_version: row.try_get("min_version")?,
+            start: row.try_get("start_date")?,
+            end: row.try_get("end_date")?,
+            features,
+            hostname: row.try_get("hostname")?,
+            os_name: row.try_get("os_name")?,
+            os_version: row.try_get("os_version")?,
+            cpu: row.try_get("cpu")?,
+            memory: row.try_get("memory")?,
+            mac_address: row.try_get("mac_address")?,
+            issued_at: row.try_get("issued_at")?,
+            hwid: row.try_get("hwid")?,
+            issued_license: row.try_get::<Option<String>, _>("issued_license")?.unwrap_or_default(),
+        });
     }
 
-    println!();
-    println!("—— Licenses for App '{}' (ID {}) and Customer '{}' (ID {}) ——————————", chosen_app.name, chosen_app.id, chosen_cust.name, chosen_cust.id);
-    println!();
-    println!("────────────────────────────────────────────────────────────────");
+    Ok(records)
+}
 
-    // 4) Print each license record in full
-    for row in &rows {
-        let license_id: i64 = row.try_get("id")?;
-        let hwid: String = row.try_get("hwid")?;
-        let support_years: i32 = row.try_get("support_years")?;
-        let issued_license: String = row.try_get("issued_license")?;
+/// Show all licenses for a selected application and customer.
+/// Since HWID and `issued_license` strings can be very long, each record is printed in full without a table.
+pub async fn show_licenses(pool: &Pool<Any>, args: ShowLicensesArgs) -> Result<(), AdminError> {
+    // 1) Select an application
+    let apps = crate::db::fetch_applications(pool).await?;
+    let chosen_app = select_application(&apps, args.application, "Select application to view licenses for")?;
 
-        println!("License ID       : {license_id}");
-        println!("Support Years    : {support_years}");
+    // 2) Select a customer
+    let customers = crate::db::fetch_customers(pool).await?;
+    let chosen_cust = select_customer(&customers, args.customer, "Select customer to view licenses for")?;
+
+    // 3) Query licenses for the chosen app/customer
+    let records = fetch_licenses(pool, chosen_app, Some(chosen_cust.id)).await?;
+
+    if records.is_empty() && args.format.is_table() {
+        println!("⚠️  No licenses found for application '{}' (ID {}) and customer '{}' (ID {}).", chosen_app.name, chosen_app.id, chosen_cust.name, chosen_cust.id);
+        return Ok(());
+    }
+
+    output::print_records(args.format, &records, |records| {
         println!();
-        println!("HWID             : {hwid}");
+        println!("—— Licenses for App '{}' (ID {}) and Customer '{}' (ID {}) ——————————", chosen_app.name, chosen_app.id, chosen_cust.name, chosen_cust.id);
         println!();
-        println!("Issued License   : {issued_license}");
         println!("────────────────────────────────────────────────────────────────");
-    }
 
-    info!("Displayed {} license(s) for app {} and customer {}.", rows.len(), chosen_app.id, chosen_cust.id);
+        // 4) Print each license record in full
+        for record in records {
+            let or_unknown = |value: &Option<String>| value.clone().unwrap_or_else(|| "-".to_string());
+
+            println!("License ID       : {} {}", record.id, record.license_uid.as_deref().unwrap_or_default());
+            println!("Licensed To      : {}", or_unknown(&record.licensed_name));
+            match &record.revoked_at {
+                Some(revoked_at) => println!("Status           : {} on {} – {}", record.status, revoked_at.get(..10).unwrap_or(revoked_at), or_unknown(&record.revoked_reason)),
+                None => println!("Status           : {}", record.status),
+            }
+            if let Some(renewed_from) = record.renewed_from {
+                println!("Renewal Of       : License {renewed_from}");
+            }
+            match record.reissued_from {
+                Some(reissued_from) => println!("Reissue Of       : License {reissued_from} (reissue {})", record.reissue_count),
+                None if record.reissue_count > 0 => println!("Reissues         : {}", record.reissue_count),
+                None => {}
+            }
+            println!("Support Years    : {}", record.support_years);
+            println!("Valid            : {} to {}", or_unknown(&record.start), or_unknown(&record.end));
+            match &record.min_version {
+                Some(min_version) => println!("Versions         : {min_version} to {} ({})", or_unknown(&record.version), or_unknown(&record.version_policy)),
+                None => println!("Version          : {} ({})", or_unknown(&record.version), or_unknown(&record.version_policy)),
+            }
+            println!("Features         : {}", record.features.join(", "));
+            println!("Host             : {} – {} {}", or_unknown(&record.hostname), or_unknown(&record.os_name), record.os_version.as_deref().unwrap_or_default());
+            println!("CPU              : {}", or_unknown(&record.cpu));
+            println!("Memory           : {}", record.memory.map_or_else(|| "-".to_string(), |m| format!("{} MB", m / 1024 / 1024)));
+            println!("MAC Address      : {}", or_unknown(&record.mac_address));
+            println!();
+            println!("HWID             : {}", record.hwid);
+            println!();
+            println!("Issued License   : {}", record.issued_license);
+            println!("────────────────────────────────────────────────────────────────");
+        }
+    })?;
+
+    info!("Displayed {} license(s) for app {} and customer {}.", records.len(), chosen_app.id, chosen_cust.id);
     Ok(())
 }
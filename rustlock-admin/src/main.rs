@@ -2,31 +2,82 @@ use clap::{Parser, Subcommand};
 use colored::Colorize;
 use directories::ProjectDirs;
 use env_logger::Env;
-use log::error;
-use sqlx::SqlitePool;
+use log::{error, info, warn};
+use sqlx::AnyPool;
 use std::fs::{File, create_dir_all};
-use std::io::{self, Write};
-use std::path::Path;
-use std::{env, process};
-use walkdir::WalkDir;
-use zip::write::FileOptions;
+use std::path::PathBuf;
+use std::process;
 
+mod api;
 mod applications;
+mod audit;
+mod backup;
+mod batch;
+mod crypto;
 mod customers;
+mod dashboard;
 mod db;
+mod delivery;
+mod error;
+mod input;
+mod keystore;
 mod license;
+mod output;
+mod profile;
+mod records;
+mod reissue;
+mod renewal;
+mod reports;
+mod revocation;
+mod tui;
+
+use api::ServeArgs;
+use applications::{AddApplicationArgs, ConfigFormat, UpdateApplicationArgs};
+use audit::ShowAuditArgs;
+use backup::RestoreArgs;
+use batch::IssueBatchArgs;
+use customers::{AddCustomerArgs, ImportCustomersArgs, UpdateCustomerArgs};
+use dashboard::DashboardArgs;
+use delivery::DeliverArgs;
+use error::AdminError;
+use license::{IssueLicenseArgs, ShowLicensesArgs, ValidateLicenseArgs};
+use output::OutputFormat;
+use profile::{Location, ProfileAction};
+use records::{ArchiveArgs, DeleteArgs, Entity};
+use reissue::ReissueLicenseArgs;
+use renewal::RenewLicenseArgs;
+use reports::ExpiringArgs;
+use revocation::RevokeArgs;
 
 /// CLI definition
 #[derive(Parser)]
 #[command(name = "rustlock-admin")]
 #[command(about = "Rustlock Interactive license & customer manager", long_about = None,)]
 struct Cli {
+    /// Database file or `postgres://` URL to use instead of the active profile, also read from `RUSTLOCK_DB`
+    #[arg(long, global = true)]
+    db: Option<String>,
+    /// Profile to use instead of the active one, also read from `RUSTLOCK_PROFILE`
+    #[arg(long, global = true)]
+    profile: Option<String>,
     #[command(subcommand)]
     command: Commands,
 }
 
 #[derive(Subcommand)]
 enum Commands {
+    #[command(flatten)]
+    Database(Box<DatabaseCommand>),
+    /// List the database profiles and switch between them
+    Profile {
+        #[command(subcommand)]
+        action: ProfileAction,
+    },
+}
+
+/// Commands that open the database
+#[derive(Subcommand)]
+enum DatabaseCommand {
     /// Add a new record
     Add {
         #[command(subcommand)]
@@ -38,54 +89,207 @@ enum Commands {
         entity: ShowEntity,
     },
     /// Issue a new license
-    Issue,
-    /// Export database to a ZIP
-    Backup,
+    Issue(IssueLicenseArgs),
+    /// Issue a license for every row of a CSV or JSON file
+    IssueBatch(IssueBatchArgs),
+    /// Write a license delivery email to the outbox, and optionally send it over SMTP
+    Deliver(DeliverArgs),
+    /// Extend a license for the same machine and features, continuing from its end date
+    Renew(RenewLicenseArgs),
+    /// Replace a license after a hardware change, showing how the new HWID differs from the one on file
+    Reissue(ReissueLicenseArgs),
+    /// Export database to an encrypted backup, or verify one
+    Backup {
+        #[command(subcommand)]
+        action: Option<BackupAction>,
+    },
+    /// Replace the database with one from a backup ZIP
+    Restore(RestoreArgs),
     /// Validate a provided license string
-    Validate,
+    Validate(ValidateLicenseArgs),
+    /// Revoke a license, shipped applications refuse it once they load the exported revocation list
+    Revoke(RevokeArgs),
+    /// Reinstate a revoked license
+    Unrevoke(RevokeArgs),
     /// Update an existing record
     Update {
         #[command(subcommand)]
         entity: UpdateEntity,
     },
+    /// Hide a customer or application from pickers, its licenses stay valid
+    Archive {
+        #[command(subcommand)]
+        entity: ArchiveEntity,
+    },
+    /// Bring an archived customer or application back
+    Unarchive {
+        #[command(subcommand)]
+        entity: ArchiveEntity,
+    },
+    /// Delete a customer or application for good
+    Delete {
+        #[command(subcommand)]
+        entity: DeleteEntity,
+    },
+    /// Set or change the master passphrase that encrypts application keys
+    ChangePassphrase,
+    /// Export records and application details to a file
+    Export {
+        #[command(subcommand)]
+        entity: ExportEntity,
+    },
+    /// Serve customers, applications and licenses as a JSON API with bearer token authentication
+    Serve(ServeArgs),
+    /// Browse and issue licenses in a web browser, on localhost by default
+    Dashboard(DashboardArgs),
+    /// Browse applications, customers and licenses full screen, and issue, renew or revoke in place
+    Tui,
+    /// Import records from a file
+    Import {
+        #[command(subcommand)]
+        entity: ImportEntity,
+    },
+    /// Reports for following up with customers
+    Report {
+        #[command(subcommand)]
+        report: Report,
+    },
 }
 
 #[derive(Subcommand)]
 enum AddEntity {
-    /// Add a new customer (interactive)
-    Customer,
-    /// Add a new application (interactive)
-    Application,
+    /// Add a new customer
+    Customer(AddCustomerArgs),
+    /// Add a new application
+    Application(AddApplicationArgs),
+}
+
+#[derive(Subcommand)]
+enum BackupAction {
+    /// Check a backup's passphrase, manifest and database without restoring it
+    Verify {
+        /// Backup archive created by `backup`
+        archive: PathBuf,
+    },
 }
 
 #[derive(Subcommand)]
 enum ShowEntity {
     /// List all customers
-    Customers,
+    Customers {
+        /// Include archived customers
+        #[arg(long)]
+        all: bool,
+        /// Output format
+        #[arg(long, value_enum, default_value_t)]
+        format: OutputFormat,
+    },
     /// List all applications
     Applications {
         /// Show information about the application
         #[arg(long)]
         config: bool,
+        /// ID of the application to show the config for
+        #[arg(long, requires = "config")]
+        application: Option<i64>,
+        /// Include archived applications
+        #[arg(long, conflicts_with = "config")]
+        all: bool,
+        /// Output format
+        #[arg(long, value_enum, default_value_t)]
+        format: OutputFormat,
     },
-    Licenses,
+    /// List licenses of an application and customer
+    Licenses(ShowLicensesArgs),
+    /// Show the audit log of admin actions and check its hash chain
+    Audit(ShowAuditArgs),
 }
 
+#[allow(clippy::large_enum_variant)]
 #[derive(Subcommand)]
 enum UpdateEntity {
     /// Edit an existing customer’s fields
-    Customer,
-    Application,
+    Customer(UpdateCustomerArgs),
+    /// Edit an existing application’s fields
+    Application(UpdateApplicationArgs),
+}
+
+#[derive(Subcommand)]
+enum ArchiveEntity {
+    /// Archive or unarchive a customer
+    Customer(ArchiveArgs),
+    /// Archive or unarchive an application
+    Application(ArchiveArgs),
+}
+
+#[derive(Subcommand)]
+enum DeleteEntity {
+    /// Delete a customer
+    Customer(DeleteArgs),
+    /// Delete an application and its keys
+    Application(DeleteArgs),
+}
+
+#[derive(Subcommand)]
+enum ExportEntity {
+    /// Write an application's settings for `rustlock_core::config::AppConfig`
+    Config {
+        /// ID of the application to export
+        #[arg(long)]
+        application: Option<i64>,
+        /// File format
+        #[arg(long, value_enum, default_value_t)]
+        format: ConfigFormat,
+        /// Output file, named after the format when left out
+        #[arg(long)]
+        output: Option<PathBuf>,
+    },
+    /// Write every customer to a CSV or JSON file
+    Customers {
+        /// Output file, `.csv` or `.json`
+        #[arg(long, default_value = "rustlock-customers.csv")]
+        output: PathBuf,
+    },
+    /// Write an application's feature schema for `#[derive(LicenseFeatures)]`
+    Schema {
+        /// ID of the application to export
+        #[arg(long)]
+        application: Option<i64>,
+        /// Output file
+        #[arg(long, default_value = "rustlock-schema.json")]
+        output: PathBuf,
+    },
+    /// Write an application's revoked license IDs for `RustLock::with_revoked_licenses`
+    Revocations {
+        /// ID of the application to export
+        #[arg(long)]
+        application: Option<i64>,
+        /// Output file
+        #[arg(long, default_value = "rustlock-revocations.json")]
+        output: PathBuf,
+    },
+}
+
+#[derive(Subcommand)]
+enum Report {
+    /// Active licenses whose support ends soon, with the customer's contact details
+    Expiring(ExpiringArgs),
+}
+
+#[derive(Subcommand)]
+enum ImportEntity {
+    /// Add customers from a CSV or JSON file, matching existing ones by email
+    Customers(ImportCustomersArgs),
 }
 
-#[allow(clippy::too_many_lines)]
 #[tokio::main]
 async fn main() {
-    println!();
-    println!("{}", " =====================================".green().bold());
-    println!("{}", " |       RustLock Admin App          |".green().bold());
-    println!("{}", " =====================================".green().bold());
-    println!();
+    // The banner goes to stderr so JSON and CSV output can be piped
+    eprintln!();
+    eprintln!("{}", " =====================================".green().bold());
+    eprintln!("{}", " |       RustLock Admin App          |".green().bold());
+    eprintln!("{}", " =====================================".green().bold());
+    eprintln!();
 
     // Initialize logging (default to "info")
     env_logger::Builder::from_env(Env::default().default_filter_or("info,sqlx=INFO,rustlock_core=TRACE")).init();
@@ -99,153 +303,295 @@ async fn main() {
         process::exit(1);
     };
 
-    let data_dir = proj_dirs.data_dir();
-    if !data_dir.exists() {
-        if let Err(e) = create_dir_all(data_dir) {
-            error!("Failed to create data directory {}: {e}", data_dir.display());
-            process::exit(1);
+    // Profiles only touch the config file, no database is opened for them
+    match cli.command {
+        Commands::Profile { action } => {
+            if let Err(e) = profile::manage_profiles(proj_dirs.config_dir(), proj_dirs.data_dir(), action) {
+                fail("Profile command failed", &e);
+            }
+        }
+        Commands::Database(command) => {
+            let (pool, location) = open_database(cli.db, cli.profile, &proj_dirs).await;
+            run(*command, pool, &location).await;
         }
     }
+}
 
-    // Construct a SQLite URL. Sqlx expects "sqlite://<absolute_path>"
-    let db_path = data_dir.join("rustlock.db");
-    let db_url = format!("sqlite://{}", db_path.display());
-
-    if !db_path.exists() {
-        if let Err(e) = File::create(&db_path) {
-            error!("Failed to create SQLite file {}: {e}", db_path.display());
-            process::exit(1);
+/// Select the database, create it when needed and bring it up to date
+async fn open_database(db: Option<String>, profile: Option<String>, proj_dirs: &ProjectDirs) -> (AnyPool, Location) {
+    let location = match Location::resolve(db, profile, proj_dirs.config_dir(), proj_dirs.data_dir()) {
+        Ok(location) => location,
+        Err(e) => fail("Failed to select the database", &e),
+    };
+    let data_dir = location.data_dir.as_path();
+    if (location.is_default() || location.db_path.is_none())
+        && !data_dir.exists()
+        && let Err(e) = create_dir_all(data_dir)
+    {
+        fail(&format!("Failed to create data directory {}", data_dir.display()), &e.into());
+    }
+    if !location.is_default() {
+        // A mistyped share or an unmounted drive must not end up as a new empty database
+        if location.db_path.is_some() && !data_dir.is_dir() {
+            fail("Failed to select the database", &AdminError::NotFound(format!("directory {}", data_dir.display())));
         }
+        info!("Using database {}{}", location.describe(), location.profile.as_ref().map(|p| format!(" of profile {p}")).unwrap_or_default());
     }
 
-    // Connect via SQLx
-    let pool = match SqlitePool::connect(&db_url).await {
+    if let Some(db_path) = &location.db_path
+        && !db_path.exists()
+        && let Err(e) = File::create(db_path)
+    {
+        fail(&format!("Failed to create SQLite file {}", db_path.display()), &e.into());
+    }
+
+    // Connect via SQLx, the URL scheme picks SQLite or PostgreSQL
+    sqlx::any::install_default_drivers();
+    let pool = match AnyPool::connect(&location.url).await {
         Ok(p) => p,
         Err(e) => {
-            error!("Failed to open SQLite via SQLx at {}: {e}", db_path.display());
-            process::exit(1);
+            fail(&format!("Failed to open the database at {}", location.describe()), &e.into());
         }
     };
 
-    // Ensure tables exist
-    if let Err(e) = db::initialize_schema(&pool).await {
-        error!("Failed to initialize database schema: {e}");
-        process::exit(1);
+    // Bring the schema up to date
+    if let Err(e) = upgrade_schema(&pool, &location).await {
+        fail("Failed to migrate the database schema", &e);
     }
 
-    // Dispatch on subcommands
-    match cli.command {
-        Commands::Add { entity } => match entity {
-            AddEntity::Customer => {
-                if let Err(e) = customers::add_customer_wizard(&pool).await {
-                    error!("Error in add-customer flow: {e}");
-                    process::exit(1);
+    // Encrypt application keys of databases from before the keystore
+    if let Err(e) = keystore::migrate(&pool).await {
+        fail("Failed to encrypt application keys", &e);
+    }
+
+    // Fill in the details of licenses issued before they were stored
+    if let Err(e) = license::backfill_details(&pool).await {
+        fail("Failed to decode existing licenses", &e);
+    }
+
+    (pool, location)
+}
+
+/// Dispatch on subcommands
+#[allow(clippy::too_many_lines)]
+async fn run(command: DatabaseCommand, pool: AnyPool, location: &Location) {
+    let data_dir = location.data_dir.as_path();
+    match command {
+        DatabaseCommand::Add { entity } => match entity {
+            AddEntity::Customer(args) => {
+                if let Err(e) = customers::add_customer_wizard(&pool, args).await {
+                    fail("Error in add-customer flow", &e);
                 }
             }
-            AddEntity::Application => {
-                if let Err(e) = applications::add_application_wizard(&pool).await {
-                    error!("Error in add-application flow: {e}");
-                    process::exit(1);
+            AddEntity::Application(args) => {
+                if let Err(e) = applications::add_application_wizard(&pool, args).await {
+                    fail("Error in add-application flow", &e);
                 }
             }
         },
-        Commands::Show { entity } => match entity {
-            ShowEntity::Customers => {
-                if let Err(e) = customers::show_customers(&pool).await {
-                    error!("Failed to show customers: {e}");
-                    process::exit(1);
+        DatabaseCommand::Show { entity } => match entity {
+            ShowEntity::Customers { all, format } => {
+                if let Err(e) = customers::show_customers(&pool, format, all).await {
+                    fail("Failed to show customers", &e);
                 }
             }
-            ShowEntity::Applications { config } => {
+            ShowEntity::Applications { config, application, all, format } => {
                 if config {
-                    if let Err(e) = applications::show_application_config(&pool).await {
-                        error!("Failed to show applications config: {e}");
-                        process::exit(1);
+                    if let Err(e) = applications::show_application_config(&pool, application, format).await {
+                        fail("Failed to show applications config", &e);
                     }
-                } else if let Err(e) = applications::show_applications(&pool).await {
-                    error!("Failed to show applications: {e}");
-                    process::exit(1);
+                } else if let Err(e) = applications::show_applications(&pool, format, all).await {
+                    fail("Failed to show applications", &e);
+                }
+            }
+            ShowEntity::Licenses(args) => {
+                if let Err(e) = license::show_licenses(&pool, args).await {
+                    fail("Failed to show licenses", &e);
                 }
             }
-            ShowEntity::Licenses => {
-                if let Err(e) = license::show_licenses(&pool).await {
-                    error!("Failed to show licenses: {e}");
-                    process::exit(1);
+            ShowEntity::Audit(args) => {
+                if let Err(e) = audit::show_audit(&pool, args).await {
+                    fail("Failed to show the audit log", &e);
                 }
             }
         },
-        Commands::Issue => {
-            if let Err(e) = license::issue_license_wizard(&pool).await {
-                error!("Error in issue-license flow: {e}");
-                process::exit(1);
+        DatabaseCommand::Issue(args) => {
+            if let Err(e) = license::issue_license_wizard(&pool, args).await {
+                fail("Error in issue-license flow", &e);
+            }
+        }
+        DatabaseCommand::Renew(args) => {
+            if let Err(e) = renewal::renew_license_wizard(&pool, args).await {
+                fail("Error in renew-license flow", &e);
+            }
+        }
+        DatabaseCommand::Deliver(args) => {
+            if let Err(e) = delivery::deliver_license(&pool, data_dir, args).await {
+                fail("Error in deliver-license flow", &e);
             }
         }
-        Commands::Backup => {
-            if let Err(e) = backup_database(data_dir) {
-                error!("Backup failed: {e}");
-                process::exit(1);
+        DatabaseCommand::Reissue(args) => {
+            if let Err(e) = reissue::reissue_license_wizard(&pool, args).await {
+                fail("Error in reissue-license flow", &e);
             }
         }
-        Commands::Validate => {
-            if let Err(e) = license::validate_license_wizard(&pool).await {
-                error!("Error in validate-license flow: {e}");
-                process::exit(1);
+        DatabaseCommand::IssueBatch(args) => {
+            if let Err(e) = batch::issue_batch(&pool, args).await {
+                fail("Batch issue failed", &e);
             }
         }
-        Commands::Update { entity } => match entity {
-            UpdateEntity::Customer => {
-                if let Err(e) = customers::update_customer_wizard(&pool).await {
-                    error!("Error in update-customer flow: {e}");
-                    process::exit(1);
+        DatabaseCommand::Backup { action } => match action {
+            None => {
+                if let Err(e) = backup::backup_database(location) {
+                    fail("Backup failed", &e);
                 }
             }
-            UpdateEntity::Application => {
-                if let Err(e) = applications::update_application_wizard(&pool).await {
-                    error!("Error in update-application flow: {e}");
-                    process::exit(1);
+            Some(BackupAction::Verify { archive }) => {
+                if let Err(e) = backup::verify_backup(location, &archive).await {
+                    fail("Backup verification failed", &e);
+                }
+            }
+        },
+        DatabaseCommand::Restore(args) => {
+            if let Err(e) = backup::restore_database(pool, location, args).await {
+                fail("Restore failed", &e);
+            }
+        }
+        DatabaseCommand::Validate(args) => {
+            if let Err(e) = license::validate_license_wizard(&pool, args).await {
+                fail("Error in validate-license flow", &e);
+            }
+        }
+        DatabaseCommand::Revoke(args) => {
+            if let Err(e) = revocation::revoke_license_wizard(&pool, args).await {
+                fail("Error in revoke-license flow", &e);
+            }
+        }
+        DatabaseCommand::Unrevoke(args) => {
+            if let Err(e) = revocation::unrevoke_license_wizard(&pool, args).await {
+                fail("Error in unrevoke-license flow", &e);
+            }
+        }
+        DatabaseCommand::Update { entity } => match entity {
+            UpdateEntity::Customer(args) => {
+                if let Err(e) = customers::update_customer_wizard(&pool, args).await {
+                    fail("Error in update-customer flow", &e);
+                }
+            }
+            UpdateEntity::Application(args) => {
+                if let Err(e) = applications::update_application_wizard(&pool, args).await {
+                    fail("Error in update-application flow", &e);
+                }
+            }
+        },
+        DatabaseCommand::Archive { entity } => {
+            let (entity, args) = match entity {
+                ArchiveEntity::Customer(args) => (Entity::Customer, args),
+                ArchiveEntity::Application(args) => (Entity::Application, args),
+            };
+            if let Err(e) = records::archive(&pool, entity, args).await {
+                fail("Archive failed", &e);
+            }
+        }
+        DatabaseCommand::Unarchive { entity } => {
+            let (entity, args) = match entity {
+                ArchiveEntity::Customer(args) => (Entity::Customer, args),
+                ArchiveEntity::Application(args) => (Entity::Application, args),
+            };
+            if let Err(e) = records::unarchive(&pool, entity, args).await {
+                fail("Unarchive failed", &e);
+            }
+        }
+        DatabaseCommand::Delete { entity } => {
+            let (entity, args) = match entity {
+                DeleteEntity::Customer(args) => (Entity::Customer, args),
+                DeleteEntity::Application(args) => (Entity::Application, args),
+            };
+            if let Err(e) = records::delete(&pool, entity, args).await {
+                fail("Delete failed", &e);
+            }
+        }
+        DatabaseCommand::ChangePassphrase => {
+            if let Err(e) = keystore::change_passphrase(&pool).await {
+                fail("Failed to change the master passphrase", &e);
+            }
+        }
+        DatabaseCommand::Export { entity } => match entity {
+            ExportEntity::Config { application, format, output } => {
+                if let Err(e) = applications::export_application_config(&pool, application, format, output).await {
+                    fail("Failed to export application config", &e);
+                }
+            }
+            ExportEntity::Customers { output } => {
+                if let Err(e) = customers::export_customers(&pool, &output).await {
+                    fail("Failed to export customers", &e);
+                }
+            }
+            ExportEntity::Schema { application, output } => {
+                if let Err(e) = applications::export_application_schema(&pool, application, &output).await {
+                    fail("Failed to export application schema", &e);
+                }
+            }
+            ExportEntity::Revocations { application, output } => {
+                if let Err(e) = revocation::export_revocations(&pool, application, &output).await {
+                    fail("Failed to export revocations", &e);
+                }
+            }
+        },
+        DatabaseCommand::Import { entity } => match entity {
+            ImportEntity::Customers(args) => {
+                if let Err(e) = customers::import_customers(&pool, args).await {
+                    fail("Customer import failed", &e);
+                }
+            }
+        },
+        DatabaseCommand::Serve(args) => {
+            if let Err(e) = api::serve(pool, args).await {
+                fail("API server failed", &e);
+            }
+        }
+        DatabaseCommand::Dashboard(args) => {
+            if let Err(e) = dashboard::dashboard(pool, args).await {
+                fail("Dashboard failed", &e);
+            }
+        }
+        DatabaseCommand::Tui => {
+            if let Err(e) = tui::tui(&pool).await {
+                fail("Terminal UI failed", &e);
+            }
+        }
+        DatabaseCommand::Report { report } => match report {
+            Report::Expiring(args) => {
+                if let Err(e) = reports::report_expiring(&pool, args).await {
+                    fail("Failed to report expiring licenses", &e);
                 }
             }
         },
     }
 }
 
-/// Copy the `SQLite` database file (and anything else in `data_dir`) into a ZIP
-fn backup_database(data_dir: &Path) -> io::Result<()> {
-    // Create a timestamped backup ZIP in the current working directory
-    let timestamp = chrono::Utc::now().format("%Y%m%d_%H%M%S");
-    let backup_file_name = format!("rustlock-backup-{timestamp}.zip");
-    let backup_path = env::current_dir()?.join(&backup_file_name);
-
-    // Create the ZIP file (this can fail with an io::Error)
-    let file = File::create(&backup_path)?;
-    let mut zip = zip::ZipWriter::new(file);
-
-    let options = FileOptions::default().compression_method(zip::CompressionMethod::Deflated).unix_permissions(0o600);
-
-    // Recursively walk data_dir and add every file
-    for entry in WalkDir::new(data_dir) {
-        let entry = entry.map_err(io::Error::other)?;
-        let path = entry.path();
-        if path.is_file() {
-            // Derive a relative path inside the ZIP
-            let rel_path = path.strip_prefix(data_dir).unwrap();
+/// Run pending migrations, backing up databases that already hold data first
+async fn upgrade_schema(pool: &AnyPool, location: &Location) -> Result<(), AdminError> {
+    let repository = db::repository(pool);
+    let pending = repository.pending_migrations().await?;
+    let current = repository.schema_version().await?;
 
-            // Read file contents into a buffer
-            let mut f = File::open(path)?;
-            let mut buffer = Vec::new();
-            io::copy(&mut f, &mut buffer)?;
-
-            // Start a new file entry in the ZIP
-            zip.start_file(rel_path.to_string_lossy(), options).map_err(io::Error::other)?;
-
-            // Write the file’s bytes into the ZIP
-            zip.write_all(&buffer)?;
+    if !pending.is_empty() && current > 0 {
+        info!("Database schema is at version {current}, migrating to {}", db::LATEST_VERSION);
+        if location.db_path.is_none() {
+            // every migration runs in its own transaction, a failed one leaves the schema as it was
+            warn!("No pre-migration backup is taken for PostgreSQL, take one with pg_dump beforehand if needed");
+            return repository.migrate().await;
         }
+        let backup_path = backup::pre_migration_backup(location, current)?;
+        info!("Pre-migration backup written to {}", backup_path.display());
     }
 
-    // Finish writing the ZIP (returns a zip::result::ZipError if something went wrong)
-    zip.finish().map_err(io::Error::other)?;
+    repository.migrate().await
+}
 
-    println!("✅ Backup created at: {}", backup_path.display());
-    Ok(())
+/// Log the error and exit with the status code matching its kind
+fn fail(context: &str, e: &AdminError) -> ! {
+    error!("{context}: {e}");
+    process::exit(e.exit_code());
 }
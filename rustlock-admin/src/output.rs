@@ -0,0 +1,86 @@
+use std::io::Write;
+use std::path::Path;
+
+use clap::ValueEnum;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::error::AdminError;
+
+/// Joins list fields such as features in CSV cells, `issue-batch` splits its `features` column on it
+pub const CSV_LIST_SEPARATOR: &str = ";";
+
+/// How `show` commands and `issue` print their records
+#[derive(ValueEnum, Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Human readable table
+    #[default]
+    Table,
+    /// JSON array of records
+    Json,
+    /// CSV with a header row
+    Csv,
+}
+
+impl OutputFormat {
+    /// Progress and confirmation messages are only printed alongside tables
+    pub fn is_table(self) -> bool {
+        self == Self::Table
+    }
+}
+
+/// Write `records` to a `.json` or `.csv` file, chosen by its extension
+pub fn write_records<T: Serialize + Default>(path: &Path, records: &[T]) -> Result<(), AdminError> {
+    match path.extension().and_then(|e| e.to_str()) {
+        Some("json") => std::fs::write(path, serde_json::to_string_pretty(records)?)?,
+        Some("csv") => {
+            let file = std::fs::File::create(path).map_err(|e| AdminError::Other(format!("{}: {e}", path.display())))?;
+            write_csv(file, records)?;
+        }
+        _ => return Err(AdminError::Invalid(format!("{} must be .csv or .json", path.display()))),
+    }
+
+    Ok(())
+}
+
+/// Print `records` as JSON or CSV, or hand them to `table` for the human readable layout
+pub fn print_records<T: Serialize + Default>(format: OutputFormat, records: &[T], table: impl FnOnce(&[T])) -> Result<(), AdminError> {
+    match format {
+        OutputFormat::Table => table(records),
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(records)?),
+        OutputFormat::Csv => write_csv(std::io::stdout(), records)?,
+    }
+
+    Ok(())
+}
+
+/// CSV with a header row taken from the field names. Lists are joined with [`CSV_LIST_SEPARATOR`] and `null` is left empty.
+///
+/// The header comes from `T::default()`, so a file without records still says what it would have held
+pub fn write_csv<T: Serialize + Default>(out: impl Write, records: &[T]) -> Result<(), AdminError> {
+    let mut writer = csv::Writer::from_writer(out);
+
+    writer.write_record(fields(&T::default())?.keys()).map_err(|e| AdminError::Other(e.to_string()))?;
+    for record in records {
+        writer.write_record(fields(record)?.values().map(csv_cell)).map_err(|e| AdminError::Other(e.to_string()))?;
+    }
+    writer.flush()?;
+
+    Ok(())
+}
+
+fn fields<T: Serialize>(record: &T) -> Result<serde_json::Map<String, Value>, AdminError> {
+    match serde_json::to_value(record)? {
+        Value::Object(fields) => Ok(fields),
+        _ => Err(AdminError::Other("only records with named fields can be written as CSV".to_string())),
+    }
+}
+
+fn csv_cell(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        Value::Array(items) => items.iter().map(csv_cell).collect::<Vec<_>>().join(CSV_LIST_SEPARATOR),
+        other => other.to_string(),
+    }
+}
@@ -0,0 +1,269 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use clap::Subcommand;
+use log::info;
+use serde::{Deserialize, Serialize};
+
+use crate::backup::DB_FILE;
+use crate::error::AdminError;
+use crate::output::{self, OutputFormat};
+
+/// Database file or PostgreSQL URL used instead of the active profile, `--db` takes precedence
+pub const DB_ENV: &str = "RUSTLOCK_DB";
+/// Profile used instead of the active one, `--profile` takes precedence
+pub const PROFILE_ENV: &str = "RUSTLOCK_PROFILE";
+/// Name of the profiles file in the config directory
+pub const PROFILES_FILE: &str = "profiles.toml";
+/// The database in the data directory, used when no other profile is active
+pub const DEFAULT_PROFILE: &str = "default";
+
+#[derive(Subcommand)]
+pub enum ProfileAction {
+    /// List the profiles and the database each one uses
+    List {
+        /// Output format
+        #[arg(long, value_enum, default_value_t)]
+        format: OutputFormat,
+    },
+    /// Add a profile for a database file or PostgreSQL URL, a file is created on first use
+    Add {
+        name: String,
+        /// Database file of the profile, e.g. on a network share, or a `postgres://` URL
+        #[arg(long)]
+        db: String,
+    },
+    /// Make a profile the active one, `default` goes back to the data directory
+    Use { name: String },
+    /// Remove a profile, its database is left in place
+    Remove { name: String },
+}
+
+/// A named database location
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Profile {
+    /// Path of a SQLite file or a `postgres://` URL
+    pub db: String,
+}
+
+/// Contents of `profiles.toml`
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Profiles {
+    /// Profile used when neither `--profile` nor `RUSTLOCK_PROFILE` is given, the default profile when not set
+    pub active: Option<String>,
+    #[serde(default)]
+    pub profiles: BTreeMap<String, Profile>,
+}
+
+impl Profiles {
+    /// Read `profiles.toml` from `config_dir`, no profiles when it doesn't exist yet
+    pub fn load(config_dir: &Path) -> Result<Self, AdminError> {
+        let path = config_dir.join(PROFILES_FILE);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        toml::from_str(&std::fs::read_to_string(&path)?).map_err(|e| AdminError::Invalid(format!("{}: {e}", path.display())))
+    }
+
+    fn save(&self, config_dir: &Path) -> Result<(), AdminError> {
+        std::fs::create_dir_all(config_dir)?;
+        let contents = toml::to_string_pretty(self).map_err(|e| AdminError::Other(e.to_string()))?;
+        std::fs::write(config_dir.join(PROFILES_FILE), contents)?;
+        Ok(())
+    }
+
+    fn db_of(&self, name: &str, data_dir: &Path) -> Result<String, AdminError> {
+        if name == DEFAULT_PROFILE {
+            return Ok(data_dir.join(DB_FILE).display().to_string());
+        }
+        match self.profiles.get(name) {
+            Some(profile) => Ok(profile.db.clone()),
+            None => Err(AdminError::NotFound(format!("profile '{name}', see `profile list`"))),
+        }
+    }
+}
+
+/// Whether `db` names a PostgreSQL database rather than a SQLite file
+pub fn is_postgres_url(db: &str) -> bool {
+    db.starts_with("postgres://") || db.starts_with("postgresql://")
+}
+
+/// `db` with the password of a URL masked, for logs and listings
+pub fn redact(db: &str) -> String {
+    if !is_postgres_url(db) {
+        return db.to_string();
+    }
+    let Some((scheme, rest)) = db.split_once("://") else {
+        return db.to_string();
+    };
+    match rest.split_once('@') {
+        Some((user, host)) if user.contains(':') => format!("{scheme}://{}:***@{host}", user.split(':').next().unwrap_or_default()),
+        _ => db.to_string(),
+    }
+}
+
+/// Where the database of this run lives
+#[derive(Debug)]
+pub struct Location {
+    /// Profile the database belongs to, `None` when it was given with `--db` or `RUSTLOCK_DB`
+    pub profile: Option<String>,
+    /// URL the pool connects to
+    pub url: String,
+    /// The SQLite file, `None` on PostgreSQL
+    pub db_path: Option<PathBuf>,
+    /// Directory of the database, it holds the delivery templates, migration backups and restore staging.
+    /// The default data directory on PostgreSQL.
+    pub data_dir: PathBuf,
+}
+
+impl Location {
+    fn new(profile: Option<String>, db: String, default_data_dir: &Path) -> Self {
+        if is_postgres_url(&db) {
+            return Self {
+                profile,
+                url: db,
+                db_path: None,
+                data_dir: default_data_dir.to_path_buf(),
+            };
+        }
+
+        let db_path = PathBuf::from(db.strip_prefix("sqlite://").or_else(|| db.strip_prefix("sqlite:")).unwrap_or(&db));
+        let data_dir = db_path.parent().map_or_else(|| PathBuf::from("."), Path::to_path_buf);
+        Self {
+            profile,
+            url: format!("sqlite://{}", db_path.display()),
+            db_path: Some(db_path),
+            data_dir,
+        }
+    }
+
+    /// Pick the database: `--db`, `--profile`, `RUSTLOCK_DB`, `RUSTLOCK_PROFILE`, the active profile, then the data directory
+    pub fn resolve(db: Option<String>, profile: Option<String>, config_dir: &Path, data_dir: &Path) -> Result<Self, AdminError> {
+        if let Some(db) = db {
+            return Ok(Self::new(None, db, data_dir));
+        }
+
+        let profile = match profile {
+            Some(profile) => Some(profile),
+            None => {
+                if let Ok(db) = std::env::var(DB_ENV) {
+                    return Ok(Self::new(None, db, data_dir));
+                }
+                std::env::var(PROFILE_ENV).ok()
+            }
+        };
+
+        let profiles = Profiles::load(config_dir)?;
+        let name = profile.or_else(|| profiles.active.clone()).unwrap_or_else(|| DEFAULT_PROFILE.to_string());
+        Ok(Self::new(Some(name.clone()), profiles.db_of(&name, data_dir)?, data_dir))
+    }
+
+    /// The database as shown to the user, the file or the URL without its password
+    pub fn describe(&self) -> String {
+        match &self.db_path {
+            Some(path) => path.display().to_string(),
+            None => redact(&self.url),
+        }
+    }
+
+    /// The SQLite file, or an error naming `what` can't be done on PostgreSQL
+    pub fn sqlite_file(&self, what: &str) -> Result<&Path, AdminError> {
+        self.db_path.as_deref().ok_or_else(|| AdminError::Invalid(format!("{what} works on SQLite databases only, use pg_dump and pg_restore for PostgreSQL")))
+    }
+
+    /// Whether this is the database in the data directory
+    pub fn is_default(&self) -> bool {
+        self.profile.as_deref() == Some(DEFAULT_PROFILE)
+    }
+}
+
+/// A profile as printed by `profile list`
+#[derive(Debug, Serialize, Default)]
+pub struct ProfileRecord {
+    pub name: String,
+    pub db: String,
+    pub active: bool,
+}
+
+fn validate_name(name: &str) -> Result<(), AdminError> {
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        return Err(AdminError::Invalid(format!("profile name '{name}' may only contain letters, digits, '-' and '_'")));
+    }
+    if name == DEFAULT_PROFILE {
+        return Err(AdminError::Invalid(format!("'{DEFAULT_PROFILE}' is the data directory and can't be redefined")));
+    }
+    Ok(())
+}
+
+/// List, add, switch or remove profiles in `profiles.toml`
+pub fn manage_profiles(config_dir: &Path, data_dir: &Path, action: ProfileAction) -> Result<(), AdminError> {
+    let mut profiles = Profiles::load(config_dir)?;
+    let active = profiles.active.clone().unwrap_or_else(|| DEFAULT_PROFILE.to_string());
+
+    match action {
+        ProfileAction::List { format } => {
+            let mut records = vec![ProfileRecord {
+                name: DEFAULT_PROFILE.to_string(),
+                db: data_dir.join(DB_FILE).display().to_string(),
+                active: active == DEFAULT_PROFILE,
+            }];
+            records.extend(profiles.profiles.iter().map(|(name, profile)| ProfileRecord {
+                name: name.clone(),
+                db: redact(&profile.db),
+                active: *name == active,
+            }));
+
+            output::print_records(format, &records, |records| {
+                println!("{}", "-".repeat(100));
+                println!("  {:<20} | Database", "Profile");
+                println!("{}", "-".repeat(100));
+                for r in records {
+                    println!("{} {:<20} | {}", if r.active { "*" } else { " " }, r.name, r.db);
+                }
+                println!();
+                println!("Profiles are stored in {}", config_dir.join(PROFILES_FILE).display());
+            })?;
+        }
+        ProfileAction::Add { name, db } => {
+            validate_name(&name)?;
+            if profiles.profiles.contains_key(&name) {
+                return Err(AdminError::Invalid(format!("profile '{name}' already exists, remove it first")));
+            }
+            let db = if is_postgres_url(&db) {
+                db
+            } else {
+                // the profile must point at the same file whichever directory the command runs in
+                let path = std::path::absolute(&db)?;
+                if !path.parent().is_some_and(Path::is_dir) {
+                    return Err(AdminError::NotFound(format!("directory of {}", path.display())));
+                }
+                path.display().to_string()
+            };
+
+            info!("Profile {name} uses {}", redact(&db));
+            profiles.profiles.insert(name.clone(), Profile { db });
+            profiles.save(config_dir)?;
+            println!("✅ Profile {name} added, switch to it with `profile use {name}`.");
+        }
+        ProfileAction::Use { name } => {
+            let db = profiles.db_of(&name, data_dir)?;
+            profiles.active = (name != DEFAULT_PROFILE).then(|| name.clone());
+            profiles.save(config_dir)?;
+            println!("✅ Profile {name} is active, commands use {}.", redact(&db));
+        }
+        ProfileAction::Remove { name } => {
+            validate_name(&name)?;
+            if profiles.profiles.remove(&name).is_none() {
+                return Err(AdminError::NotFound(format!("profile '{name}'")));
+            }
+            if active == name {
+                profiles.active = None;
+                info!("Profile {name} was active, the {DEFAULT_PROFILE} profile is used from now on");
+            }
+            profiles.save(config_dir)?;
+            println!("✅ Profile {name} removed, its database was left in place.");
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,193 @@
+use clap::Args;
+use log::{info, warn};
+use serde_json::{Value, json};
+use sqlx::{Any, Pool, Row};
+
+use crate::audit;
+use crate::error::AdminError;
+use crate::input;
+use crate::license::STATUS_REVOKED;
+
+/// Inputs for `archive` and `unarchive`
+#[derive(Args, Debug)]
+pub struct ArchiveArgs {
+    /// ID of the record, prompted for when a terminal is attached
+    #[arg(long)]
+    pub id: Option<i64>,
+}
+
+/// Inputs for `delete`
+#[derive(Args, Debug)]
+pub struct DeleteArgs {
+    /// ID of the record, prompted for when a terminal is attached
+    #[arg(long)]
+    pub id: Option<i64>,
+    /// Also delete the licenses that reference the record, refused while any of them is revoked
+    #[arg(long)]
+    pub cascade: bool,
+    /// Delete without asking for confirmation, required when no terminal is attached
+    #[arg(long)]
+    pub yes: bool,
+}
+
+/// Records that can be archived and deleted
+#[derive(Clone, Copy, Debug)]
+pub enum Entity {
+    Customer,
+    Application,
+}
+
+impl Entity {
+    /// Name used in messages and the audit log
+    fn name(self) -> &'static str {
+        match self {
+            Self::Customer => "customer",
+            Self::Application => "application",
+        }
+    }
+
+    fn table(self) -> &'static str {
+        match self {
+            Self::Customer => "customers",
+            Self::Application => "applications",
+        }
+    }
+
+    /// Column of `licenses` that references the record
+    fn license_column(self) -> &'static str {
+        match self {
+            Self::Customer => "customer_id",
+            Self::Application => "application_id",
+        }
+    }
+}
+
+/// Audit snapshot of the record, which also checks it exists
+async fn snapshot(pool: &Pool<Any>, entity: Entity, id: i64) -> Result<(Value, bool), AdminError> {
+    let record = match entity {
+        Entity::Customer => crate::db::fetch_customers(pool).await?.into_iter().find(|c| c.id == id).map(|c| (audit::snapshot(&c), c.archived_at.is_some())),
+        Entity::Application => crate::db::fetch_applications(pool).await?.into_iter().find(|a| a.id == id).map(|a| (audit::snapshot(&a), a.archived_at.is_some())),
+    };
+
+    match record {
+        Some((snapshot, archived)) => Ok((snapshot?, archived)),
+        None => Err(AdminError::NotFound(format!("{} with ID {id}", entity.name()))),
+    }
+}
+
+/// Use `id`, or prompt for a record. `archived` limits the choices to archived or active records
+async fn select(pool: &Pool<Any>, entity: Entity, id: Option<i64>, archived: Option<bool>, prompt: &str) -> Result<i64, AdminError> {
+    if let Some(id) = id {
+        return Ok(id);
+    }
+
+    let rows = sqlx::query(&format!("SELECT id, name, archived_at FROM {} ORDER BY id", entity.table())).fetch_all(pool).await?;
+
+    let mut items = Vec::new();
+    for row in &rows {
+        let is_archived = row.try_get::<Option<String>, _>("archived_at")?.is_some();
+        if archived.is_none_or(|archived| archived == is_archived) {
+            items.push((row.try_get("id")?, row.try_get("name")?));
+        }
+    }
+
+    let index = input::select_id(None, &items, entity.name(), prompt)?;
+    Ok(items[index].0)
+}
+
+/// Hide a record from pickers and stop new licenses being issued for it, its licenses stay valid
+pub async fn archive(pool: &Pool<Any>, entity: Entity, args: ArchiveArgs) -> Result<(), AdminError> {
+    let id = select(pool, entity, args.id, Some(false), &format!("Select a {} to archive", entity.name())).await?;
+
+    let (before, archived) = snapshot(pool, entity, id).await?;
+    if archived {
+        return Err(AdminError::Invalid(format!("{} {id} is already archived", entity.name())));
+    }
+
+    let archived_at = chrono::Utc::now().to_rfc3339();
+    let mut after = before.clone();
+    after["archived_at"] = json!(archived_at);
+
+    let mut tx = crate::db::repository(pool).begin().await?;
+    sqlx::query(&format!("UPDATE {} SET archived_at = $1 WHERE id = $2", entity.table())).bind(&archived_at).bind(id).execute(&mut *tx).await?;
+    audit::record(&mut tx, "archive", entity.name(), Some(id), Some(before), Some(after)).await?;
+    tx.commit().await?;
+
+    info!("✅ Archived {} {id}.", entity.name());
+    Ok(())
+}
+
+/// Bring an archived record back into the pickers
+pub async fn unarchive(pool: &Pool<Any>, entity: Entity, args: ArchiveArgs) -> Result<(), AdminError> {
+    let id = select(pool, entity, args.id, Some(true), &format!("Select a {} to unarchive", entity.name())).await?;
+
+    let (before, archived) = snapshot(pool, entity, id).await?;
+    if !archived {
+        return Err(AdminError::Invalid(format!("{} {id} isn't archived", entity.name())));
+    }
+
+    let mut after = before.clone();
+    after["archived_at"] = Value::Null;
+
+    let mut tx = crate::db::repository(pool).begin().await?;
+    sqlx::query(&format!("UPDATE {} SET archived_at = NULL WHERE id = $1", entity.table())).bind(id).execute(&mut *tx).await?;
+    audit::record(&mut tx, "unarchive", entity.name(), Some(id), Some(before), Some(after)).await?;
+    tx.commit().await?;
+
+    info!("✅ Unarchived {} {id}.", entity.name());
+    Ok(())
+}
+
+/// Delete a record for good. Records referenced by licenses are refused unless `--cascade` deletes those licenses too,
+/// and revoked licenses are never deleted so they stay in the exported revocation list
+pub async fn delete(pool: &Pool<Any>, entity: Entity, args: DeleteArgs) -> Result<(), AdminError> {
+    let id = select(pool, entity, args.id, None, &format!("Select a {} to delete", entity.name())).await?;
+    let (mut before, _) = snapshot(pool, entity, id).await?;
+
+    let rows: Vec<(i64, String)> = sqlx::query_as(&format!("SELECT id, status FROM licenses WHERE {} = $1 ORDER BY id", entity.license_column())).bind(id).fetch_all(pool).await?;
+    if !rows.is_empty() && !args.cascade {
+        return Err(AdminError::Invalid(format!("{} {id} is referenced by {} license(s), archive it instead or pass --cascade to delete the licenses too", entity.name(), rows.len())));
+    }
+
+    let revoked = rows.iter().filter(|(_, status)| status == STATUS_REVOKED).count();
+    if revoked > 0 {
+        return Err(AdminError::Invalid(format!("{} {id} has {revoked} revoked license(s) that must stay in the revocation list, archive it instead", entity.name())));
+    }
+    let licenses: Vec<i64> = rows.into_iter().map(|(id, _)| id).collect();
+
+    if !args.yes {
+        if !input::interactive() {
+            return Err(AdminError::MissingInput("--yes".to_string()));
+        }
+        let prompt = if licenses.is_empty() {
+            format!("Delete {} {id}? This can't be undone", entity.name())
+        } else {
+            format!("Delete {} {id} and its {} license(s)? This can't be undone", entity.name(), licenses.len())
+        };
+        if !input::confirm(&prompt, false)? {
+            println!("Delete cancelled, nothing was changed.");
+            return Ok(());
+        }
+    }
+
+    let mut tx = crate::db::repository(pool).begin().await?;
+    // A license revoked since the check above is left in place and the delete aborted
+    let deleted = sqlx::query(&format!("DELETE FROM licenses WHERE {} = $1 AND status <> $2", entity.license_column())).bind(id).bind(STATUS_REVOKED).execute(&mut *tx).await?;
+    if deleted.rows_affected() != licenses.len() as u64 {
+        return Err(AdminError::Invalid(format!("the licenses of {} {id} changed while deleting it, nothing was changed", entity.name())));
+    }
+    sqlx::query(&format!("DELETE FROM {} WHERE id = $1", entity.table())).bind(id).execute(&mut *tx).await?;
+
+    if !licenses.is_empty() {
+        before["deleted_licenses"] = json!(licenses);
+    }
+    audit::record(&mut tx, "delete", entity.name(), Some(id), Some(before), None).await?;
+    tx.commit().await?;
+
+    if !licenses.is_empty() {
+        warn!("Deleted {} license(s) of {} {id}, they can no longer be renewed or looked up", licenses.len(), entity.name());
+    }
+
+    info!("✅ Deleted {} {id}.", entity.name());
+    Ok(())
+}
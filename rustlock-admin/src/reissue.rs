@@ -0,0 +1,238 @@
+use std::path::PathBuf;
+
+use clap::Args;
+use dialoguer::{Input, theme::ColorfulTheme};
+use log::{info, warn};
+use rustlock_core::sysinfo::SysInfo;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::{Any, Pool, Row};
+
+use crate::audit;
+use crate::error::AdminError;
+use crate::input;
+use crate::license::{self, IssueRequest, IssuedLicense, IssuedLicenseRecord, STATUS_ACTIVE, STATUS_SUPERSEDED, decode_hwinfo_from_string, issued_version};
+use crate::output::{self, OutputFormat};
+
+/// Inputs for `reissue`, missing values are prompted for when a terminal is attached
+#[derive(Args, Serialize, Deserialize, Default, Debug)]
+pub struct ReissueLicenseArgs {
+    /// ID of the license whose machine changed
+    #[arg(long)]
+    pub license: Option<i64>,
+    /// New HWID generated on the changed machine
+    #[arg(long)]
+    pub hwid: Option<String>,
+    /// Reissue even when more components changed than the application allows
+    #[arg(long)]
+    #[serde(skip)]
+    pub approve: bool,
+    /// Only show the fingerprint diff, nothing is issued
+    #[arg(long)]
+    #[serde(skip)]
+    pub dry_run: bool,
+    /// Read the fields above from a JSON or TOML file
+    #[arg(long)]
+    #[serde(skip)]
+    pub input: Option<PathBuf>,
+    /// Output format of the issued license record
+    #[arg(long, value_enum, default_value_t)]
+    #[serde(skip)]
+    pub format: OutputFormat,
+}
+
+/// One line of the fingerprint diff
+#[derive(Debug, Serialize)]
+pub struct ComponentDiff {
+    pub component: &'static str,
+    pub stored: String,
+    pub new: String,
+    /// Only the lock hashes decide whether a license still validates
+    pub lock: bool,
+}
+
+impl ComponentDiff {
+    fn changed(&self) -> bool {
+        self.stored != self.new
+    }
+}
+
+/// Compare the readable fields and the four lock hashes of two fingerprints
+pub fn diff_fingerprints(stored: &SysInfo, new: &SysInfo) -> Vec<ComponentDiff> {
+    let field = |component, stored: &str, new: &str, lock| ComponentDiff {
+        component,
+        stored: stored.to_string(),
+        new: new.to_string(),
+        lock,
+    };
+    let memory = |info: &SysInfo| format!("{} MB", info.mem / 1024 / 1024);
+
+    vec![
+        field("Hostname", &stored.hostname, &new.hostname, false),
+        field("OS", &format!("{} {}", stored.name, stored.version), &format!("{} {}", new.name, new.version), false),
+        field("CPU", &stored.cpu, &new.cpu, false),
+        field("Memory", &memory(stored), &memory(new), false),
+        field("MAC Address", &stored.net, &new.net, false),
+        field("Storage", &format!("{} ({})", stored.storage_name, stored.storage_type), &format!("{} ({})", new.storage_name, new.storage_type), false),
+        field("OS Lock", &stored.o_hash, &new.o_hash, true),
+        field("CPU Lock", &stored.c_hash, &new.c_hash, true),
+        field("Storage Lock", &stored.s_hash, &new.s_hash, true),
+        field("Network Lock", &stored.n_hash, &new.n_hash, true),
+    ]
+}
+
+fn print_diff(diff: &[ComponentDiff]) {
+    // hashes are long, a prefix is enough to tell them apart
+    let short = |value: &str| value.chars().take(32).collect::<String>();
+
+    println!("{}", "-".repeat(100));
+    println!("{:<14} | {:<7} | {:<35} | {:<35}", "Component", "Changed", "On File", "New");
+    println!("{}", "-".repeat(100));
+    for d in diff {
+        println!("{:<14} | {:<7} | {:<35} | {:<35}", d.component, if d.changed() { "yes" } else { "" }, short(&d.stored), short(&d.new));
+    }
+    println!();
+}
+
+/// Issue a license for the new fingerprint of license `id`, with the same features and end date, and mark `id` superseded
+pub async fn reissue_license(pool: &Pool<Any>, args: &ReissueLicenseArgs, id: i64, hwid: String) -> Result<Option<IssuedLicenseRecord>, AdminError> {
+    let Some(row) = sqlx::query("SELECT application_id, customer_id, hwid, support_years, issued_license, status, reissue_count FROM licenses WHERE id = $1")
+        .bind(id)
+        .fetch_optional(pool)
+        .await?
+    else {
+        return Err(AdminError::NotFound(format!("license with ID {id}")));
+    };
+    let (application_id, customer_id): (i64, i64) = (row.try_get("application_id")?, row.try_get("customer_id")?);
+    let stored_hwid: String = row.try_get("hwid")?;
+    let support_years: i32 = row.try_get("support_years")?;
+    let issued_license: Option<String> = row.try_get("issued_license")?;
+    let status: String = row.try_get("status")?;
+    let reissue_count: i64 = row.try_get("reissue_count")?;
+    if status != STATUS_ACTIVE {
+        return Err(AdminError::Invalid(format!("license {id} is {status}, only active licenses can be reissued")));
+    }
+
+    let apps = crate::db::fetch_applications(pool).await?;
+    let Some(app) = apps.iter().find(|a| a.id == application_id) else {
+        return Err(AdminError::NotFound(format!("application with ID {application_id}")));
+    };
+    let customers = crate::db::fetch_customers(pool).await?;
+    let Some(customer) = customers.iter().find(|c| c.id == customer_id) else {
+        return Err(AdminError::NotFound(format!("customer with ID {customer_id}")));
+    };
+
+    let Some(stored) = decode_hwinfo_from_string(&stored_hwid, &app.info_public_key) else {
+        return Err(AdminError::Invalid(format!("stored HWID of license {id} could not be decoded with the keys of application {}", app.id)));
+    };
+    let Some(new) = decode_hwinfo_from_string(&hwid, &app.info_public_key) else {
+        return Err(AdminError::Invalid(format!("HWID could not be decoded with the keys of application {}", app.id)));
+    };
+
+    let diff = diff_fingerprints(&stored, &new);
+    let changed: Vec<&str> = diff.iter().filter(|d| d.lock && d.changed()).map(|d| d.component).collect();
+
+    if args.format.is_table() {
+        print_diff(&diff);
+    } else {
+        for d in diff.iter().filter(|d| d.changed()) {
+            info!("{} changed: {} -> {}", d.component, d.stored, d.new);
+        }
+    }
+
+    if changed.is_empty() {
+        return Err(AdminError::Invalid(format!("the new fingerprint matches license {id}, no reissue is needed")));
+    }
+    info!("{} of 4 lock components changed, application {} allows {} without approval", changed.len(), app.id, app.hardware_changes_allowed);
+
+    if args.dry_run {
+        println!("✅ Dry run, no license was reissued.");
+        return Ok(None);
+    }
+
+    let approval_needed = i64::try_from(changed.len()).unwrap_or(i64::MAX) > app.hardware_changes_allowed;
+    if approval_needed && !args.approve {
+        let prompt = format!("{} changed, more than the {} allowed. Reissue anyway?", changed.join(", "), app.hardware_changes_allowed);
+        if !input::interactive() {
+            return Err(AdminError::Invalid(format!(
+                "{} changed, more than the {} allowed by application {}, pass --approve to reissue anyway",
+                changed.join(", "),
+                app.hardware_changes_allowed,
+                app.id
+            )));
+        }
+        if !input::confirm(&prompt, false)? {
+            println!("Reissue cancelled, nothing was changed.");
+            return Ok(None);
+        }
+    }
+
+    // The stored license is the source of the features and dates, not the details columns
+    let previous = rustlock_core::read_license(&app.lic_public_key, issued_license.as_deref().unwrap_or_default())?;
+
+    let (version, max_version) = issued_version(&previous);
+    let request = IssueRequest {
+        hwid,
+        support_years,
+        version,
+        max_version,
+        features: [previous.f1, previous.f2, previous.f3, previous.f4, previous.f5],
+        end: Some((previous.end_year, previous.end_month)),
+    };
+
+    let (lic, fingerprint, issued_license) = license::build_license(app, customer, &request)?;
+
+    let mut tx = crate::db::repository(pool).begin().await?;
+    // Supersede first so two reissues of the same license, or a reissue racing a renewal or revocation, can't both go through
+    let superseded = sqlx::query("UPDATE licenses SET status = $1 WHERE id = $2 AND status = $3").bind(STATUS_SUPERSEDED).bind(id).bind(STATUS_ACTIVE).execute(&mut *tx).await?;
+    if superseded.rows_affected() != 1 {
+        return Err(AdminError::Invalid(format!("license {id} is no longer active, it was not reissued")));
+    }
+
+    let new_id = license::insert_license(&mut tx, app, customer, &request, &lic, &fingerprint, &issued_license).await?;
+    sqlx::query("UPDATE licenses SET reissued_from = $1, reissue_count = $2 WHERE id = $3").bind(id).bind(reissue_count + 1).bind(new_id).execute(&mut *tx).await?;
+
+    let mut before = audit::snapshot(&previous)?;
+    before["license_id"] = json!(id);
+    let mut after = audit::snapshot(&lic)?;
+    after["changed"] = json!(changed);
+    after["approved"] = json!(approval_needed);
+    after["reissue_count"] = json!(reissue_count + 1);
+    audit::record(&mut tx, "reissue", "license", Some(new_id), Some(before), Some(after)).await?;
+    tx.commit().await?;
+
+    if reissue_count > 0 {
+        warn!("License {id} has been reissued {} time(s) before", reissue_count);
+    }
+
+    let issued = IssuedLicense { id: new_id, license: lic, issued_license };
+    Ok(Some(IssuedLicenseRecord::new(app, customer, &request.hwid, &issued)))
+}
+
+/// Interactive wizard to reissue a license for a changed machine
+pub async fn reissue_license_wizard(pool: &Pool<Any>, args: ReissueLicenseArgs) -> Result<(), AdminError> {
+    let file = args.input.clone();
+    // flags that can't come from the input file
+    let (approve, dry_run, format) = (args.approve, args.dry_run, args.format);
+    let mut args = input::merge_input_file(args, file.as_deref())?;
+    (args.approve, args.dry_run, args.format) = (approve, dry_run, format);
+    let theme = ColorfulTheme::default();
+
+    let id = license::select_license(pool, args.license, STATUS_ACTIVE, "Select a license to reissue").await?;
+    let hwid = input::text(args.hwid.take(), "--hwid", || Input::with_theme(&theme).with_prompt("New HWID").interact_text())?;
+
+    let Some(record) = reissue_license(pool, &args, id, hwid).await? else {
+        return Ok(());
+    };
+    info!("Reissued license {id} as license {} for app {} and customer {}", record.id, record.application_id, record.customer_id);
+
+    if !args.format.is_table() {
+        return output::print_records(args.format, &[record], |_| {});
+    }
+
+    println!();
+    info!("Generated License: {}", record.issued_license);
+    println!();
+    println!("✅ License {id} reissued for the new machine, it is replaced by license {}.", record.id);
+    Ok(())
+}
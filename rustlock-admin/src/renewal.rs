@@ -0,0 +1,142 @@
+use std::path::PathBuf;
+
+use chrono::{Datelike, Utc};
+use clap::Args;
+use dialoguer::{Input, theme::ColorfulTheme};
+use log::info;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::{Any, Pool};
+
+use crate::audit;
+use crate::error::AdminError;
+use crate::input;
+use crate::license::{self, IssueRequest, IssuedLicense, IssuedLicenseRecord, STATUS_ACTIVE, STATUS_SUPERSEDED, issued_version};
+use crate::output::{self, OutputFormat};
+
+/// Inputs for `renew`, missing values are prompted for when a terminal is attached
+#[derive(Args, Serialize, Deserialize, Default, Debug)]
+pub struct RenewLicenseArgs {
+    /// ID of the license to renew
+    #[arg(long)]
+    pub license: Option<i64>,
+    /// Years of support added to the end of the current license
+    #[arg(long)]
+    pub years: Option<i32>,
+    /// New license version (semver), the current version is kept when left out
+    #[arg(long)]
+    pub version: Option<String>,
+    /// Highest licensed version, only used by the `semver_range` policy
+    #[arg(long)]
+    pub max_version: Option<String>,
+    /// Read the fields above from a JSON or TOML file
+    #[arg(long)]
+    #[serde(skip)]
+    pub input: Option<PathBuf>,
+    /// Output format of the issued license record
+    #[arg(long, value_enum, default_value_t)]
+    #[serde(skip)]
+    pub format: OutputFormat,
+}
+
+/// Issue a license for the same machine and features that continues where `id` ends, and mark `id` superseded
+pub async fn renew_license(pool: &Pool<Any>, id: i64, years: i32, version: Option<String>, max_version: Option<String>) -> Result<IssuedLicenseRecord, AdminError> {
+    if years < 1 {
+        return Err(AdminError::Invalid("renewals must add at least one year".to_string()));
+    }
+
+    let row: Option<(i64, i64, String, Option<String>, String)> = sqlx::query_as("SELECT application_id, customer_id, hwid, issued_license, status FROM licenses WHERE id = $1").bind(id).fetch_optional(pool).await?;
+    let Some((application_id, customer_id, hwid, issued_license, status)) = row else {
+        return Err(AdminError::NotFound(format!("license with ID {id}")));
+    };
+    if status != STATUS_ACTIVE {
+        return Err(AdminError::Invalid(format!("license {id} is {status}, only active licenses can be renewed")));
+    }
+
+    let apps = crate::db::fetch_applications(pool).await?;
+    let Some(app) = apps.iter().find(|a| a.id == application_id) else {
+        return Err(AdminError::NotFound(format!("application with ID {application_id}")));
+    };
+    let customers = crate::db::fetch_customers(pool).await?;
+    let Some(customer) = customers.iter().find(|c| c.id == customer_id) else {
+        return Err(AdminError::NotFound(format!("customer with ID {customer_id}")));
+    };
+
+    // The stored license is the source of the features and dates, not the details columns
+    let previous = rustlock_core::read_license(&app.lic_public_key, issued_license.as_deref().unwrap_or_default())?;
+
+    let end = (previous.end_year + years, previous.end_month);
+    let now = Utc::now();
+    if end < (now.year(), now.month()) {
+        return Err(AdminError::Invalid(format!("license {id} ended {}-{:02}, renewing it for {years} year(s) still ends in the past", previous.end_year, previous.end_month)));
+    }
+
+    let (current_version, current_max) = issued_version(&previous);
+    let request = IssueRequest {
+        hwid,
+        support_years: years,
+        max_version: max_version.or(if version.is_none() { current_max } else { None }),
+        version: version.unwrap_or(current_version),
+        features: [previous.f1, previous.f2, previous.f3, previous.f4, previous.f5],
+        end: Some(end),
+    };
+
+    let (lic, fingerprint, issued_license) = license::build_license(app, customer, &request)?;
+
+    let mut tx = crate::db::repository(pool).begin().await?;
+    // Supersede first so a concurrent renewal, reissue or revocation of the same license can't also go through
+    let superseded = sqlx::query("UPDATE licenses SET status = $1 WHERE id = $2 AND status = $3").bind(STATUS_SUPERSEDED).bind(id).bind(STATUS_ACTIVE).execute(&mut *tx).await?;
+    if superseded.rows_affected() != 1 {
+        return Err(AdminError::Invalid(format!("license {id} is no longer active, it was not renewed")));
+    }
+
+    let new_id = license::insert_license(&mut tx, app, customer, &request, &lic, &fingerprint, &issued_license).await?;
+    // Reissues of the machine carry over, the count is per license chain
+    sqlx::query("UPDATE licenses SET renewed_from = $1, reissue_count = (SELECT reissue_count FROM licenses WHERE id = $1) WHERE id = $2")
+        .bind(id)
+        .bind(new_id)
+        .execute(&mut *tx)
+        .await?;
+
+    let mut before = audit::snapshot(&previous)?;
+    before["license_id"] = json!(id);
+    audit::record(&mut tx, "renew", "license", Some(new_id), Some(before), Some(audit::snapshot(&lic)?)).await?;
+    tx.commit().await?;
+
+    let issued = IssuedLicense { id: new_id, license: lic, issued_license };
+    Ok(IssuedLicenseRecord::new(app, customer, &request.hwid, &issued))
+}
+
+/// Interactive wizard to renew a license
+pub async fn renew_license_wizard(pool: &Pool<Any>, args: RenewLicenseArgs) -> Result<(), AdminError> {
+    let file = args.input.clone();
+    let args = input::merge_input_file(args, file.as_deref())?;
+    let theme = ColorfulTheme::default();
+
+    let id = license::select_license(pool, args.license, STATUS_ACTIVE, "Select a license to renew").await?;
+
+    let years = match args.years {
+        Some(years) => years,
+        None if input::interactive() => Input::with_theme(&theme).with_prompt("Years of support to add").default(1).interact_text()?,
+        None => 1,
+    };
+
+    let version = match args.version {
+        Some(version) => Some(version),
+        None if input::interactive() => input::non_empty(input::optional_text(&theme, "New license version (blank keeps the current version)", "")?),
+        None => None,
+    };
+
+    let record = renew_license(pool, id, years, version, args.max_version).await?;
+    info!("Renewed license {id} as license {} for app {} and customer {}", record.id, record.application_id, record.customer_id);
+
+    if !args.format.is_table() {
+        return output::print_records(args.format, &[record], |_| {});
+    }
+
+    println!();
+    info!("Generated License: {}", record.issued_license);
+    println!();
+    println!("✅ License {id} renewed until {}, it is replaced by license {}.", record.end, record.id);
+    Ok(())
+}
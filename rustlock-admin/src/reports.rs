@@ -0,0 +1,219 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use chrono::{Months, NaiveDate, Utc};
+use clap::Args;
+use log::{info, warn};
+use serde::Serialize;
+use sqlx::{Any, Pool, Row};
+
+use crate::error::AdminError;
+use crate::license::STATUS_ACTIVE;
+use crate::output::{self, OutputFormat};
+
+/// Inputs for `report expiring`
+#[derive(Args, Debug)]
+pub struct ExpiringArgs {
+    /// How far ahead to look, in days (`60d`), weeks (`8w`), months (`3m`, 30 days) or years (`1y`)
+    #[arg(long, default_value = "60d", value_parser = parse_within)]
+    pub within: i64,
+    /// Only report licenses of this application
+    #[arg(long)]
+    pub application: Option<i64>,
+    /// Also list active licenses whose support already ended
+    #[arg(long)]
+    pub include_expired: bool,
+    /// Print the per-application counts instead of the licenses
+    #[arg(long)]
+    pub summary: bool,
+    /// Output format
+    #[arg(long, value_enum, default_value_t)]
+    pub format: OutputFormat,
+    /// Also write the records to a `.csv` or `.json` file
+    #[arg(long)]
+    pub output: Option<PathBuf>,
+}
+
+/// `60d`, `8w`, `3m` or `1y` as a number of days, a bare number is days
+fn parse_within(value: &str) -> Result<i64, String> {
+    let value = value.trim();
+    let (number, days_per_unit) = match value.char_indices().last() {
+        Some((i, 'd')) => (&value[..i], 1),
+        Some((i, 'w')) => (&value[..i], 7),
+        Some((i, 'm')) => (&value[..i], 30),
+        Some((i, 'y')) => (&value[..i], 365),
+        _ => (value, 1),
+    };
+
+    match number.parse::<i64>() {
+        Ok(number) if number >= 0 => Ok(number * days_per_unit),
+        _ => Err(format!("'{value}' is not a duration like 60d, 8w, 3m or 1y")),
+    }
+}
+
+/// Last day a license with `end_date` (`YYYY-MM`, the last month of support) is valid
+fn last_valid_day(end_date: &str) -> Option<NaiveDate> {
+    let first = NaiveDate::parse_from_str(&format!("{end_date}-01"), "%Y-%m-%d").ok()?;
+    first.checked_add_months(Months::new(1))?.pred_opt()
+}
+
+/// An active license whose support ends within the report window
+#[derive(Debug, Serialize, Default)]
+pub struct ExpiringLicenseRecord {
+    pub license_id: i64,
+    pub license_uid: Option<String>,
+    pub application_id: i64,
+    pub application: String,
+    pub customer_id: i64,
+    pub customer: String,
+    pub contact_email: String,
+    pub mobile: String,
+    pub hostname: Option<String>,
+    pub version: Option<String>,
+    /// `YYYY-MM`, the last month the license is valid
+    pub end: String,
+    /// Negative once support has ended
+    pub days_left: i64,
+}
+
+/// Active license counts of one application
+#[derive(Debug, Serialize, Default)]
+pub struct ExpiringSummary {
+    pub application_id: i64,
+    pub application: String,
+    pub active: i64,
+    /// Support ends within the report window
+    pub expiring: i64,
+    /// Support ended but the license was never renewed
+    pub expired: i64,
+    /// Customers with at least one expiring license
+    pub customers: i64,
+}
+
+/// List active licenses by end date with the customer's contact details, and count them per application
+pub async fn report_expiring(pool: &Pool<Any>, args: ExpiringArgs) -> Result<(), AdminError> {
+    let rows = sqlx::query(
+        r"
+        SELECT
+          l.id, l.license_uid, l.hostname, l.version, l.end_date,
+          a.id AS application_id, a.name AS application,
+          c.id AS customer_id, c.name AS customer, c.contact_email, c.mobile
+        FROM licenses l
+        JOIN applications a ON a.id = l.application_id
+        JOIN customers c ON c.id = l.customer_id
+        WHERE l.status = $1
+          AND ($2 IS NULL OR l.application_id = $2)
+        ORDER BY l.end_date, l.id
+        ",
+    )
+    .bind(STATUS_ACTIVE)
+    .bind(args.application)
+    .fetch_all(pool)
+    .await?;
+
+    let today = Utc::now().date_naive();
+    let mut records = Vec::new();
+    let mut summaries: BTreeMap<i64, (ExpiringSummary, Vec<i64>)> = BTreeMap::new();
+    let mut undated = 0;
+
+    for row in &rows {
+        let application_id: i64 = row.try_get("application_id")?;
+        let (summary, customers) = summaries.entry(application_id).or_insert_with(|| {
+            let summary = ExpiringSummary {
+                application_id,
+                application: row.try_get("application").unwrap_or_default(),
+                ..ExpiringSummary::default()
+            };
+            (summary, Vec::new())
+        });
+        summary.active += 1;
+
+        let end: Option<String> = row.try_get("end_date")?;
+        let Some((end, last_day)) = end.and_then(|end| last_valid_day(&end).map(|day| (end, day))) else {
+            undated += 1;
+            continue;
+        };
+
+        let days_left = (last_day - today).num_days();
+        if days_left < 0 {
+            summary.expired += 1;
+            if !args.include_expired {
+                continue;
+            }
+        } else if days_left <= args.within {
+            summary.expiring += 1;
+        } else {
+            continue;
+        }
+
+        let customer_id: i64 = row.try_get("customer_id")?;
+        if days_left >= 0 && !customers.contains(&customer_id) {
+            customers.push(customer_id);
+            summary.customers += 1;
+        }
+
+        records.push(ExpiringLicenseRecord {
+            license_id: row.try_get("id")?,
+            license_uid: row.try_get("license_uid")?,
+            application_id,
+            application: summary.application.clone(),
+            customer_id,
+            customer: row.try_get("customer")?,
+            contact_email: row.try_get("contact_email")?,
+            mobile: row.try_get("mobile")?,
+            hostname: row.try_get("hostname")?,
+            version: row.try_get("version")?,
+            end,
+            days_left,
+        });
+    }
+
+    if undated > 0 {
+        warn!("{undated} active license(s) have no end date and were left out, they could not be decoded");
+    }
+
+    let summaries: Vec<ExpiringSummary> = summaries.into_values().map(|(summary, _)| summary).collect();
+
+    if args.summary {
+        if let Some(path) = &args.output {
+            output::write_records(path, &summaries)?;
+            info!("Summary written to: {}", path.display());
+        }
+        return output::print_records(args.format, &summaries, print_summary);
+    }
+
+    if let Some(path) = &args.output {
+        output::write_records(path, &records)?;
+        info!("Report written to: {}", path.display());
+    }
+
+    output::print_records(args.format, &records, |records| {
+        println!();
+        println!("—— Licenses ending within {} days ——————————", args.within);
+        println!();
+
+        if records.is_empty() {
+            println!("⚠️  No active licenses end within {} days.", args.within);
+        } else {
+            println!("{}", "-".repeat(120));
+            println!("{:<6} | {:<20} | {:<20} | {:<30} | {:<15} | {:<7} | {:>5}", "ID", "Application", "Customer", "Email", "Mobile", "Ends", "Days");
+            println!("{}", "-".repeat(120));
+            for r in records {
+                println!("{:<6} | {:<20} | {:<20} | {:<30} | {:<15} | {:<7} | {:>5}", r.license_id, r.application, r.customer, r.contact_email, r.mobile, r.end, r.days_left);
+            }
+        }
+
+        print_summary(&summaries);
+    })
+}
+
+fn print_summary(summaries: &[ExpiringSummary]) {
+    println!();
+    println!("{}", "-".repeat(80));
+    println!("{:<6} | {:<20} | {:>8} | {:>8} | {:>8} | {:>9}", "ID", "Application", "Active", "Expiring", "Expired", "Customers");
+    println!("{}", "-".repeat(80));
+    for s in summaries {
+        println!("{:<6} | {:<20} | {:>8} | {:>8} | {:>8} | {:>9}", s.application_id, s.application, s.active, s.expiring, s.expired, s.customers);
+    }
+    println!();
+}
@@ -0,0 +1,191 @@
+use std::path::{Path, PathBuf};
+
+use clap::Args;
+use dialoguer::{Input, theme::ColorfulTheme};
+use log::{info, warn};
+use rustlock_core::license::RevocationList;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::{Any, AnyConnection, Pool, Row};
+
+use crate::applications::select_application;
+use crate::audit;
+use crate::error::AdminError;
+use crate::input;
+use crate::license::{self, STATUS_ACTIVE, STATUS_REVOKED};
+
+/// Inputs for `revoke` and `unrevoke`, missing values are prompted for when a terminal is attached
+#[derive(Args, Serialize, Deserialize, Default, Debug)]
+pub struct RevokeArgs {
+    /// ID of the license
+    #[arg(long)]
+    pub license: Option<i64>,
+    /// Why the license is revoked or reinstated, kept in the audit log
+    #[arg(long)]
+    pub reason: Option<String>,
+    /// Read the fields above from a JSON or TOML file
+    #[arg(long)]
+    #[serde(skip)]
+    pub input: Option<PathBuf>,
+    /// Skip the confirmation, required when no terminal is attached
+    #[arg(long)]
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub yes: bool,
+}
+
+/// Revocation state of a license
+#[derive(Debug, Serialize)]
+struct LicenseStatus {
+    id: i64,
+    customer_id: i64,
+    license_uid: Option<String>,
+    status: String,
+    revoked_at: Option<String>,
+    revoked_reason: Option<String>,
+}
+
+async fn fetch_status(conn: &mut AnyConnection, id: i64) -> Result<LicenseStatus, AdminError> {
+    let row = sqlx::query("SELECT id, customer_id, license_uid, status, revoked_at, revoked_reason FROM licenses WHERE id = $1").bind(id).fetch_optional(conn).await?;
+    let Some(row) = row else {
+        return Err(AdminError::NotFound(format!("license with ID {id}")));
+    };
+
+    Ok(LicenseStatus {
+        id: row.try_get("id")?,
+        customer_id: row.try_get("customer_id")?,
+        license_uid: row.try_get("license_uid")?,
+        status: row.try_get("status")?,
+        revoked_at: row.try_get("revoked_at")?,
+        revoked_reason: row.try_get("revoked_reason")?,
+    })
+}
+
+/// Mark a license as revoked, shipped applications refuse it once they load the next revocation list
+pub async fn revoke_license(pool: &Pool<Any>, id: i64, reason: &str) -> Result<(), AdminError> {
+    let mut tx = crate::db::repository(pool).begin().await?;
+    let before = fetch_status(&mut tx, id).await?;
+    if before.status == STATUS_REVOKED {
+        return Err(AdminError::Invalid(format!("license {id} is already revoked")));
+    }
+
+    sqlx::query("UPDATE licenses SET status_before_revoke = status, status = $1, revoked_at = $2, revoked_reason = $3 WHERE id = $4")
+        .bind(STATUS_REVOKED)
+        .bind(chrono::Utc::now().to_rfc3339())
+        .bind(reason)
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+
+    let after = fetch_status(&mut tx, id).await?;
+    audit::record(&mut tx, "revoke", "license", Some(id), Some(audit::snapshot(&before)?), Some(audit::snapshot(&after)?)).await?;
+    tx.commit().await?;
+
+    if after.license_uid.is_none() {
+        warn!(
+            "License {id} was issued without a license ID, shipped applications can't refuse it individually. Block customer {} or reissue the customer's licenses instead.",
+            after.customer_id
+        );
+    }
+    Ok(())
+}
+
+/// Reinstate a revoked license with the status it had before, a renewed license stays superseded
+pub async fn unrevoke_license(pool: &Pool<Any>, id: i64, reason: &str) -> Result<(), AdminError> {
+    let mut tx = crate::db::repository(pool).begin().await?;
+    let before = fetch_status(&mut tx, id).await?;
+    if before.status != STATUS_REVOKED {
+        return Err(AdminError::Invalid(format!("license {id} isn't revoked")));
+    }
+
+    sqlx::query("UPDATE licenses SET status = COALESCE(status_before_revoke, $1), status_before_revoke = NULL, revoked_at = NULL, revoked_reason = NULL WHERE id = $2")
+        .bind(STATUS_ACTIVE)
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+
+    let after = fetch_status(&mut tx, id).await?;
+    let mut after = audit::snapshot(&after)?;
+    after["reason"] = json!(reason);
+    audit::record(&mut tx, "unrevoke", "license", Some(id), Some(audit::snapshot(&before)?), Some(after)).await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Interactive wizard to revoke a license
+pub async fn revoke_license_wizard(pool: &Pool<Any>, args: RevokeArgs) -> Result<(), AdminError> {
+    let file = args.input.clone();
+    let args = input::merge_input_file(args, file.as_deref())?;
+    let theme = ColorfulTheme::default();
+
+    let id = license::select_license(pool, args.license, STATUS_ACTIVE, "Select a license to revoke").await?;
+    let reason = input::text(args.reason, "reason", || Input::with_theme(&theme).with_prompt("Reason for revoking").interact_text())?;
+
+    if !args.yes && !input::confirm(&format!("Revoke license {id}?"), true)? {
+        info!("Revocation cancelled.");
+        return Ok(());
+    }
+
+    revoke_license(pool, id, &reason).await?;
+    info!("✅ License {id} revoked, run `export revocations` and ship the list to apply it.");
+    Ok(())
+}
+
+/// Interactive wizard to reinstate a revoked license
+pub async fn unrevoke_license_wizard(pool: &Pool<Any>, args: RevokeArgs) -> Result<(), AdminError> {
+    let file = args.input.clone();
+    let args = input::merge_input_file(args, file.as_deref())?;
+    let theme = ColorfulTheme::default();
+
+    let id = license::select_license(pool, args.license, STATUS_REVOKED, "Select a license to reinstate").await?;
+    let reason = input::text(args.reason, "reason", || Input::with_theme(&theme).with_prompt("Reason for reinstating").interact_text())?;
+
+    if !args.yes && !input::confirm(&format!("Reinstate license {id}?"), true)? {
+        info!("Reinstatement cancelled.");
+        return Ok(());
+    }
+
+    unrevoke_license(pool, id, &reason).await?;
+    info!("✅ License {id} reinstated, run `export revocations` and ship the list to apply it.");
+    Ok(())
+}
+
+/// Write the IDs of an application's revoked licenses and the licenses they replaced for `RustLock::with_revoked_licenses`,
+/// sealed with its issuing key
+pub async fn export_revocations(pool: &Pool<Any>, application: Option<i64>, output: &Path) -> Result<(), AdminError> {
+    let apps = crate::db::fetch_applications(pool).await?;
+    let app = select_application(&apps, application, "Select an application to export revocations for")?;
+
+    // The licenses a revoked one was renewed or reissued from are still valid files, so they're revoked along with it
+    let revoked: Vec<String> = sqlx::query_scalar(
+        r"
+        WITH RECURSIVE chain (id) AS (
+            SELECT id FROM licenses WHERE application_id = $1 AND status = $2
+            UNION
+            SELECT previous.id
+            FROM chain
+            JOIN licenses l ON l.id = chain.id
+            JOIN licenses previous ON previous.id = l.renewed_from OR previous.id = l.reissued_from
+        )
+        SELECT license_uid FROM licenses
+        WHERE id IN (SELECT id FROM chain) AND license_uid IS NOT NULL
+        ORDER BY id
+        ",
+    )
+    .bind(app.id)
+    .bind(STATUS_REVOKED)
+    .fetch_all(pool)
+    .await?;
+
+    let mut list = RevocationList {
+        application: app.name.clone(),
+        generated_at: chrono::Utc::now().to_rfc3339(),
+        revoked,
+        sealed: String::new(),
+    };
+    list.seal(&app.lic_private_key)?;
+    std::fs::write(output, serde_json::to_string_pretty(&list)?)?;
+
+    info!("Exported {} revoked license(s) for application {}.", list.revoked.len(), app.id);
+    println!("✅ Revocation list written to: {}", output.display());
+    Ok(())
+}
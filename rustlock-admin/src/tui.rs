@@ -0,0 +1,686 @@
+use std::io::stdout;
+
+use log::LevelFilter;
+use ratatui::crossterm::event::{self, DisableBracketedPaste, EnableBracketedPaste, Event, KeyCode, KeyEvent, KeyEventKind};
+use ratatui::crossterm::execute;
+use ratatui::layout::{Constraint, Flex, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style, Stylize};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, Cell, Clear, Paragraph, Row, Table, TableState, Tabs, Wrap};
+use ratatui::{DefaultTerminal, Frame};
+use sqlx::{Any, Pool};
+
+use crate::db::{self, Application, Customer};
+use crate::error::AdminError;
+use crate::license::{self, IssueRequest, LicenseRecord, STATUS_ACTIVE, STATUS_REVOKED, decode_hwinfo_from_string};
+use crate::{input, keystore, renewal, revocation};
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Pane {
+    Applications,
+    Customers,
+    Licenses,
+}
+
+const PANES: [Pane; 3] = [Pane::Applications, Pane::Customers, Pane::Licenses];
+
+impl Pane {
+    const fn title(self) -> &'static str {
+        match self {
+            Self::Applications => "Applications",
+            Self::Customers => "Customers",
+            Self::Licenses => "Licenses",
+        }
+    }
+
+    const fn index(self) -> usize {
+        match self {
+            Self::Applications => 0,
+            Self::Customers => 1,
+            Self::Licenses => 2,
+        }
+    }
+}
+
+/// A license with the names the list shows
+struct LicenseRow {
+    record: LicenseRecord,
+    application: String,
+    customer: String,
+}
+
+/// What a form does once it is submitted
+#[derive(Clone, Copy)]
+enum FormAction {
+    Issue,
+    Renew(i64),
+    Revoke(i64),
+    Unrevoke(i64),
+}
+
+/// A popup of text fields, filled in before an action runs
+struct Form {
+    title: String,
+    action: FormAction,
+    fields: Vec<(&'static str, String)>,
+    focus: usize,
+}
+
+impl Form {
+    fn new(title: String, action: FormAction, fields: Vec<(&'static str, String)>) -> Self {
+        Self { title, action, fields, focus: 0 }
+    }
+
+    fn value(&self, label: &str) -> &str {
+        self.fields.iter().find(|(l, _)| *l == label).map_or("", |(_, value)| value.trim())
+    }
+}
+
+enum Mode {
+    Browse,
+    Search,
+    Form(Form),
+}
+
+/// Whether the event loop keeps running
+#[derive(PartialEq, Eq)]
+enum Flow {
+    Continue,
+    Quit,
+}
+
+struct Tui<'a> {
+    pool: &'a Pool<Any>,
+    pane: Pane,
+    apps: Vec<Application>,
+    customers: Vec<Customer>,
+    licenses: Vec<LicenseRow>,
+    tables: [TableState; 3],
+    /// Search text of each pane
+    search: [String; 3],
+    detail_scroll: u16,
+    mode: Mode,
+    /// Result of the last action, `true` for errors
+    status: Option<(String, bool)>,
+}
+
+fn parse_number<T: std::str::FromStr>(value: &str, field: &str) -> Result<T, AdminError> {
+    value.parse().map_err(|_| AdminError::Invalid(format!("{field} '{value}' is not a number")))
+}
+
+fn or_dash(value: Option<&str>) -> String {
+    value.filter(|v| !v.is_empty()).unwrap_or("-").to_string()
+}
+
+fn field(label: &str, value: impl Into<String>) -> Line<'static> {
+    Line::from(vec![Span::styled(format!("{label:<14}"), Style::new().add_modifier(Modifier::BOLD)), Span::raw(value.into())])
+}
+
+fn heading(text: &str) -> Line<'static> {
+    Line::from(Span::styled(format!("—— {text} "), Style::new().fg(Color::Cyan).add_modifier(Modifier::BOLD)))
+}
+
+impl<'a> Tui<'a> {
+    async fn load(pool: &'a Pool<Any>) -> Result<Self, AdminError> {
+        let mut tui = Self {
+            pool,
+            pane: Pane::Licenses,
+            apps: Vec::new(),
+            customers: Vec::new(),
+            licenses: Vec::new(),
+            tables: Default::default(),
+            search: Default::default(),
+            detail_scroll: 0,
+            mode: Mode::Browse,
+            status: None,
+        };
+        tui.reload().await?;
+        Ok(tui)
+    }
+
+    /// Read everything again, after an action or when F5 is pressed
+    async fn reload(&mut self) -> Result<(), AdminError> {
+        self.apps = db::fetch_applications(self.pool).await?;
+        self.customers = db::fetch_customers(self.pool).await?;
+
+        let mut licenses = Vec::new();
+        for app in &self.apps {
+            for record in license::fetch_licenses(self.pool, app, None).await? {
+                let customer = self.customers.iter().find(|c| c.id == record.customer_id).map_or_else(|| record.customer_id.to_string(), |c| c.name.clone());
+                licenses.push(LicenseRow { record, application: app.name.clone(), customer });
+            }
+        }
+        // newest first, they are the ones being worked on
+        licenses.sort_by_key(|l| std::cmp::Reverse(l.record.id));
+        self.licenses = licenses;
+
+        for pane in PANES {
+            let len = self.visible(pane).len();
+            let table = &mut self.tables[pane.index()];
+            table.select(if len == 0 { None } else { Some(table.selected().unwrap_or(0).min(len - 1)) });
+        }
+        Ok(())
+    }
+
+    fn active_licenses(&self, matches: impl Fn(&LicenseRecord) -> bool) -> usize {
+        self.licenses.iter().filter(|l| l.record.status == STATUS_ACTIVE && matches(&l.record)).count()
+    }
+
+    fn header(pane: Pane) -> &'static [&'static str] {
+        match pane {
+            Pane::Applications => &["ID", "Name", "Policy", "Features", "Active"],
+            Pane::Customers => &["ID", "Name", "Email", "Mobile", "Active"],
+            Pane::Licenses => &["ID", "Application", "Customer", "Status", "Hostname", "Version", "Ends"],
+        }
+    }
+
+    fn widths(pane: Pane) -> Vec<Constraint> {
+        match pane {
+            Pane::Applications => vec![Constraint::Length(5), Constraint::Fill(2), Constraint::Length(14), Constraint::Fill(3), Constraint::Length(6)],
+            Pane::Customers => vec![Constraint::Length(5), Constraint::Fill(2), Constraint::Fill(2), Constraint::Length(14), Constraint::Length(6)],
+            Pane::Licenses => vec![Constraint::Length(5), Constraint::Fill(2), Constraint::Fill(2), Constraint::Length(10), Constraint::Fill(2), Constraint::Length(9), Constraint::Length(7)],
+        }
+    }
+
+    /// The columns of row `i` of `pane`, also what search matches against
+    fn cells(&self, pane: Pane, i: usize) -> Vec<String> {
+        match pane {
+            Pane::Applications => {
+                let a = &self.apps[i];
+                let features: Vec<String> = a.feature_schema().features.into_iter().map(|f| f.name).collect();
+                vec![a.id.to_string(), a.name.clone(), a.version_policy.to_string(), features.join(", "), self.active_licenses(|l| l.application_id == a.id).to_string()]
+            }
+            Pane::Customers => {
+                let c = &self.customers[i];
+                vec![c.id.to_string(), c.name.clone(), c.contact_email.clone(), c.mobile.clone(), self.active_licenses(|l| l.customer_id == c.id).to_string()]
+            }
+            Pane::Licenses => {
+                let l = &self.licenses[i];
+                let r = &l.record;
+                vec![r.id.to_string(), l.application.clone(), l.customer.clone(), r.status.clone(), or_dash(r.hostname.as_deref()), or_dash(r.version.as_deref()), or_dash(r.end.as_deref())]
+            }
+        }
+    }
+
+    /// Archived applications and customers and licenses that are no longer active are greyed out
+    fn dimmed(&self, pane: Pane, i: usize) -> bool {
+        match pane {
+            Pane::Applications => self.apps[i].archived_at.is_some(),
+            Pane::Customers => self.customers[i].archived_at.is_some(),
+            Pane::Licenses => self.licenses[i].record.status != STATUS_ACTIVE,
+        }
+    }
+
+    /// Indexes of the rows of `pane` that match its search text
+    fn visible(&self, pane: Pane) -> Vec<usize> {
+        let len = match pane {
+            Pane::Applications => self.apps.len(),
+            Pane::Customers => self.customers.len(),
+            Pane::Licenses => self.licenses.len(),
+        };
+        let search = self.search[pane.index()].to_lowercase();
+        (0..len).filter(|&i| search.is_empty() || self.cells(pane, i).join(" ").to_lowercase().contains(&search)).collect()
+    }
+
+    /// Index of the highlighted row of the current pane
+    fn selected(&self) -> Option<usize> {
+        let row = self.tables[self.pane.index()].selected()?;
+        self.visible(self.pane).get(row).copied()
+    }
+
+    fn selected_license(&self) -> Option<&LicenseRecord> {
+        if self.pane != Pane::Licenses {
+            return None;
+        }
+        self.selected().map(|i| &self.licenses[i].record)
+    }
+
+    fn move_by(&mut self, delta: isize) {
+        let len = self.visible(self.pane).len();
+        let table = &mut self.tables[self.pane.index()];
+        if len == 0 {
+            table.select(None);
+            return;
+        }
+        let current = table.selected().unwrap_or(0);
+        table.select(Some(current.saturating_add_signed(delta).min(len - 1)));
+        self.detail_scroll = 0;
+    }
+
+    fn switch_pane(&mut self, pane: Pane) {
+        self.pane = pane;
+        self.detail_scroll = 0;
+        self.move_by(0);
+    }
+
+    /// Show the license with ID `id` in the licenses pane
+    fn focus_license(&mut self, id: i64) {
+        self.search[Pane::Licenses.index()].clear();
+        self.switch_pane(Pane::Licenses);
+        let row = self.licenses.iter().position(|l| l.record.id == id);
+        self.tables[Pane::Licenses.index()].select(row);
+    }
+
+    fn issue_form(&self) -> Form {
+        let (mut application, mut customer, mut hwid) = (String::new(), String::new(), String::new());
+        match (self.pane, self.selected()) {
+            (Pane::Applications, Some(i)) => application = self.apps[i].id.to_string(),
+            (Pane::Customers, Some(i)) => customer = self.customers[i].id.to_string(),
+            // another license for the same machine, e.g. a second application
+            (Pane::Licenses, Some(i)) => {
+                let r = &self.licenses[i].record;
+                (application, customer, hwid) = (r.application_id.to_string(), r.customer_id.to_string(), r.hwid.clone());
+            }
+            _ => {}
+        }
+
+        Form::new(
+            "Issue License".to_string(),
+            FormAction::Issue,
+            vec![
+                ("Application ID", application),
+                ("Customer ID", customer),
+                ("HWID", hwid),
+                ("Version", "1.0.0".to_string()),
+                ("Max Version", String::new()),
+                ("Support Years", "1".to_string()),
+                ("Features", String::new()),
+            ],
+        )
+    }
+
+    /// Open the form for a license action, or explain why it can't run on the selection
+    fn license_form(&mut self, key: char) {
+        let Some(r) = self.selected_license() else {
+            self.status = Some(("Select a license in the Licenses pane first".to_string(), true));
+            return;
+        };
+
+        let form = match key {
+            'r' if r.status == STATUS_ACTIVE => Form::new(format!("Renew License {}", r.id), FormAction::Renew(r.id), vec![("Years", "1".to_string()), ("Version", String::new()), ("Max Version", String::new())]),
+            'x' if r.status == STATUS_ACTIVE => Form::new(format!("Revoke License {}", r.id), FormAction::Revoke(r.id), vec![("Reason", String::new())]),
+            'u' if r.status == STATUS_REVOKED => Form::new(format!("Unrevoke License {}", r.id), FormAction::Unrevoke(r.id), vec![("Reason", String::new())]),
+            _ => {
+                self.status = Some((format!("License {} is {}", r.id, r.status), true));
+                return;
+            }
+        };
+        self.mode = Mode::Form(form);
+    }
+
+    /// Run the action of `form`, returns the status message and the license to show afterwards
+    async fn submit(&self, form: &Form) -> Result<(String, i64), AdminError> {
+        match form.action {
+            FormAction::Issue => {
+                let app = db::fetch_application(self.pool, parse_number(form.value("Application ID"), "application ID")?).await?;
+                let customer = db::fetch_customer(self.pool, parse_number(form.value("Customer ID"), "customer ID")?).await?;
+                let names: Vec<String> = form.value("Features").split(',').map(str::trim).filter(|n| !n.is_empty()).map(str::to_string).collect();
+
+                let request = IssueRequest {
+                    hwid: form.value("HWID").split_whitespace().collect(),
+                    support_years: parse_number(form.value("Support Years"), "support years")?,
+                    version: form.value("Version").to_string(),
+                    max_version: input::non_empty(form.value("Max Version").to_string()),
+                    features: license::features_by_name(&app, &names)?,
+                    end: None,
+                };
+                if request.hwid.is_empty() {
+                    return Err(AdminError::Invalid("a HWID is required".to_string()));
+                }
+
+                let issued = license::issue_license(self.pool, &app, &customer, &request).await?;
+                Ok((format!("Issued license {} for {} to {}", issued.id, app.name, customer.name), issued.id))
+            }
+            FormAction::Renew(id) => {
+                let years = parse_number(form.value("Years"), "years")?;
+                let record = renewal::renew_license(self.pool, id, years, input::non_empty(form.value("Version").to_string()), input::non_empty(form.value("Max Version").to_string())).await?;
+                Ok((format!("Renewed license {id}, it is replaced by license {} ending {}", record.id, record.end), record.id))
+            }
+            FormAction::Revoke(id) => {
+                if form.value("Reason").is_empty() {
+                    return Err(AdminError::Invalid("a reason is required".to_string()));
+                }
+                revocation::revoke_license(self.pool, id, form.value("Reason")).await?;
+                Ok((format!("Revoked license {id}"), id))
+            }
+            FormAction::Unrevoke(id) => {
+                revocation::unrevoke_license(self.pool, id, form.value("Reason")).await?;
+                Ok((format!("License {id} is active again"), id))
+            }
+        }
+    }
+
+    async fn run(&mut self, terminal: &mut DefaultTerminal) -> Result<(), AdminError> {
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+            match event::read()? {
+                Event::Key(key) if key.kind == KeyEventKind::Press && self.on_key(key).await? == Flow::Quit => return Ok(()),
+                Event::Paste(text) => self.on_paste(&text),
+                _ => {}
+            }
+        }
+    }
+
+    fn on_paste(&mut self, text: &str) {
+        let text = text.trim();
+        match &mut self.mode {
+            Mode::Form(form) => form.fields[form.focus].1.push_str(text),
+            Mode::Search => {
+                self.search[self.pane.index()].push_str(text);
+                self.tables[self.pane.index()].select(Some(0));
+                self.move_by(0);
+            }
+            Mode::Browse => {}
+        }
+    }
+
+    async fn on_key(&mut self, key: KeyEvent) -> Result<Flow, AdminError> {
+        match &mut self.mode {
+            Mode::Form(form) => {
+                match key.code {
+                    KeyCode::Esc => self.mode = Mode::Browse,
+                    KeyCode::Tab | KeyCode::Down => form.focus = (form.focus + 1) % form.fields.len(),
+                    KeyCode::BackTab | KeyCode::Up => form.focus = (form.focus + form.fields.len() - 1) % form.fields.len(),
+                    KeyCode::Backspace => {
+                        form.fields[form.focus].1.pop();
+                    }
+                    KeyCode::Char(c) => form.fields[form.focus].1.push(c),
+                    KeyCode::Enter => {
+                        let Mode::Form(form) = std::mem::replace(&mut self.mode, Mode::Browse) else {
+                            return Ok(Flow::Continue);
+                        };
+                        match self.submit(&form).await {
+                            Ok((message, id)) => {
+                                self.reload().await?;
+                                self.focus_license(id);
+                                self.status = Some((message, false));
+                            }
+                            Err(e) => {
+                                // keep the form so the input can be corrected
+                                self.status = Some((e.to_string(), true));
+                                self.mode = Mode::Form(form);
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+                return Ok(Flow::Continue);
+            }
+            Mode::Search => {
+                let search = &mut self.search[self.pane.index()];
+                match key.code {
+                    KeyCode::Enter => self.mode = Mode::Browse,
+                    KeyCode::Esc => {
+                        search.clear();
+                        self.mode = Mode::Browse;
+                    }
+                    KeyCode::Backspace => {
+                        search.pop();
+                    }
+                    KeyCode::Char(c) => search.push(c),
+                    _ => return Ok(Flow::Continue),
+                }
+                self.tables[self.pane.index()].select(Some(0));
+                self.move_by(0);
+                return Ok(Flow::Continue);
+            }
+            Mode::Browse => {}
+        }
+
+        self.status = None;
+        match key.code {
+            KeyCode::Char('q') => return Ok(Flow::Quit),
+            KeyCode::Esc if self.search[self.pane.index()].is_empty() => return Ok(Flow::Quit),
+            KeyCode::Esc => {
+                self.search[self.pane.index()].clear();
+                self.move_by(0);
+            }
+            KeyCode::Char('/') => self.mode = Mode::Search,
+            KeyCode::Tab | KeyCode::Right => self.switch_pane(PANES[(self.pane.index() + 1) % PANES.len()]),
+            KeyCode::BackTab | KeyCode::Left => self.switch_pane(PANES[(self.pane.index() + PANES.len() - 1) % PANES.len()]),
+            KeyCode::Char(c @ '1'..='3') => self.switch_pane(PANES[usize::from(c as u8 - b'1')]),
+            KeyCode::Down | KeyCode::Char('j') => self.move_by(1),
+            KeyCode::Up | KeyCode::Char('k') => self.move_by(-1),
+            KeyCode::PageDown => self.move_by(10),
+            KeyCode::PageUp => self.move_by(-10),
+            KeyCode::Home => self.move_by(isize::MIN),
+            KeyCode::End => self.move_by(isize::MAX),
+            KeyCode::Char('J') => self.detail_scroll = self.detail_scroll.saturating_add(1),
+            KeyCode::Char('K') => self.detail_scroll = self.detail_scroll.saturating_sub(1),
+            // the licenses of the selected application or customer
+            KeyCode::Enter => {
+                let name = match (self.pane, self.selected()) {
+                    (Pane::Applications, Some(i)) => self.apps[i].name.clone(),
+                    (Pane::Customers, Some(i)) => self.customers[i].name.clone(),
+                    _ => return Ok(Flow::Continue),
+                };
+                self.search[Pane::Licenses.index()] = name;
+                self.tables[Pane::Licenses.index()].select(Some(0));
+                self.switch_pane(Pane::Licenses);
+            }
+            KeyCode::Char('i') => self.mode = Mode::Form(self.issue_form()),
+            KeyCode::Char(c @ ('r' | 'x' | 'u')) => self.license_form(c),
+            KeyCode::F(5) => {
+                self.reload().await?;
+                self.status = Some(("Reloaded".to_string(), false));
+            }
+            _ => {}
+        }
+        Ok(Flow::Continue)
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [tabs_area, search_area, body, footer] = Layout::vertical([Constraint::Length(1), Constraint::Length(1), Constraint::Fill(1), Constraint::Length(1)]).areas(frame.area());
+        let [list_area, detail_area] = Layout::horizontal([Constraint::Percentage(55), Constraint::Percentage(45)]).areas(body);
+
+        let titles = PANES.iter().map(|p| format!("{} {}", p.index() + 1, p.title()));
+        frame.render_widget(Tabs::new(titles).select(self.pane.index()).highlight_style(Style::new().fg(Color::Cyan).add_modifier(Modifier::BOLD)), tabs_area);
+
+        let search = &self.search[self.pane.index()];
+        let search_line = match self.mode {
+            Mode::Search => Line::from(vec![Span::raw("/ "), Span::raw(search.clone())]),
+            _ if search.is_empty() => Line::from("Press / to search".dark_gray()),
+            _ => Line::from(vec![Span::raw("Filter: "), Span::styled(search.clone(), Style::new().fg(Color::Yellow)), "  (Esc clears)".dark_gray()]),
+        };
+        frame.render_widget(Paragraph::new(search_line), search_area);
+        if matches!(self.mode, Mode::Search) {
+            let x = search_area.x.saturating_add(2).saturating_add(u16::try_from(search.chars().count()).unwrap_or(u16::MAX));
+            frame.set_cursor_position((x.min(search_area.right().saturating_sub(1)), search_area.y));
+        }
+
+        let visible = self.visible(self.pane);
+        let rows: Vec<Row> = visible
+            .iter()
+            .map(|&i| {
+                let row = Row::new(self.cells(self.pane, i).into_iter().map(Cell::from));
+                if self.dimmed(self.pane, i) { row.style(Style::new().fg(Color::DarkGray)) } else { row }
+            })
+            .collect();
+        let table = Table::new(rows, Self::widths(self.pane))
+            .header(Row::new(Self::header(self.pane).iter().copied()).style(Style::new().add_modifier(Modifier::BOLD)))
+            .block(Block::default().borders(Borders::ALL).title(format!(" {} ({}) ", self.pane.title(), visible.len())))
+            .row_highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(table, list_area, &mut self.tables[self.pane.index()]);
+
+        let detail = Paragraph::new(self.detail()).block(Block::default().borders(Borders::ALL).title(" Details ")).wrap(Wrap { trim: false }).scroll((self.detail_scroll, 0));
+        frame.render_widget(detail, detail_area);
+
+        let footer_line = match (&self.status, &self.mode) {
+            (Some((message, true)), _) => Line::from(message.clone().red()),
+            (Some((message, false)), _) => Line::from(message.clone().green()),
+            (None, Mode::Browse) => Line::from("q quit · tab/1-3 pane · / search · enter licenses · i issue · r renew · x revoke · u unrevoke · J/K scroll details · F5 reload".dark_gray()),
+            (None, Mode::Search) => Line::from("type to filter · enter keep filter · esc clear".dark_gray()),
+            (None, Mode::Form(_)) => Line::from("tab next field · enter submit · esc cancel".dark_gray()),
+        };
+        frame.render_widget(Paragraph::new(footer_line), footer);
+
+        if let Mode::Form(form) = &self.mode {
+            draw_form(frame, form);
+        }
+    }
+
+    fn detail(&self) -> Vec<Line<'static>> {
+        let Some(i) = self.selected() else {
+            return vec![Line::from("Nothing selected".dark_gray())];
+        };
+
+        match self.pane {
+            Pane::Applications => self.application_detail(&self.apps[i]),
+            Pane::Customers => self.customer_detail(&self.customers[i]),
+            Pane::Licenses => self.license_detail(&self.licenses[i]),
+        }
+    }
+
+    fn application_detail(&self, a: &Application) -> Vec<Line<'static>> {
+        let features: Vec<String> = a.feature_schema().features.into_iter().map(|f| format!("{} ({})", f.name, f.slot)).collect();
+        let blocked: Vec<String> = a.blocked_customer_ids.iter().map(u64::to_string).collect();
+
+        vec![
+            field("ID", a.id.to_string()),
+            field("Name", a.name.clone()),
+            field("Policy", a.version_policy.to_string()),
+            field("Features", if features.is_empty() { "-".to_string() } else { features.join(", ") }),
+            field("HW Changes", format!("{} of 4 lock components", a.hardware_changes_allowed)),
+            field("Blocked", if blocked.is_empty() { "-".to_string() } else { blocked.join(", ") }),
+            field("Archived", or_dash(a.archived_at.as_deref())),
+            field("Active", self.active_licenses(|l| l.application_id == a.id).to_string()),
+            field("Licenses", self.licenses.iter().filter(|l| l.record.application_id == a.id).count().to_string()),
+        ]
+    }
+
+    fn customer_detail(&self, c: &Customer) -> Vec<Line<'static>> {
+        let mut lines = vec![
+            field("ID", c.id.to_string()),
+            field("Name", c.name.clone()),
+            field("Email", c.contact_email.clone()),
+            field("Mobile", c.mobile.clone()),
+            field("Archived", or_dash(c.archived_at.as_deref())),
+            Line::default(),
+            heading("Licenses"),
+        ];
+        for l in self.licenses.iter().filter(|l| l.record.customer_id == c.id) {
+            let r = &l.record;
+            lines.push(Line::from(format!("{:<5} {:<20} {:<10} ends {}", r.id, l.application, r.status, or_dash(r.end.as_deref()))));
+        }
+        lines
+    }
+
+    fn license_detail(&self, l: &LicenseRow) -> Vec<Line<'static>> {
+        let r = &l.record;
+        let mut lines = vec![
+            field("ID", r.id.to_string()),
+            field("Application", format!("{} ({})", l.application, r.application_id)),
+            field("Customer", format!("{} ({})", l.customer, r.customer_id)),
+            field("Status", r.status.clone()),
+            field("Issued", or_dash(r.issued_at.as_deref())),
+            field("Support Years", r.support_years.to_string()),
+        ];
+        if r.status == STATUS_REVOKED {
+            lines.push(field("Revoked", format!("{} ({})", or_dash(r.revoked_at.as_deref()), or_dash(r.revoked_reason.as_deref()))));
+        }
+        if let Some(from) = r.renewed_from {
+            lines.push(field("Renewal Of", from.to_string()));
+        }
+        if let Some(from) = r.reissued_from {
+            lines.push(field("Reissue Of", format!("{from} ({} reissues)", r.reissue_count)));
+        }
+
+        let Some(app) = self.apps.iter().find(|a| a.id == r.application_id) else {
+            return lines;
+        };
+
+        lines.push(Line::default());
+        lines.push(heading("License"));
+        match rustlock_core::read_license(&app.lic_public_key, &r.issued_license) {
+            Ok(lic) => {
+                let features: Vec<String> = app.feature_schema().features.into_iter().filter(|f| lic.feature(f.slot)).map(|f| f.name).collect();
+                lines.extend([
+                    field("License ID", or_dash(Some(lic.id.as_str()))),
+                    field("Licensed To", format!("{} ({})", lic.name, lic.customer)),
+                    field("Policy", lic.version_policy.to_string()),
+                    field("Version", if lic.min_version.is_empty() { lic.version.clone() } else { format!("{} to {}", lic.min_version, lic.version) }),
+                    field("Valid", format!("{}-{:02} to {}-{:02}", lic.start_year, lic.start_month, lic.end_year, lic.end_month)),
+                    field("Features", if features.is_empty() { "-".to_string() } else { features.join(", ") }),
+                ]);
+            }
+            Err(e) => lines.push(Line::from(format!("Could not be decoded: {e}").red())),
+        }
+
+        lines.push(Line::default());
+        lines.push(heading("Fingerprint"));
+        match decode_hwinfo_from_string(&r.hwid, &app.info_public_key) {
+            Some(info) => lines.extend([
+                field("Hostname", info.hostname),
+                field("OS", format!("{} {}", info.name, info.version)),
+                field("CPU", info.cpu),
+                field("Memory", format!("{} MB", info.mem / 1024 / 1024)),
+                field("MAC Address", info.net),
+                field("Storage", format!("{} ({})", info.storage_name, info.storage_type)),
+                field("OS Lock", info.o_hash),
+                field("CPU Lock", info.c_hash),
+                field("Storage Lock", info.s_hash),
+                field("Network Lock", info.n_hash),
+            ]),
+            None => lines.push(Line::from("Could not be decoded with the application's keys".red())),
+        }
+
+        lines.push(Line::default());
+        lines.push(heading("License String"));
+        lines.push(Line::from(r.issued_license.clone()));
+        lines
+    }
+}
+
+fn draw_form(frame: &mut Frame, form: &Form) {
+    let height = u16::try_from(form.fields.len()).unwrap_or(u16::MAX).saturating_add(2);
+    let [area] = Layout::vertical([Constraint::Length(height)]).flex(Flex::Center).areas(frame.area());
+    let [area] = Layout::horizontal([Constraint::Percentage(70)]).flex(Flex::Center).areas(area);
+
+    let label_width = 16;
+    let inner = Rect {
+        x: area.x + 1,
+        y: area.y + 1,
+        width: area.width.saturating_sub(2),
+        height: area.height.saturating_sub(2),
+    };
+    // long values such as HWIDs show their end, where the cursor is
+    let room = usize::from(inner.width).saturating_sub(label_width + 1);
+    let shown = |value: &str| value.chars().skip(value.chars().count().saturating_sub(room)).collect::<String>();
+
+    let lines: Vec<Line> = form
+        .fields
+        .iter()
+        .enumerate()
+        .map(|(i, (label, value))| {
+            let style = if i == form.focus { Style::new().fg(Color::Yellow).add_modifier(Modifier::BOLD) } else { Style::new() };
+            Line::from(vec![Span::styled(format!("{label:<label_width$}"), style), Span::raw(shown(value))])
+        })
+        .collect();
+
+    frame.render_widget(Clear, area);
+    frame.render_widget(Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title(format!(" {} ", form.title))), area);
+
+    let x = inner.x.saturating_add(u16::try_from(label_width + shown(&form.fields[form.focus].1).chars().count()).unwrap_or(u16::MAX));
+    frame.set_cursor_position((x, inner.y + u16::try_from(form.focus).unwrap_or(0)));
+}
+
+/// Browse applications, customers and licenses full screen, and issue, renew or revoke in place
+pub async fn tui(pool: &Pool<Any>) -> Result<(), AdminError> {
+    // Ask for the master passphrase before the screen is taken over, actions must never prompt
+    keystore::unlock(pool).await?;
+    input::disable_prompts();
+    let mut tui = Tui::load(pool).await?;
+
+    // log lines would be drawn over the screen
+    let level = log::max_level();
+    log::set_max_level(LevelFilter::Off);
+
+    let mut terminal = ratatui::init();
+    let _ = execute!(stdout(), EnableBracketedPaste);
+    let result = tui.run(&mut terminal).await;
+    let _ = execute!(stdout(), DisableBracketedPaste);
+    ratatui::restore();
+
+    log::set_max_level(level);
+    result
+}
@@ -0,0 +1,103 @@
+//! `serve` answered over plain HTTP/1.1
+
+mod common;
+
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::process::{Child, Stdio};
+use std::time::Duration;
+
+use common::Admin;
+use serde_json::{Value, json};
+
+const TOKEN: &str = "integration-api-token";
+
+/// A running `serve`, stopped when dropped
+struct Server {
+    child: Child,
+    port: u16,
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// Starts `serve` on a free port with the token in the environment, ending in a newline as one read from a file often does
+fn serve(admin: &Admin) -> Server {
+    let port = TcpListener::bind("127.0.0.1:0").expect("free port").local_addr().expect("address").port();
+    let child = admin
+        .command(&["serve", "--listen", &format!("127.0.0.1:{port}")])
+        .env("RUSTLOCK_API_TOKEN", format!("{TOKEN}\n"))
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .expect("spawn serve");
+    let server = Server { child, port };
+
+    for _ in 0..100 {
+        if TcpStream::connect(("127.0.0.1", port)).is_ok() {
+            return server;
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    }
+    panic!("serve didn't start listening on port {port}");
+}
+
+impl Server {
+    /// Sends one request and returns the status code and the JSON body
+    fn request(&self, method: &str, path: &str, token: Option<&str>, body: Option<Value>) -> (u16, Value) {
+        let mut stream = TcpStream::connect(("127.0.0.1", self.port)).expect("connect");
+        let body = body.map(|body| body.to_string()).unwrap_or_default();
+        let auth = token.map(|token| format!("Authorization: Bearer {token}\r\n")).unwrap_or_default();
+        write!(stream, "{method} {path} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n{auth}Content-Type: application/json\r\nContent-Length: {}\r\n\r\n{body}", body.len()).expect("send request");
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).expect("read response");
+        let (head, body) = response.split_once("\r\n\r\n").expect("response head");
+        let status = head.split(' ').nth(1).and_then(|code| code.parse().ok()).expect("status code");
+        (status, serde_json::from_str(body).unwrap_or(Value::Null))
+    }
+
+    fn authorized(&self, method: &str, path: &str, body: Option<Value>) -> (u16, Value) {
+        self.request(method, path, Some(TOKEN), body)
+    }
+}
+
+#[test]
+fn missing_or_wrong_token_is_unauthorized() {
+    let admin = Admin::sqlite();
+    admin.ok(&["show", "customers"]);
+    let server = serve(&admin);
+
+    assert_eq!(server.request("GET", "/api/v1/customers", None, None).0, 401);
+    assert_eq!(server.request("GET", "/api/v1/customers", Some("not-the-api-token"), None).0, 401);
+    assert_eq!(server.authorized("GET", "/api/v1/customers", None), (200, json!([])));
+}
+
+#[test]
+fn create_issue_and_validate() {
+    let admin = Admin::sqlite();
+    admin.ok(&["add", "application", "--name", "Api App", "--feature", "Export", "--yes"]);
+    let hwid = admin.fingerprint(1, "ada-laptop");
+    let server = serve(&admin);
+
+    let (status, customer) = server.authorized("POST", "/api/v1/customers", Some(json!({"name": "Ada", "contact_email": "ada@example.com", "mobile": "555-0100"})));
+    assert_eq!(status, 201, "{customer}");
+
+    let (status, issued) = server.authorized("POST", "/api/v1/licenses", Some(json!({"application": 1, "customer": customer["id"], "hwid": hwid, "version": "1.4.0", "features": ["Export"]})));
+    assert_eq!(status, 201, "{issued}");
+
+    let (status, validated) = server.authorized("POST", "/api/v1/licenses/validate", Some(json!({"application": 1, "license": issued["issued_license"]})));
+    assert_eq!(status, 200, "{validated}");
+    assert_eq!(validated["valid"], true);
+    assert_eq!(validated["status"], "active");
+    assert_eq!(validated["license"]["customer"], customer["id"]);
+    assert_eq!(validated["license"]["f1"], true);
+
+    let (status, licenses) = server.authorized("GET", "/api/v1/licenses?application=1", None);
+    assert_eq!(status, 200, "{licenses}");
+    assert_eq!(validated["license"]["id"], licenses[0]["license_uid"]);
+}
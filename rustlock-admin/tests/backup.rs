@@ -0,0 +1,122 @@
+//! `backup`, `backup verify` and `restore` with the encrypted `.rlbk` format
+
+mod common;
+
+use std::io::{Cursor, Read, Write};
+use std::path::PathBuf;
+
+use argon2::Argon2;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use common::Admin;
+
+/// Magic, format version and salt, authenticated along with the contents
+const HEADER_LEN: usize = 4 + 1 + 16;
+const NONCE_LEN: usize = 24;
+
+fn backup(admin: &Admin) -> PathBuf {
+    let output = admin.command(&["backup"]).current_dir(admin.dir()).output().expect("run backup");
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let path = stdout.lines().find_map(|line| line.split_once("Backup created at: ")).expect("backup path").1;
+    PathBuf::from(path.trim())
+}
+
+fn customers(admin: &Admin) -> usize {
+    admin.json(&["show", "customers"]).as_array().expect("customers").len()
+}
+
+fn cipher(header: &[u8]) -> XChaCha20Poly1305 {
+    let mut key = [0u8; 32];
+    Argon2::default().hash_password_into(common::BACKUP_PASSPHRASE.as_bytes(), &header[HEADER_LEN - 16..], &mut key).expect("derive key");
+    XChaCha20Poly1305::new((&key).into())
+}
+
+/// Decrypt a backup, let `change` rewrite its ZIP and encrypt it again with the same header
+fn rewrite(path: &PathBuf, change: impl FnOnce(Vec<u8>) -> Vec<u8>) {
+    let bytes = std::fs::read(path).expect("read backup");
+    let (header, sealed) = bytes.split_at(HEADER_LEN);
+    let (nonce, msg) = sealed.split_at(NONCE_LEN);
+    let cipher = cipher(header);
+    let plain = cipher.decrypt(XNonce::from_slice(nonce), Payload { msg, aad: header }).expect("decrypt backup");
+
+    let changed = change(plain);
+    let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let sealed = cipher.encrypt(&nonce, Payload { msg: &changed, aad: header }).expect("encrypt backup");
+    std::fs::write(path, [header, nonce.as_slice(), &sealed].concat()).expect("write backup");
+}
+
+#[test]
+fn round_trip() {
+    let admin = Admin::sqlite();
+    admin.ok(&["add", "customer", "--name", "Ada", "--email", "ada@example.com", "--mobile", "555-0100"]);
+    let archive = backup(&admin);
+    let archive = archive.to_str().expect("path");
+
+    admin.ok(&["add", "customer", "--name", "Grace", "--email", "grace@example.com", "--mobile", "555-0101"]);
+    assert_eq!(customers(&admin), 2);
+
+    admin.ok(&["backup", "verify", archive]);
+    admin.ok(&["restore", archive, "--yes"]);
+    assert_eq!(customers(&admin), 1);
+}
+
+#[test]
+fn wrong_passphrase_is_refused() {
+    let admin = Admin::sqlite();
+    admin.ok(&["show", "customers"]);
+    let archive = backup(&admin);
+
+    let output = admin.command(&["backup", "verify", archive.to_str().expect("path")]).env("RUSTLOCK_BACKUP_PASSPHRASE", "not-the-passphrase").output().expect("run verify");
+    assert_eq!(output.status.code(), Some(3));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("wrong passphrase"));
+}
+
+#[test]
+fn corrupted_header_is_refused() {
+    let admin = Admin::sqlite();
+    admin.ok(&["show", "customers"]);
+    let archive = backup(&admin);
+
+    // One bit of the salt, the key no longer matches and the header fails authentication
+    let mut bytes = std::fs::read(&archive).expect("read backup");
+    bytes[HEADER_LEN - 1] ^= 1;
+    std::fs::write(&archive, &bytes).expect("write backup");
+    assert_eq!(admin.fails(&["backup", "verify", archive.to_str().expect("path")]).0, 3);
+
+    // An unknown format version is named as such
+    bytes[4] = 99;
+    std::fs::write(&archive, &bytes).expect("write backup");
+    let (code, stderr) = admin.fails(&["backup", "verify", archive.to_str().expect("path")]);
+    assert_eq!(code, 3);
+    assert!(stderr.contains("uses backup format 99"), "{stderr}");
+}
+
+#[test]
+fn manifest_mismatch_is_detected() {
+    let admin = Admin::sqlite();
+    admin.ok(&["add", "customer", "--name", "Ada", "--email", "ada@example.com", "--mobile", "555-0100"]);
+    let archive = backup(&admin);
+
+    // Correctly encrypted, but the database no longer matches the hash the manifest lists for it
+    rewrite(&archive, |plain| {
+        let mut original = zip::ZipArchive::new(Cursor::new(plain)).expect("backup ZIP");
+        let mut changed = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        for i in 0..original.len() {
+            let mut file = original.by_index(i).expect("entry");
+            let mut contents = Vec::new();
+            file.read_to_end(&mut contents).expect("read entry");
+            if file.name() == "rustlock.db" {
+                contents.extend_from_slice(b"appended");
+            }
+            changed.start_file(file.name(), zip::write::FileOptions::default()).expect("start entry");
+            changed.write_all(&contents).expect("write entry");
+        }
+        changed.finish().expect("finish ZIP").into_inner()
+    });
+
+    let (code, stderr) = admin.fails(&["backup", "verify", archive.to_str().expect("path")]);
+    assert_eq!(code, 3);
+    assert!(stderr.contains("rustlock.db doesn't match its hash in the manifest"), "{stderr}");
+}
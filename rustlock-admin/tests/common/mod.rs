@@ -0,0 +1,168 @@
+//! Runs the `rustlock-admin` binary against a throwaway database
+
+#![allow(dead_code)]
+
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+
+use serde_json::Value;
+use sqlx::{AnyPool, Row};
+use tempfile::TempDir;
+
+pub const MASTER_PASSPHRASE: &str = "integration-master";
+pub const BACKUP_PASSPHRASE: &str = "integration-backup";
+
+/// PostgreSQL database the ignored PostgreSQL tests run against, they fail when it isn't set.
+/// The database is emptied before each test
+pub const POSTGRES_URL_ENV: &str = "RUSTLOCK_TEST_POSTGRES_URL";
+
+/// One data directory with its own config, data and database
+pub struct Admin {
+    dir: TempDir,
+    pub db: String,
+}
+
+impl Admin {
+    /// Fresh SQLite database in a temporary directory
+    pub fn sqlite() -> Self {
+        let dir = tempfile::tempdir().expect("temp dir");
+        let db = dir.path().join("rustlock.db").display().to_string();
+        Self { dir, db }
+    }
+
+    /// The PostgreSQL database from [`POSTGRES_URL_ENV`], emptied first. `None` when it isn't set
+    pub async fn postgres() -> Option<Self> {
+        let url = std::env::var(POSTGRES_URL_ENV).ok().filter(|url| !url.is_empty())?;
+
+        let pool = connect(&url).await;
+        sqlx::query("DROP SCHEMA public CASCADE").execute(&pool).await.expect("drop schema");
+        sqlx::query("CREATE SCHEMA public").execute(&pool).await.expect("create schema");
+        pool.close().await;
+
+        Some(Self {
+            dir: tempfile::tempdir().expect("temp dir"),
+            db: url,
+        })
+    }
+
+    pub fn dir(&self) -> &Path {
+        self.dir.path()
+    }
+
+    pub fn url(&self) -> String {
+        if self.db.starts_with("postgres") { self.db.clone() } else { format!("sqlite://{}?mode=rwc", self.db) }
+    }
+
+    pub fn command(&self, args: &[&str]) -> Command {
+        let mut command = Command::new(env!("CARGO_BIN_EXE_rustlock-admin"));
+        command
+            .arg("--db")
+            .arg(&self.db)
+            .args(args)
+            .env("HOME", self.dir())
+            .env("XDG_DATA_HOME", self.dir().join("data"))
+            .env("XDG_CONFIG_HOME", self.dir().join("config"))
+            .env("RUSTLOCK_MASTER_PASSPHRASE", MASTER_PASSPHRASE)
+            .env("RUSTLOCK_BACKUP_PASSPHRASE", BACKUP_PASSPHRASE)
+            .env("RUSTLOCK_OPERATOR", "integration")
+            .env_remove("RUSTLOCK_DB")
+            .env_remove("RUSTLOCK_PROFILE")
+            .env_remove("RUSTLOCK_MASTER_KEY_FILE")
+            .stdin(Stdio::null());
+        command
+    }
+
+    pub fn run(&self, args: &[&str]) -> Output {
+        self.command(args).output().expect("run rustlock-admin")
+    }
+
+    /// Runs the command and returns its stdout, panicking with its logs when it fails
+    pub fn ok(&self, args: &[&str]) -> String {
+        let output = self.run(args);
+        assert!(output.status.success(), "rustlock-admin {args:?} failed with {}:\n{}", output.status, String::from_utf8_lossy(&output.stderr));
+        String::from_utf8(output.stdout).expect("utf-8 output")
+    }
+
+    /// Runs the command and returns its exit code and logs, panicking when it succeeds
+    pub fn fails(&self, args: &[&str]) -> (i32, String) {
+        let output = self.run(args);
+        assert!(!output.status.success(), "rustlock-admin {args:?} should have failed");
+        (output.status.code().unwrap_or(-1), String::from_utf8_lossy(&output.stderr).to_string())
+    }
+
+    /// Runs a command with `--format json` and parses its output
+    pub fn json(&self, args: &[&str]) -> Value {
+        let mut args = args.to_vec();
+        args.extend(["--format", "json"]);
+        serde_json::from_str(&self.ok(&args)).expect("JSON output")
+    }
+
+    pub async fn pool(&self) -> AnyPool {
+        connect(&self.url()).await
+    }
+
+    /// Run the statements of `tests/fixtures/<name>` against the database
+    pub async fn load_fixture(&self, name: &str) {
+        let sql = std::fs::read_to_string(fixture(name)).expect("read fixture");
+        let pool = self.pool().await;
+        for statement in sql.split(';').map(str::trim).filter(|s| !s.lines().all(|l| l.trim().is_empty() || l.trim().starts_with("--"))) {
+            sqlx::query(statement).execute(&pool).await.unwrap_or_else(|e| panic!("fixture statement failed: {e}\n{statement}"));
+        }
+        pool.close().await;
+    }
+
+    /// Issue a license and return its ID
+    pub fn issue(&self, application: i64, customer: i64, hostname: &str, extra: &[&str]) -> i64 {
+        let hwid = self.fingerprint(application, hostname);
+        let (application, customer) = (application.to_string(), customer.to_string());
+        let mut args = vec!["issue", "--application", &application, "--customer", &customer, "--hwid", &hwid, "--support-years", "1", "--version", "1.4.0"];
+        args.extend(extra);
+        self.json(&args)[0]["id"].as_i64().expect("license ID")
+    }
+
+    /// Fingerprint of a made up machine, encrypted for `application` the way `rustlock-core` does it
+    pub fn fingerprint(&self, application: i64, hostname: &str) -> String {
+        let config = self.json(&["show", "applications", "--config", "--application", &application.to_string()]);
+        let key = config[0]["info_private_key"].as_str().expect("info key");
+        fingerprint(key, hostname)
+    }
+}
+
+pub fn fixture(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("fixtures").join(name)
+}
+
+pub async fn connect(url: &str) -> AnyPool {
+    sqlx::any::install_default_drivers();
+    AnyPool::connect(url).await.unwrap_or_else(|e| panic!("connect to {url}: {e}"))
+}
+
+/// Every row of every table as text, to compare a database before and after
+pub async fn dump(pool: &AnyPool, tables: &[&str]) -> Vec<String> {
+    let mut rows = Vec::new();
+    for table in tables {
+        for row in sqlx::query(&format!("SELECT * FROM {table} ORDER BY 1")).fetch_all(pool).await.expect("dump table") {
+            let values: Vec<String> = (0..row.len()).map(|i| row.try_get::<String, _>(i).or_else(|_| row.try_get::<i64, _>(i).map(|v| v.to_string())).unwrap_or_else(|_| "NULL".to_string())).collect();
+            rows.push(format!("{table}: {}", values.join(" | ")));
+        }
+    }
+    rows
+}
+
+pub fn fingerprint(info_key: &str, hostname: &str) -> String {
+    let info = rustlock_core::sysinfo::SysInfo {
+        hostname: hostname.to_string(),
+        name: "Linux".to_string(),
+        version: "6.1".to_string(),
+        cpu: "Test CPU".to_string(),
+        storage_name: "nvme0n1".to_string(),
+        mem: 16384,
+        c_hash: "cpu".to_string(),
+        o_hash: format!("os-{hostname}"),
+        n_hash: "net".to_string(),
+        s_hash: "storage".to_string(),
+        ..Default::default()
+    };
+    let key = hex::decode(info_key).expect("hex key");
+    hex::encode_upper(ecies::encrypt(&key, &rmp_serde::to_vec(&info).expect("msgpack")).expect("encrypt"))
+}
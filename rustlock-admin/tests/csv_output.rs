@@ -0,0 +1,22 @@
+//! `--format csv` output
+
+mod common;
+
+use common::Admin;
+
+#[test]
+fn empty_results_still_have_a_header() {
+    let admin = Admin::sqlite();
+
+    let csv = admin.ok(&["show", "customers", "--format", "csv"]);
+    assert_eq!(csv, "id,name,contact_email,mobile,archived_at\n");
+}
+
+#[test]
+fn records_follow_the_header() {
+    let admin = Admin::sqlite();
+    admin.ok(&["add", "customer", "--name", "Ada", "--email", "ada@example.com", "--mobile", "555-0100"]);
+
+    let csv = admin.ok(&["show", "customers", "--format", "csv"]);
+    assert_eq!(csv, "id,name,contact_email,mobile,archived_at\n1,Ada,ada@example.com,555-0100,\n");
+}
@@ -0,0 +1,110 @@
+//! `deliver --send` against a stand-in SMTP server
+
+mod common;
+
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::thread::JoinHandle;
+
+use common::Admin;
+
+/// What the client sent in one SMTP session
+#[derive(Debug, Default)]
+struct Received {
+    from: String,
+    recipients: Vec<String>,
+    data: String,
+}
+
+/// Accepts one session on a local port and answers every command with success
+fn smtp_server() -> (u16, JoinHandle<Received>) {
+    let listener = TcpListener::bind("127.0.0.1:0").expect("bind SMTP listener");
+    let port = listener.local_addr().expect("listener address").port();
+
+    let handle = std::thread::spawn(move || {
+        let (stream, _) = listener.accept().expect("accept SMTP client");
+        session(stream)
+    });
+    (port, handle)
+}
+
+fn session(mut stream: TcpStream) -> Received {
+    let mut reader = BufReader::new(stream.try_clone().expect("clone stream"));
+    let mut received = Received::default();
+    let reply = |stream: &mut TcpStream, line: &str| stream.write_all(format!("{line}\r\n").as_bytes()).expect("write reply");
+
+    reply(&mut stream, "220 localhost ESMTP stand-in");
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).expect("read command") == 0 {
+            break;
+        }
+        let command = line.trim_end();
+        let verb = command.split([' ', ':']).next().unwrap_or_default().to_ascii_uppercase();
+
+        match verb.as_str() {
+            "EHLO" | "HELO" => reply(&mut stream, "250 localhost"),
+            "MAIL" => {
+                received.from = command.to_string();
+                reply(&mut stream, "250 OK");
+            }
+            "RCPT" => {
+                received.recipients.push(command.to_string());
+                reply(&mut stream, "250 OK");
+            }
+            "DATA" => {
+                reply(&mut stream, "354 End data with <CR><LF>.<CR><LF>");
+                loop {
+                    let mut data = String::new();
+                    reader.read_line(&mut data).expect("read data");
+                    if data == ".\r\n" || data.is_empty() {
+                        break;
+                    }
+                    received.data.push_str(&data);
+                }
+                reply(&mut stream, "250 OK queued");
+            }
+            "QUIT" => {
+                reply(&mut stream, "221 Bye");
+                break;
+            }
+            _ => reply(&mut stream, "250 OK"),
+        }
+    }
+    received
+}
+
+#[test]
+fn sends_the_license_with_its_attachment() {
+    let admin = Admin::sqlite();
+    admin.ok(&["add", "application", "--name", "Mail App", "--feature", "Export", "--yes"]);
+    admin.ok(&["add", "customer", "--name", "Ada", "--email", "ada@example.com", "--mobile", "555-0100"]);
+    let id = admin.issue(1, 1, "ada-laptop", &[]);
+
+    let (port, server) = smtp_server();
+    let outbox = admin.dir().join("outbox");
+    admin.ok(&[
+        "deliver",
+        "--license",
+        &id.to_string(),
+        "--from",
+        "licenses@example.com",
+        "--outbox",
+        outbox.to_str().expect("outbox path"),
+        "--send",
+        "--smtp",
+        &format!("smtp://127.0.0.1:{port}"),
+    ]);
+    let received = server.join().expect("SMTP server");
+
+    assert!(received.from.contains("<licenses@example.com>"), "{received:?}");
+    assert_eq!(received.recipients.len(), 1, "{received:?}");
+    assert!(received.recipients[0].contains("<ada@example.com>"), "{received:?}");
+    assert!(received.data.contains("Subject: Your Mail App license"), "{}", received.data);
+    assert!(received.data.contains(&format!("filename=\"mail-app-license-{id}.lic\"")), "{}", received.data);
+
+    let issued = admin.json(&["show", "licenses", "--application", "1", "--customer", "1"]);
+    let license = issued[0]["issued_license"].as_str().expect("issued license");
+    let data: String = received.data.split("\r\n").collect();
+    assert!(data.contains(&license[..64]), "the license string is missing from the message");
+}
@@ -0,0 +1,58 @@
+-- Database as written by the first release of rustlock-admin, before schema_version existed.
+-- Keys are plaintext, licenses are stored without their decoded details.
+
+CREATE TABLE IF NOT EXISTS customers (
+    id            INTEGER PRIMARY KEY AUTOINCREMENT,
+    name          TEXT NOT NULL,
+    contact_email TEXT NOT NULL,
+    mobile        TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS applications (
+    id                    INTEGER PRIMARY KEY AUTOINCREMENT,
+    name                  TEXT NOT NULL,
+    lic_public_key        TEXT NOT NULL,
+    lic_private_key       TEXT NOT NULL,
+    blocked_customer_ids  TEXT NOT NULL,
+    machine_id_key        TEXT NOT NULL,
+    info_public_key       TEXT NOT NULL,
+    info_private_key      TEXT NOT NULL,
+    feature1              TEXT,
+    feature2              TEXT,
+    feature3              TEXT,
+    feature4              TEXT,
+    feature5              TEXT
+);
+
+CREATE TABLE IF NOT EXISTS licenses (
+    id              INTEGER PRIMARY KEY AUTOINCREMENT,
+    hwid            TEXT NOT NULL,
+    support_years   INTEGER NOT NULL,
+    customer_id     INTEGER NOT NULL,
+    application_id  INTEGER NOT NULL,
+    issued_license  TEXT,
+    FOREIGN KEY(customer_id) REFERENCES customers(id),
+    FOREIGN KEY(application_id) REFERENCES applications(id)
+);
+
+INSERT INTO customers (name, contact_email, mobile) VALUES ('Acme Pty Ltd', 'ops@acme.test', '555-0100');
+INSERT INTO customers (name, contact_email, mobile) VALUES ('Globex', 'it@globex.test', '555-0199');
+
+INSERT INTO applications (name, lic_public_key, lic_private_key, blocked_customer_ids, machine_id_key, info_public_key, info_private_key, feature1, feature2, feature3, feature4, feature5)
+VALUES (
+    'Designer',
+    '52B200EBA6B8290587D7BA412B91EA6559BF0FFB41EEF04293048B1B9F36DE1E',
+    '042CD97E9785BA169741EAF0D3189EB8025D0DAACE898A0C5BDC3222565A04C432AD7DEC3A0080074B3E6D13438130AFCF4D138CDB3EC57476D6A78432521263CD',
+    '[9999]',
+    '0429A7A967C78A08059E5C765BCF7A9260BDC92A2797A789D9BAAEB48C04222F826228C01A801C863493C8469AB516459CE3A3610F88F1C63A6A35654AD6AC005B',
+    '799B4874C970039887ABA92A387D51AD3D9213BE41FFFAD9DA9923BF886BDB5B',
+    '0417DCE82665D025231697D17BAAE0BC278EA8837984BFA1111FCD9AAB9FE7ECFA46BE0DC300AF54D022FD2775CCE3F0255EA2D0D5F33400D3CC4593A00FD569F0',
+    'Export PDF', NULL, 'Cloud Sync', NULL, NULL
+);
+
+INSERT INTO licenses (hwid, support_years, customer_id, application_id, issued_license)
+VALUES (
+    '0488200FDFF8C4B6C9D7EBADC125FB632B6D1DF8FCFFE5C4F78A35DB93810CBA451686673CE136DD9178DB1DB525043DF4C35880E84E84BF4D5E15F6C67D491BE612F097145039777EADF159A7BE685C12C912CF483E7523A18A0A6BFBB837595D38E9E07E07B2C204EE9A0427C3A4B4D90A304EAEC43814E682ED51710AB10118EEDA66ED8458666F169621E070C9DB8A8F49',
+    1, 1, 1,
+    '04DB2361392ADC6ACD8FB9638899177C49CDBBED8C98AD5720652F69BC2D077CBF60AAE09B16E3EEB58276414F8E665E18C222AD6C3AF9853F7845EE52B337BB923F5F2F85B713C8EC099E7F6A9889FBF00AD1A61AACDE83A446EC0DF946BE60F45CD310721F2C7248B233C9B4F8807107013B52763501504E617E7762BBF30845F14658EA2043493DE4693C6482'
+);
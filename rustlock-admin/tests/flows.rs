@@ -0,0 +1,163 @@
+//! Customer, application and license flows, run against SQLite, and against PostgreSQL with `--include-ignored` and `RUSTLOCK_TEST_POSTGRES_URL` set
+
+mod common;
+
+use common::Admin;
+use rustlock_core::license::RevocationList;
+use serde_json::Value;
+
+const TABLES: [&str; 6] = ["customers", "applications", "licenses", "keystore", "audit_log", "schema_version"];
+
+fn licenses(admin: &Admin) -> Vec<Value> {
+    admin.json(&["show", "licenses", "--application", "1", "--customer", "1"]).as_array().expect("licenses").clone()
+}
+
+fn status(admin: &Admin, id: i64) -> String {
+    let licenses = licenses(admin);
+    let license = licenses.iter().find(|l| l["id"] == id).unwrap_or_else(|| panic!("license {id} in {licenses:?}"));
+    license["status"].as_str().expect("status").to_string()
+}
+
+/// Migrations bring a new database to the latest version, and running them again changes nothing
+async fn migrations_apply_once(admin: Admin) {
+    admin.ok(&["show", "customers"]);
+    let pool = admin.pool().await;
+    let versions: Vec<i64> = sqlx::query_scalar("SELECT version FROM schema_version ORDER BY version").fetch_all(&pool).await.expect("versions");
+    assert_eq!(versions, (1..=versions.len() as i64).collect::<Vec<_>>());
+
+    let before = common::dump(&pool, &TABLES).await;
+    admin.ok(&["show", "customers"]);
+    assert_eq!(common::dump(&pool, &TABLES).await, before);
+    pool.close().await;
+}
+
+/// Add a customer and an application, issue, renew, revoke and unrevoke a license
+async fn license_lifecycle(admin: Admin) {
+    admin.ok(&["add", "customer", "--name", "Ada", "--email", "ada@example.com", "--mobile", "555-0100"]);
+    admin.ok(&["add", "application", "--name", "Designer", "--feature", "Export PDF", "--feature", "Cloud Sync", "--yes"]);
+
+    let customers = admin.json(&["show", "customers"]);
+    assert_eq!(customers[0]["name"], "Ada");
+    let config = admin.json(&["show", "applications", "--config", "--application", "1"]);
+    assert_eq!(config[0]["name"], "Designer");
+    assert_eq!(config[0]["feature2"], "Cloud Sync");
+
+    let first = admin.issue(1, 1, "ada-laptop", &["--feature", "Export PDF"]);
+    let issued = licenses(&admin);
+    assert_eq!(issued.len(), 1);
+    assert_eq!(issued[0]["status"], "active");
+    assert_eq!(issued[0]["features"], serde_json::json!(["Export PDF"]));
+    assert_eq!(issued[0]["hostname"], "ada-laptop");
+
+    let renewed = admin.json(&["renew", "--license", &first.to_string(), "--years", "1"])[0]["id"].as_i64().expect("renewed ID");
+    assert_eq!(status(&admin, first), "superseded");
+    assert_eq!(status(&admin, renewed), "active");
+
+    // The superseded license can't be renewed a second time
+    admin.fails(&["renew", "--license", &first.to_string(), "--years", "1"]);
+
+    admin.ok(&["revoke", "--license", &renewed.to_string(), "--reason", "chargeback", "--yes"]);
+    assert_eq!(status(&admin, renewed), "revoked");
+
+    // The revocation list covers the renewal and the license it replaced, sealed for the application
+    let path = admin.dir().join("revocations.json");
+    admin.ok(&["export", "revocations", "--application", "1", "--output", path.to_str().expect("path")]);
+    let list: RevocationList = serde_json::from_str(&std::fs::read_to_string(&path).expect("read list")).expect("revocation list");
+    let uids: Vec<Value> = licenses(&admin).iter().map(|l| l["license_uid"].clone()).collect();
+    assert_eq!(list.revoked.iter().map(|uid| Value::from(uid.as_str())).collect::<Vec<_>>(), uids);
+    list.open(config[0]["license_public_key"].as_str().expect("license key")).expect("sealed with the application's key");
+
+    admin.ok(&["unrevoke", "--license", &renewed.to_string(), "--reason", "resolved", "--yes"]);
+    assert_eq!(status(&admin, renewed), "active");
+
+    let audit = admin.json(&["show", "audit"]);
+    let actions: Vec<&str> = audit.as_array().expect("audit").iter().map(|e| e["action"].as_str().expect("action")).collect();
+    assert_eq!(actions, ["create", "set_passphrase", "create", "issue", "renew", "revoke", "unrevoke"]);
+}
+
+/// Operators writing at the same time each chain their audit entry to the one before
+async fn concurrent_writes_keep_the_audit_chain(admin: Admin) {
+    admin.ok(&["show", "customers"]);
+
+    let writers: Vec<_> = (0..6)
+        .map(|i| {
+            let (name, email) = (format!("Customer {i}"), format!("c{i}@example.com"));
+            admin.command(&["add", "customer", "--name", &name, "--email", &email, "--mobile", "555-0100"]).stdout(std::process::Stdio::null()).spawn().expect("spawn writer")
+        })
+        .collect();
+    for writer in writers {
+        let output = writer.wait_with_output().expect("writer");
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    }
+
+    // `show audit` fails when the hash chain is broken
+    let audit = admin.json(&["show", "audit"]);
+    assert_eq!(audit.as_array().expect("audit").len(), 6);
+}
+
+/// `show audit` notices an entry that was changed or removed behind the tool's back, and checks `--since`
+async fn audit_log_detects_tampering(admin: Admin) {
+    for i in 0..3 {
+        admin.ok(&["add", "customer", "--name", &format!("Customer {i}"), "--email", &format!("c{i}@example.com"), "--mobile", "555-0100"]);
+    }
+    assert_eq!(admin.json(&["show", "audit", "--since", "2000-01-01"]).as_array().expect("audit").len(), 3);
+    assert_eq!(admin.json(&["show", "audit", "--since", "2999-01-01T00:00:00Z"]).as_array().expect("audit").len(), 0);
+    assert_eq!(admin.fails(&["show", "audit", "--since", "last week"]).0, 3);
+
+    // The append-only triggers are what keeps the tool itself from doing this
+    let pool = admin.pool().await;
+    let drop_triggers: &[&str] = if admin.url().starts_with("postgres") {
+        &["DROP TRIGGER audit_log_append_only ON audit_log"]
+    } else {
+        &["DROP TRIGGER audit_log_no_update", "DROP TRIGGER audit_log_no_delete"]
+    };
+    for statement in drop_triggers {
+        sqlx::query(statement).execute(&pool).await.expect("drop trigger");
+    }
+
+    let after: String = sqlx::query_scalar("SELECT after FROM audit_log WHERE id = 2").fetch_one(&pool).await.expect("entry 2");
+    sqlx::query("UPDATE audit_log SET after = $1 WHERE id = 2").bind(after.replace("Customer 1", "Someone else")).execute(&pool).await.expect("tamper");
+    let (code, stderr) = admin.fails(&["show", "audit"]);
+    assert_eq!(code, 3);
+    assert!(stderr.contains("broken at entry 2"), "{stderr}");
+
+    sqlx::query("UPDATE audit_log SET after = $1 WHERE id = 2").bind(&after).execute(&pool).await.expect("undo");
+    admin.ok(&["show", "audit"]);
+
+    sqlx::query("DELETE FROM audit_log WHERE id = 2").execute(&pool).await.expect("remove");
+    let (code, stderr) = admin.fails(&["show", "audit"]);
+    assert_eq!(code, 3);
+    assert!(stderr.contains("broken at entry 3"), "{stderr}");
+    pool.close().await;
+}
+
+#[tokio::test]
+async fn sqlite_migrations_apply_once() {
+    migrations_apply_once(Admin::sqlite()).await;
+}
+
+#[tokio::test]
+async fn sqlite_license_lifecycle() {
+    license_lifecycle(Admin::sqlite()).await;
+}
+
+#[tokio::test]
+async fn sqlite_concurrent_writes_keep_the_audit_chain() {
+    concurrent_writes_keep_the_audit_chain(Admin::sqlite()).await;
+}
+
+#[tokio::test]
+async fn sqlite_audit_log_detects_tampering() {
+    audit_log_detects_tampering(Admin::sqlite()).await;
+}
+
+/// One test for every flow, they share the database
+#[tokio::test]
+#[ignore = "needs RUSTLOCK_TEST_POSTGRES_URL pointing at a scratch PostgreSQL database"]
+async fn postgres_flows() {
+    let postgres = || async { Admin::postgres().await.unwrap_or_else(|| panic!("{} is not set", common::POSTGRES_URL_ENV)) };
+    migrations_apply_once(postgres().await).await;
+    license_lifecycle(postgres().await).await;
+    concurrent_writes_keep_the_audit_chain(postgres().await).await;
+    audit_log_detects_tampering(postgres().await).await;
+}
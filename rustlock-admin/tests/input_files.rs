@@ -0,0 +1,42 @@
+//! Command inputs read from `--input` files, merged with the flags given on the command line
+
+mod common;
+
+use common::Admin;
+
+#[test]
+fn switches_in_the_input_file_apply_when_the_flag_is_left_out() {
+    let admin = Admin::sqlite();
+    let app = admin.dir().join("app.json");
+    std::fs::write(&app, r#"{ "name": "FileApp", "features": ["Export"], "yes": true }"#).expect("write input");
+
+    admin.ok(&["add", "application", "--input", app.to_str().expect("path")]);
+    let config = admin.json(&["show", "applications", "--config", "--application", "1"]);
+    assert_eq!(config[0]["name"], "FileApp");
+    assert_eq!(config[0]["feature1"], "Export");
+
+    admin.ok(&["add", "customer", "--name", "Ada", "--email", "ada@example.com", "--mobile", "555-0100"]);
+    let id = admin.issue(1, 1, "ada-laptop", &[]).to_string();
+
+    let revoke = admin.dir().join("revoke.toml");
+    std::fs::write(&revoke, format!("license = {id}\nreason = \"chargeback\"\nyes = true\n")).expect("write input");
+    admin.ok(&["revoke", "--input", revoke.to_str().expect("path")]);
+
+    let licenses = admin.json(&["show", "licenses", "--application", "1", "--customer", "1"]);
+    assert_eq!(licenses[0]["status"], "revoked");
+}
+
+#[test]
+fn flags_win_over_the_input_file() {
+    let admin = Admin::sqlite();
+    let app = admin.dir().join("app.json");
+    std::fs::write(&app, r#"{ "name": "FileApp", "yes": false }"#).expect("write input");
+
+    admin.ok(&["add", "application", "--input", app.to_str().expect("path"), "--name", "FlagApp", "--yes"]);
+    let config = admin.json(&["show", "applications", "--config", "--application", "1"]);
+    assert_eq!(config[0]["name"], "FlagApp");
+
+    // Without `--yes` from either source there is nobody to confirm
+    let (code, _) = admin.fails(&["add", "application", "--input", app.to_str().expect("path")]);
+    assert_eq!(code, 2);
+}
@@ -0,0 +1,44 @@
+//! `issue-batch` stores every license or none, and only leaves an output file when it stored them
+
+mod common;
+
+use common::Admin;
+
+fn batch(admin: &Admin) -> std::path::PathBuf {
+    admin.ok(&["add", "application", "--name", "Batch App", "--feature", "Export", "--yes"]);
+    admin.ok(&["add", "customer", "--name", "Ada", "--email", "ada@example.com", "--mobile", "555-0100"]);
+
+    let rows: Vec<_> = ["ada-laptop", "ada-desktop"].iter().map(|host| serde_json::json!({"customer": 1, "application": 1, "hwid": admin.fingerprint(1, host), "version": "1.4.0"})).collect();
+    let path = admin.dir().join("batch.json");
+    std::fs::write(&path, serde_json::to_string(&rows).expect("rows")).expect("write batch");
+    path
+}
+
+fn issued(admin: &Admin) -> usize {
+    admin.json(&["show", "licenses", "--application", "1", "--customer", "1"]).as_array().expect("licenses").len()
+}
+
+#[test]
+fn writes_the_output_once_the_licenses_are_stored() {
+    let admin = Admin::sqlite();
+    let file = batch(&admin);
+    let output = admin.dir().join("issued.csv");
+
+    admin.ok(&["issue-batch", file.to_str().expect("path"), "--output", output.to_str().expect("path"), "--yes"]);
+
+    assert_eq!(issued(&admin), 2);
+    assert_eq!(std::fs::read_to_string(&output).expect("output").lines().count(), 3);
+    assert!(!admin.dir().join("issued.csv.part").exists());
+}
+
+#[test]
+fn an_unwritable_output_issues_nothing() {
+    let admin = Admin::sqlite();
+    let file = batch(&admin);
+    let output = admin.dir().join("missing").join("issued.csv");
+
+    admin.fails(&["issue-batch", file.to_str().expect("path"), "--output", output.to_str().expect("path"), "--yes"]);
+
+    assert_eq!(issued(&admin), 0);
+    assert!(!output.exists());
+}
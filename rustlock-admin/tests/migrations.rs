@@ -0,0 +1,85 @@
+//! Migrating a database written by the first release, from before `schema_version` existed
+
+mod common;
+
+use common::Admin;
+use sqlx::AnyPool;
+
+const TABLES: [&str; 6] = ["customers", "applications", "licenses", "keystore", "audit_log", "schema_version"];
+
+async fn schema_version(pool: &AnyPool) -> i64 {
+    sqlx::query_scalar("SELECT MAX(version) FROM schema_version").fetch_one(pool).await.expect("schema version")
+}
+
+/// Column names of every table, to compare a migrated schema against a fresh one
+async fn columns(pool: &AnyPool) -> Vec<String> {
+    sqlx::query_scalar("SELECT m.name || '.' || p.name FROM sqlite_master m, pragma_table_info(m.name) p WHERE m.type = 'table' AND m.name != 'sqlite_sequence' ORDER BY m.name, p.name")
+        .fetch_all(pool)
+        .await
+        .expect("columns")
+}
+
+fn backups(admin: &Admin) -> Vec<String> {
+    std::fs::read_dir(admin.dir().join("backups")).map(|dir| dir.map(|e| e.expect("entry").file_name().to_string_lossy().to_string()).collect()).unwrap_or_default()
+}
+
+#[tokio::test]
+async fn baseline_database_migrates_to_the_latest_schema_with_its_data() {
+    let admin = Admin::sqlite();
+    admin.load_fixture("baseline.sql").await;
+
+    let customers = admin.json(&["show", "customers"]);
+
+    let fresh = Admin::sqlite();
+    fresh.ok(&["show", "customers"]);
+    let (pool, fresh_pool) = (admin.pool().await, fresh.pool().await);
+    assert_eq!(schema_version(&pool).await, schema_version(&fresh_pool).await);
+    assert_eq!(columns(&pool).await, columns(&fresh_pool).await);
+
+    // The old data is still there
+    let names: Vec<&str> = customers.as_array().expect("customers").iter().map(|c| c["name"].as_str().expect("name")).collect();
+    assert_eq!(names, ["Acme Pty Ltd", "Globex"]);
+    assert_eq!(customers[0]["contact_email"], "ops@acme.test");
+
+    let config = admin.json(&["show", "applications", "--config", "--application", "1"]);
+    assert_eq!(config[0]["name"], "Designer");
+    assert_eq!(config[0]["version_policy"], "major_minor");
+    assert_eq!(config[0]["feature3"], "Cloud Sync");
+
+    // Secret keys are encrypted, and still decrypt to keys that read the old license
+    let sealed: Vec<String> = sqlx::query_scalar("SELECT lic_private_key FROM applications").fetch_all(&pool).await.expect("keys");
+    assert!(sealed.iter().all(|key| key.starts_with("enc:")), "{sealed:?}");
+
+    let licenses = admin.json(&["show", "licenses", "--application", "1", "--customer", "1"]);
+    let license = &licenses[0];
+    assert_eq!(license["status"], "active");
+    assert_eq!(license["licensed_name"], "Acme Pty Ltd");
+    assert_eq!(license["version"], "2.1.9999");
+    assert_eq!(license["end"], "2026-03");
+    assert_eq!(license["features"], serde_json::json!(["Export PDF", "Cloud Sync"]));
+    assert_eq!(license["hostname"], "build-01");
+
+    // A backup of the unversioned database was taken before migrating
+    let backups = backups(&admin);
+    assert_eq!(backups.len(), 1, "{backups:?}");
+    assert!(backups[0].starts_with("rustlock-pre-migration-v1-"), "{backups:?}");
+}
+
+#[tokio::test]
+async fn migrating_again_changes_nothing() {
+    let admin = Admin::sqlite();
+    admin.load_fixture("baseline.sql").await;
+    admin.ok(&["show", "customers"]);
+
+    let pool = admin.pool().await;
+    let before = common::dump(&pool, &TABLES).await;
+    let backups_before = backups(&admin);
+
+    let output = admin.run(&["show", "customers"]);
+    assert!(output.status.success());
+    let logs = String::from_utf8_lossy(&output.stderr);
+    assert!(!logs.contains("migrated to version"), "{logs}");
+
+    assert_eq!(common::dump(&pool, &TABLES).await, before);
+    assert_eq!(backups(&admin), backups_before);
+}
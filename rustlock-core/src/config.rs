@@ -0,0 +1,149 @@
+use std::path::Path;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::RustLock;
+use crate::error::RustLockErrors;
+use crate::features::{FeatureSchema, FeatureSlot};
+use crate::license::VersionPolicy;
+
+/// Application name. The `ENV_*` variables are read by [`AppConfig::from_env`] and written to `.env` files by `rustlock-admin export config`
+pub const ENV_APPLICATION: &str = "RUSTLOCK_APPLICATION";
+pub const ENV_LICENSE_KEY: &str = "RUSTLOCK_LICENSE_KEY";
+pub const ENV_MACHINE_KEY: &str = "RUSTLOCK_MACHINE_KEY";
+pub const ENV_INFO_KEY: &str = "RUSTLOCK_INFO_KEY";
+/// Comma separated customer IDs
+pub const ENV_BLOCKED_CUSTOMERS: &str = "RUSTLOCK_BLOCKED_CUSTOMERS";
+pub const ENV_VERSION_POLICY: &str = "RUSTLOCK_VERSION_POLICY";
+/// Prefix of the feature names, `RUSTLOCK_FEATURE_1` to `RUSTLOCK_FEATURE_5`
+pub const ENV_FEATURE_PREFIX: &str = "RUSTLOCK_FEATURE_";
+
+/// Everything an application needs to validate its licenses, written by `rustlock-admin export config`
+#[derive(Serialize, Deserialize, Default, Debug, Eq, PartialEq, Clone)]
+pub struct AppConfig {
+    pub application: String,
+    /// `license_key` of [`RustLock::new`]
+    pub license_key: String,
+    /// `mid_key` of [`RustLock::new`]
+    pub machine_key: String,
+    /// `info_key` of [`RustLock::new`]
+    pub info_key: String,
+    #[serde(default)]
+    pub blocked_customers: Vec<u64>,
+    /// Licenses carry their own policy, this tells the application whether it has to call [`RustLock::with_release_date`]
+    #[serde(default, serialize_with = "policy_to_str", deserialize_with = "policy_from_str")]
+    pub version_policy: VersionPolicy,
+    #[serde(default)]
+    pub features: Vec<FeatureSlot>,
+}
+
+fn policy_to_str<S: Serializer>(policy: &VersionPolicy, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(policy.as_str())
+}
+
+fn policy_from_str<'de, D: Deserializer<'de>>(deserializer: D) -> Result<VersionPolicy, D::Error> {
+    String::deserialize(deserializer)?.parse().map_err(serde::de::Error::custom)
+}
+
+impl AppConfig {
+    /// # Errors
+    /// Will return `Err` if `json` isn't a valid config
+    pub fn from_json(json: &str) -> Result<Self, RustLockErrors> {
+        serde_json::from_str(json).map_err(|e| RustLockErrors::InvalidConfig(e.to_string()))
+    }
+
+    /// # Errors
+    /// Will return `Err` if `toml` isn't a valid config
+    #[cfg(feature = "toml")]
+    pub fn from_toml(toml: &str) -> Result<Self, RustLockErrors> {
+        toml::from_str(toml).map_err(|e| RustLockErrors::InvalidConfig(e.to_string()))
+    }
+
+    /// Reads the `RUSTLOCK_*` variables of a `.env` file
+    /// # Errors
+    /// Will return `Err` if a required variable is missing or a value can't be parsed
+    pub fn from_dotenv(contents: &str) -> Result<Self, RustLockErrors> {
+        let vars: Vec<(String, String)> = contents
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .filter_map(|line| line.split_once('='))
+            .map(|(key, value)| (key.trim().to_string(), unquote(value.trim())))
+            .collect();
+
+        Self::from_vars(|name| vars.iter().find(|(key, _)| key == name).map(|(_, value)| value.clone()))
+    }
+
+    /// Reads the `RUSTLOCK_*` variables of the process environment
+    /// # Errors
+    /// Will return `Err` if a required variable is missing or a value can't be parsed
+    pub fn from_env() -> Result<Self, RustLockErrors> {
+        Self::from_vars(|name| std::env::var(name).ok())
+    }
+
+    /// Loads a `.json`, `.toml` or `.env` file, chosen by its extension
+    /// # Errors
+    /// Will return `Err` if the file can't be read or isn't a valid config
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, RustLockErrors> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path).map_err(|e| RustLockErrors::InvalidConfig(format!("{}: {e}", path.display())))?;
+
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("json") => Self::from_json(&contents),
+            #[cfg(feature = "toml")]
+            Some("toml") => Self::from_toml(&contents),
+            _ if path.file_name().and_then(|n| n.to_str()).is_some_and(|n| n == ".env" || n.ends_with(".env")) => Self::from_dotenv(&contents),
+            _ => Err(RustLockErrors::InvalidConfig(format!("{} is not a supported config file", path.display()))),
+        }
+    }
+
+    fn from_vars(var: impl Fn(&str) -> Option<String>) -> Result<Self, RustLockErrors> {
+        let required = |name: &str| var(name).ok_or_else(|| RustLockErrors::InvalidConfig(format!("{name} is not set")));
+
+        let blocked_customers = var(ENV_BLOCKED_CUSTOMERS)
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|id| !id.is_empty())
+            .map(|id| id.parse().map_err(|_| RustLockErrors::InvalidConfig(format!("{ENV_BLOCKED_CUSTOMERS}: '{id}' is not a customer ID"))))
+            .collect::<Result<_, _>>()?;
+
+        let version_policy = match var(ENV_VERSION_POLICY) {
+            Some(policy) => policy.parse().map_err(|e| RustLockErrors::InvalidConfig(format!("{ENV_VERSION_POLICY}: {e}")))?,
+            None => VersionPolicy::default(),
+        };
+
+        let features = (1..=5).filter_map(|slot| var(&format!("{ENV_FEATURE_PREFIX}{slot}")).filter(|name| !name.is_empty()).map(|name| FeatureSlot { slot, name })).collect();
+
+        Ok(Self {
+            application: var(ENV_APPLICATION).unwrap_or_default(),
+            license_key: required(ENV_LICENSE_KEY)?,
+            machine_key: required(ENV_MACHINE_KEY)?,
+            info_key: required(ENV_INFO_KEY)?,
+            blocked_customers,
+            version_policy,
+            features,
+        })
+    }
+
+    /// Feature schema of the application, as used by `#[derive(LicenseFeatures)]`
+    #[must_use]
+    pub fn schema(&self) -> FeatureSchema {
+        FeatureSchema {
+            application: self.application.clone(),
+            features: self.features.clone(),
+        }
+    }
+
+    /// Creates the [`RustLock`] for this application
+    /// # Errors
+    /// Will return `Err` if the we cant generate a fingerprint for this pc
+    pub fn lock(&self, version: String) -> Result<RustLock, RustLockErrors> {
+        RustLock::new(self.license_key.clone(), self.blocked_customers.clone(), version, self.machine_key.clone(), self.info_key.clone())
+    }
+}
+
+/// Values may be wrapped in double quotes, with `\"` inside
+fn unquote(value: &str) -> String {
+    value.strip_prefix('"').and_then(|v| v.strip_suffix('"')).map_or_else(|| value.to_string(), |v| v.replace("\\\"", "\""))
+}
@@ -14,4 +14,8 @@ pub enum RustLockErrors {
     InvalidDecrypt,
     #[error("Failed to Generate HW Info")]
     HWInfoFailed,
+    #[error("Invalid Config: {0}")]
+    InvalidConfig(String),
+    #[error("Revocation List Sealed Content")]
+    InvalidRevocationList,
 }
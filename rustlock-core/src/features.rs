@@ -0,0 +1,28 @@
+pub use rustlock_schema::{FeatureSchema, FeatureSlot, feature_ident};
+
+use crate::license::License;
+
+/// Typed view over the feature flags of a license, usually implemented with `#[derive(LicenseFeatures)]`
+pub trait LicenseFeatures: Sized {
+    fn from_license(license: &License) -> Self;
+}
+
+impl License {
+    /// Returns the feature flag stored in `slot` (1 to 5), unknown slots are never enabled
+    #[must_use]
+    pub const fn feature(&self, slot: u8) -> bool {
+        match slot {
+            1 => self.f1,
+            2 => self.f2,
+            3 => self.f3,
+            4 => self.f4,
+            5 => self.f5,
+            _ => false,
+        }
+    }
+
+    #[must_use]
+    pub fn features<T: LicenseFeatures>(&self) -> T {
+        T::from_license(self)
+    }
+}
@@ -1,23 +1,27 @@
 #![allow(clippy::redundant_else)]
 use ::sysinfo::{Disks, Networks, System};
 use ecies::decrypt;
-use license::License;
+use license::{License, RevocationList, VersionPolicy};
 use log::trace;
 use machineid_rs::{Encryption, HWIDComponent, IdBuilder};
 use version_compare::Version;
 
 use crate::error::RustLockErrors;
 
+pub mod config;
 pub mod error;
+pub mod features;
 pub mod license;
 pub mod sysinfo;
 
 pub struct RustLock {
     license_key: String,
-    blocked_customer: Vec<u16>,
+    blocked_customer: Vec<u64>,
     version: String,
     mid_key: String,
     info_key: String,
+    release_date: Option<(i32, u32)>,
+    revoked: Vec<String>,
 
     network_lock: String,
     storage_lock: String,
@@ -28,7 +32,7 @@ pub struct RustLock {
 impl RustLock {
     /// # Errors
     /// Will return `Err` if the we cant generate a fingerprint for this pc
-    pub fn new(license_key: String, blocked_customer: Vec<u16>, version: String, mid_key: String, info_key: String) -> Result<Self, RustLockErrors> {
+    pub fn new(license_key: String, blocked_customer: Vec<u64>, version: String, mid_key: String, info_key: String) -> Result<Self, RustLockErrors> {
         let (network_lock, storage_lock, cpu_lock, os_lock) = sysinfo::get_locks(&mid_key)?;
 
         Ok(Self {
@@ -37,6 +41,8 @@ impl RustLock {
             version,
             mid_key,
             info_key,
+            release_date: None,
+            revoked: Vec::new(),
 
             network_lock,
             storage_lock,
@@ -45,6 +51,22 @@ impl RustLock {
         })
     }
 
+    /// Sets the release date of this build, required to validate licenses using [`VersionPolicy::ReleaseDate`]
+    #[must_use]
+    pub const fn with_release_date(mut self, year: i32, month: u32) -> Self {
+        self.release_date = Some((year, month));
+        self
+    }
+
+    /// Sets the revoked licenses from the list exported by `rustlock-admin export revocations`
+    /// # Errors
+    /// Will return `Err` if the list wasn't sealed for this application's license key or doesn't match its sealed copy
+    pub fn with_revoked_licenses(mut self, list: RevocationList) -> Result<Self, RustLockErrors> {
+        list.open(&self.license_key)?;
+        self.revoked = list.revoked;
+        Ok(self)
+    }
+
     /// Gets the systems fingerprint and encrypts
     /// # Errors
     /// Will return `Err` if the we cant generate a fingerprint for this pc
@@ -121,49 +143,95 @@ impl RustLock {
             return Err(RustLockErrors::InvalidKey);
         }
 
-        let Some(max_version) = Version::from(&lic.version) else {
-            trace!("License Version Decode Failed");
+        if !lic.id.is_empty() && self.revoked.contains(&lic.id) {
+            trace!("License Revoked");
             return Err(RustLockErrors::InvalidKey);
-        };
+        }
 
-        if current_version <= max_version {
+        if self.version_allowed(&current_version, &lic) {
             if lic.c1 == os_lock && lic.c2 == cpu_lock && lic.c3 == storage_lock {
                 return Ok(lic);
             } else {
                 trace!("Hardware Locks Failed to match");
             }
-        } else {
-            trace!("License Version {current_version} <= {max_version}");
         }
 
         Err(RustLockErrors::InvalidKey)
     }
 
+    fn version_allowed(&self, current_version: &Version, lic: &License) -> bool {
+        match lic.version_policy {
+            VersionPolicy::MajorMinor => {
+                let Some(max_version) = Version::from(&lic.version) else {
+                    trace!("License Version Decode Failed");
+                    return false;
+                };
+
+                if *current_version <= max_version {
+                    return true;
+                }
+                trace!("License Version {current_version} <= {max_version}");
+            }
+            VersionPolicy::SemverRange => {
+                let (Some(min_version), Some(max_version)) = (Version::from(&lic.min_version), Version::from(&lic.version)) else {
+                    trace!("License Version Range Decode Failed");
+                    return false;
+                };
+
+                if min_version <= *current_version && *current_version <= max_version {
+                    return true;
+                }
+                trace!("License Version {min_version} <= {current_version} <= {max_version}");
+            }
+            VersionPolicy::ReleaseDate => {
+                let Some((year, month)) = self.release_date else {
+                    trace!("License Requires A Release Date");
+                    return false;
+                };
+
+                if (year, month) <= (lic.end_year, lic.end_month) {
+                    return true;
+                }
+                trace!("License Release {year}-{month:02} <= {}-{:02}", lic.end_year, lic.end_month);
+            }
+        }
+
+        false
+    }
+
     /// # Errors
     ///
     /// WARNING This should only be used to read the license details to show who its registered too
     pub fn read_license(&self, license: &str) -> Result<License, RustLockErrors> {
-        let Ok(sk) = hex::decode(&self.license_key) else {
-            trace!("License Public Key Failed");
-            return Err(RustLockErrors::InvalidPublicKey);
-        };
-
-        let Ok(payload) = hex::decode(license) else {
-            trace!("License Hex Decode Failed");
-            return Err(RustLockErrors::InvalidHexDecode);
-        };
-
-        let Ok(decrypted) = decrypt(&sk, &payload) else {
-            trace!("Decryption Failed");
-            return Err(RustLockErrors::InvalidDecrypt);
-        };
-
-        // MsgPack
-        let Ok(lic) = rmp_serde::from_read::<&[u8], License>(&*decrypted) else {
-            trace!("RMP Decode Failed");
-            return Err(RustLockErrors::InvalidKey);
-        };
-
-        Ok(lic)
+        read_license(&self.license_key, license)
     }
 }
+
+/// Decrypts a license with the license key, without checking it against this machine
+/// # Errors
+///
+/// WARNING This should only be used to read the license details, e.g. by the admin tool which has no hardware locks of its own
+pub fn read_license(license_key: &str, license: &str) -> Result<License, RustLockErrors> {
+    let Ok(sk) = hex::decode(license_key) else {
+        trace!("License Public Key Failed");
+        return Err(RustLockErrors::InvalidPublicKey);
+    };
+
+    let Ok(payload) = hex::decode(license) else {
+        trace!("License Hex Decode Failed");
+        return Err(RustLockErrors::InvalidHexDecode);
+    };
+
+    let Ok(decrypted) = decrypt(&sk, &payload) else {
+        trace!("Decryption Failed");
+        return Err(RustLockErrors::InvalidDecrypt);
+    };
+
+    // MsgPack
+    let Ok(lic) = rmp_serde::from_read::<&[u8], License>(&*decrypted) else {
+        trace!("RMP Decode Failed");
+        return Err(RustLockErrors::InvalidKey);
+    };
+
+    Ok(lic)
+}
@@ -1,7 +1,14 @@
-use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+use ecies::{decrypt, encrypt};
+use serde::ser::SerializeStruct;
+use serde::{Deserialize, Serialize, Serializer};
+
+use crate::error::RustLockErrors;
 
 #[allow(clippy::struct_excessive_bools)]
-#[derive(Serialize, Deserialize, Default, Debug, Eq, PartialEq, Clone)]
+#[derive(Deserialize, Default, Debug, Eq, PartialEq, Clone)]
 pub struct License {
     pub start_month: u32,
     pub start_year: i32,
@@ -11,7 +18,9 @@ pub struct License {
 
     pub version: String,
 
-    pub customer: u16,
+    // issued as u16 before customer ids were widened, msgpack stores integers in their
+    // smallest encoding so those licenses still decode into a u64
+    pub customer: u64,
 
     // feature flags
     pub f1: bool, // 1
@@ -29,4 +38,129 @@ pub struct License {
 
     pub id: String,
     pub name: String,
+
+    // added after the first release, licenses issued before default to `MajorMinor`
+    #[serde(default)]
+    pub version_policy: VersionPolicy,
+    #[serde(default)]
+    pub min_version: String,
+}
+
+/// Number of fields in the first release's license layout
+const FIRST_RELEASE_FIELDS: usize = 18;
+
+impl Serialize for License {
+    /// Licenses are positional MessagePack and applications built against the first release reject extra fields,
+    /// so a `MajorMinor` license is written in that layout and only the newer policies add their fields
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let extended = serializer.is_human_readable() || self.version_policy != VersionPolicy::MajorMinor || !self.min_version.is_empty();
+
+        let mut license = serializer.serialize_struct("License", if extended { FIRST_RELEASE_FIELDS + 2 } else { FIRST_RELEASE_FIELDS })?;
+        license.serialize_field("start_month", &self.start_month)?;
+        license.serialize_field("start_year", &self.start_year)?;
+        license.serialize_field("end_month", &self.end_month)?;
+        license.serialize_field("end_year", &self.end_year)?;
+        license.serialize_field("version", &self.version)?;
+        license.serialize_field("customer", &self.customer)?;
+        license.serialize_field("f1", &self.f1)?;
+        license.serialize_field("f2", &self.f2)?;
+        license.serialize_field("f3", &self.f3)?;
+        license.serialize_field("f4", &self.f4)?;
+        license.serialize_field("f5", &self.f5)?;
+        license.serialize_field("c1", &self.c1)?;
+        license.serialize_field("c2", &self.c2)?;
+        license.serialize_field("c3", &self.c3)?;
+        license.serialize_field("c4", &self.c4)?;
+        license.serialize_field("c5", &self.c5)?;
+        license.serialize_field("id", &self.id)?;
+        license.serialize_field("name", &self.name)?;
+        if extended {
+            license.serialize_field("version_policy", &self.version_policy)?;
+            license.serialize_field("min_version", &self.min_version)?;
+        }
+        license.end()
+    }
+}
+
+/// Revoked licenses of an application, written by `rustlock-admin export revocations`.
+/// Pass it to [`crate::RustLock::with_revoked_licenses`], which opens it with the application's license key
+#[derive(Serialize, Deserialize, Default, Debug, Eq, PartialEq, Clone)]
+pub struct RevocationList {
+    pub application: String,
+    pub generated_at: String,
+    /// License IDs (`License::id`)
+    pub revoked: Vec<String>,
+    /// Hex encoded, see [`Self::seal`]
+    #[serde(default)]
+    pub sealed: String,
+}
+
+impl RevocationList {
+    fn content(&self) -> Result<Vec<u8>, RustLockErrors> {
+        rmp_serde::to_vec(&(&self.application, &self.generated_at, &self.revoked)).map_err(|_| RustLockErrors::InvalidRevocationList)
+    }
+
+    /// Seal the list with the key licenses are issued with (`lic_private_key` in rustlock-admin).
+    ///
+    /// NOTE like a license, the content is sealed with ECIES so only the license key embedded in the application opens it.
+    /// This is not a signature: the sealing key can be derived from the license key in the application, so it catches a list
+    /// edited by hand or exported for another application, not one forged by someone who extracted that key
+    /// # Errors
+    /// Will return `Err` if the key isn't a hex encoded public key
+    pub fn seal(&mut self, issuing_key: &str) -> Result<(), RustLockErrors> {
+        let pk = hex::decode(issuing_key).map_err(|_| RustLockErrors::InvalidPublicKey)?;
+        let sealed = encrypt(&pk, &self.content()?).map_err(|_| RustLockErrors::InvalidPublicKey)?;
+        self.sealed = hex::encode_upper(sealed);
+        Ok(())
+    }
+
+    /// Open the sealed copy with the license key embedded in the application and compare it to the list
+    /// # Errors
+    /// Will return `Err` if the list isn't sealed, was sealed for another application or doesn't match its sealed copy
+    pub fn open(&self, license_key: &str) -> Result<(), RustLockErrors> {
+        let sk = hex::decode(license_key).map_err(|_| RustLockErrors::InvalidPublicKey)?;
+        let sealed = hex::decode(&self.sealed).map_err(|_| RustLockErrors::InvalidRevocationList)?;
+        let content = decrypt(&sk, &sealed).map_err(|_| RustLockErrors::InvalidRevocationList)?;
+
+        if content == self.content()? { Ok(()) } else { Err(RustLockErrors::InvalidRevocationList) }
+    }
+}
+
+/// Decides which application versions a license is valid for
+#[derive(Serialize, Deserialize, Default, Debug, Eq, PartialEq, Clone, Copy)]
+pub enum VersionPolicy {
+    /// Any version up to `version`, issued as `major.minor.9999`
+    #[default]
+    MajorMinor,
+    /// Any version from `min_version` up to `version`
+    SemverRange,
+    /// Any version released before the end of support (`end_year`/`end_month`)
+    ReleaseDate,
+}
+
+impl VersionPolicy {
+    pub const ALL: [Self; 3] = [Self::MajorMinor, Self::SemverRange, Self::ReleaseDate];
+
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::MajorMinor => "major_minor",
+            Self::SemverRange => "semver_range",
+            Self::ReleaseDate => "release_date",
+        }
+    }
+}
+
+impl fmt::Display for VersionPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for VersionPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL.into_iter().find(|p| p.as_str() == s).ok_or_else(|| format!("unknown version policy '{s}'"))
+    }
 }
@@ -47,14 +47,13 @@ pub struct SysInfo {
 impl SysInfo {
     #[must_use]
     pub(crate) fn to_encrypt_string(&self, info_key: &str) -> String {
-        if let Ok(msg) = rmp_serde::to_vec(&self) {
-            if let Ok(pk) = hex::decode(info_key) {
-                if let Ok(encrypted) = encrypt(&pk, &msg) {
-                    let encrypted_string = hex::encode_upper(encrypted);
-
-                    return encrypted_string;
-                }
-            }
+        if let Ok(msg) = rmp_serde::to_vec(&self)
+            && let Ok(pk) = hex::decode(info_key)
+            && let Ok(encrypted) = encrypt(&pk, &msg)
+        {
+            let encrypted_string = hex::encode_upper(encrypted);
+
+            return encrypted_string;
         }
 
         String::new()
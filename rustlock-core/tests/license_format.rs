@@ -0,0 +1,84 @@
+//! Licenses issued now have to decode in applications built against the first release
+
+use rustlock_core::license::{License, VersionPolicy};
+use serde::Deserialize;
+
+/// `License` as the first release declared it
+#[allow(clippy::struct_excessive_bools)]
+#[derive(Deserialize, Debug)]
+struct FirstReleaseLicense {
+    start_month: u32,
+    start_year: i32,
+    end_month: u32,
+    end_year: i32,
+    version: String,
+    customer: u16,
+    f1: bool,
+    f2: bool,
+    f3: bool,
+    f4: bool,
+    f5: bool,
+    c1: String,
+    c2: String,
+    c3: String,
+    c4: String,
+    c5: String,
+    id: String,
+    name: String,
+}
+
+fn license(version_policy: VersionPolicy, min_version: &str) -> License {
+    License {
+        start_month: 3,
+        start_year: 2025,
+        end_month: 3,
+        end_year: 2026,
+        version: "2.1.9999".to_string(),
+        customer: 42,
+        f1: true,
+        f3: true,
+        c1: "os".to_string(),
+        c2: "cpu".to_string(),
+        c3: "storage".to_string(),
+        id: "6f1c2d3e-0000-4000-8000-000000000001".to_string(),
+        name: "Acme Pty Ltd".to_string(),
+        version_policy,
+        min_version: min_version.to_string(),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn major_minor_licenses_decode_with_the_first_release_layout() {
+    let issued = license(VersionPolicy::MajorMinor, "");
+    let bytes = rmp_serde::to_vec(&issued).expect("encode");
+
+    let old: FirstReleaseLicense = rmp_serde::from_slice(&bytes).expect("the first release decodes a major_minor license");
+    assert_eq!((old.start_month, old.start_year, old.end_month, old.end_year), (3, 2025, 3, 2026));
+    assert_eq!(old.version, "2.1.9999");
+    assert_eq!(old.customer, 42);
+    assert_eq!((old.f1, old.f2, old.f3, old.f4, old.f5), (true, false, true, false, false));
+    assert_eq!((old.c1.as_str(), old.c2.as_str(), old.c3.as_str(), old.c4.as_str(), old.c5.as_str()), ("os", "cpu", "storage", "", ""));
+    assert_eq!(old.id, issued.id);
+    assert_eq!(old.name, "Acme Pty Ltd");
+
+    assert_eq!(rmp_serde::from_slice::<License>(&bytes).expect("decode"), issued);
+}
+
+#[test]
+fn newer_policies_round_trip_and_are_refused_by_the_first_release() {
+    for issued in [license(VersionPolicy::SemverRange, "2.0.0"), license(VersionPolicy::ReleaseDate, "")] {
+        let bytes = rmp_serde::to_vec(&issued).expect("encode");
+
+        assert_eq!(rmp_serde::from_slice::<License>(&bytes).expect("decode"), issued);
+        // An application that can't enforce the policy must not accept the license
+        assert!(rmp_serde::from_slice::<FirstReleaseLicense>(&bytes).is_err());
+    }
+}
+
+#[test]
+fn json_keeps_every_field() {
+    let json = serde_json::to_value(license(VersionPolicy::MajorMinor, "")).expect("json");
+    assert_eq!(json["version_policy"], "MajorMinor");
+    assert_eq!(json["min_version"], "");
+}
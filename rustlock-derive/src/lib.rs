@@ -0,0 +1,208 @@
+use std::{collections::HashMap, path::PathBuf};
+
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::quote;
+use rustlock_schema::{FeatureSchema, feature_ident};
+use syn::{Attribute, Data, DeriveInput, Fields, Ident, LitStr, Type, parse_macro_input, spanned::Spanned};
+
+/// Derives typed feature accessors from an application schema exported by `rustlock-admin export schema`.
+///
+/// On a struct every field must be a `bool` named after a feature in the schema, the struct then implements
+/// `rustlock_core::features::LicenseFeatures` and gets one accessor per field. On an enum every unit variant
+/// names a feature and gets `slot()`, `name()` and `is_enabled(&license)`.
+///
+/// ```ignore
+/// #[derive(LicenseFeatures)]
+/// #[rustlock(schema = "rustlock-schema.json")]
+/// struct Features {
+///     export_pdf: bool,
+///     #[rustlock(feature = "Cloud Sync")]
+///     sync: bool,
+/// }
+///
+/// let features: Features = license.features();
+/// if features.export_pdf() { /* ... */ }
+/// ```
+#[proc_macro_derive(LicenseFeatures, attributes(rustlock))]
+pub fn derive_license_features(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    expand(&input).unwrap_or_else(syn::Error::into_compile_error).into()
+}
+
+fn expand(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let Some(schema_path) = schema_attr(&input.attrs)? else {
+        return Err(syn::Error::new(Span::call_site(), "missing #[rustlock(schema = \"...\")] attribute pointing at the exported application schema"));
+    };
+    let (schema, full_path) = load_schema(&schema_path)?;
+
+    let full_path = full_path.to_string_lossy().to_string();
+    // Makes cargo rebuild when the schema file changes
+    let track = quote! { const _: &[u8] = include_bytes!(#full_path); };
+
+    match &input.data {
+        Data::Struct(data) => expand_struct(input, &data.fields, &schema, &track),
+        Data::Enum(data) => {
+            let mut variants = Vec::new();
+            let mut slots = HashMap::new();
+            for variant in &data.variants {
+                if !matches!(variant.fields, Fields::Unit) {
+                    return Err(syn::Error::new(variant.span(), "LicenseFeatures enum variants must be unit variants"));
+                }
+                let name = feature_attr(&variant.attrs)?.map_or_else(|| feature_ident(&variant.ident.to_string()), |lit| lit.value());
+                let slot = resolve_slot(&schema, &name, variant.ident.span())?;
+                claim_slot(&mut slots, slot, &variant.ident)?;
+                variants.push((variant.ident.clone(), schema.name_of(slot).map_or(name, str::to_string), slot));
+            }
+            Ok(expand_enum(input, &variants, &track))
+        }
+        Data::Union(_) => Err(syn::Error::new(input.span(), "LicenseFeatures cannot be derived for unions")),
+    }
+}
+
+fn expand_struct(input: &DeriveInput, fields: &Fields, schema: &FeatureSchema, track: &TokenStream2) -> syn::Result<TokenStream2> {
+    let Fields::Named(fields) = fields else {
+        return Err(syn::Error::new(input.span(), "LicenseFeatures structs must have named fields"));
+    };
+
+    let mut inits = Vec::new();
+    let mut accessors = Vec::new();
+    let mut slots = HashMap::new();
+
+    for field in &fields.named {
+        let Some(ident) = &field.ident else { continue };
+
+        if !is_bool(&field.ty) {
+            return Err(syn::Error::new(field.ty.span(), "LicenseFeatures fields must be `bool`"));
+        }
+
+        let name = feature_attr(&field.attrs)?.map_or_else(|| ident.to_string(), |lit| lit.value());
+        let slot = resolve_slot(schema, &name, ident.span())?;
+        claim_slot(&mut slots, slot, ident)?;
+
+        inits.push(quote! { #ident: license.feature(#slot) });
+
+        let doc = format!("Whether `{name}` is enabled on the license");
+        accessors.push(quote! {
+            #[doc = #doc]
+            #[must_use]
+            pub const fn #ident(&self) -> bool {
+                self.#ident
+            }
+        });
+    }
+
+    let ty = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        #track
+
+        impl #impl_generics ::rustlock_core::features::LicenseFeatures for #ty #ty_generics #where_clause {
+            fn from_license(license: &::rustlock_core::license::License) -> Self {
+                Self { #(#inits),* }
+            }
+        }
+
+        impl #impl_generics #ty #ty_generics #where_clause {
+            #(#accessors)*
+        }
+    })
+}
+
+fn expand_enum(input: &DeriveInput, variants: &[(Ident, String, u8)], track: &TokenStream2) -> TokenStream2 {
+    let ty = &input.ident;
+    let idents: Vec<&Ident> = variants.iter().map(|(ident, _, _)| ident).collect();
+    let names: Vec<&String> = variants.iter().map(|(_, name, _)| name).collect();
+    let slots: Vec<u8> = variants.iter().map(|(_, _, slot)| *slot).collect();
+
+    quote! {
+        #track
+
+        impl #ty {
+            /// Every feature declared on this enum
+            pub const ALL: &'static [Self] = &[#(Self::#idents),*];
+
+            /// License slot (`f1`..`f5`) this feature is stored in
+            #[must_use]
+            pub const fn slot(&self) -> u8 {
+                match self {
+                    #(Self::#idents => #slots),*
+                }
+            }
+
+            /// Feature name as declared in the application schema
+            #[must_use]
+            pub const fn name(&self) -> &'static str {
+                match self {
+                    #(Self::#idents => #names),*
+                }
+            }
+
+            #[must_use]
+            pub const fn is_enabled(&self, license: &::rustlock_core::license::License) -> bool {
+                license.feature(self.slot())
+            }
+        }
+    }
+}
+
+fn resolve_slot(schema: &FeatureSchema, name: &str, span: Span) -> syn::Result<u8> {
+    schema.slot_of(name).ok_or_else(|| {
+        let known = schema.features.iter().map(|f| feature_ident(&f.name)).collect::<Vec<_>>().join(", ");
+        syn::Error::new(span, format!("unknown feature `{name}` for application '{}', expected one of: {known}", schema.application))
+    })
+}
+
+/// Two fields reading the same slot would silently alias one feature, so the second one is rejected
+fn claim_slot(slots: &mut HashMap<u8, Ident>, slot: u8, ident: &Ident) -> syn::Result<()> {
+    if let Some(first) = slots.get(&slot) {
+        return Err(syn::Error::new(ident.span(), format!("`{ident}` maps to the same feature slot (f{slot}) as `{first}`")));
+    }
+    slots.insert(slot, ident.clone());
+    Ok(())
+}
+
+fn is_bool(ty: &Type) -> bool {
+    matches!(ty, Type::Path(path) if path.qself.is_none() && path.path.is_ident("bool"))
+}
+
+fn schema_attr(attrs: &[Attribute]) -> syn::Result<Option<LitStr>> {
+    rustlock_attr(attrs, "schema")
+}
+
+fn feature_attr(attrs: &[Attribute]) -> syn::Result<Option<LitStr>> {
+    rustlock_attr(attrs, "feature")
+}
+
+/// Reads `#[rustlock(<key> = "...")]`, rejecting keys the macro doesn't know about
+fn rustlock_attr(attrs: &[Attribute], key: &str) -> syn::Result<Option<LitStr>> {
+    let mut value = None;
+
+    for attr in attrs.iter().filter(|a| a.path().is_ident("rustlock")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident(key) {
+                value = Some(meta.value()?.parse::<LitStr>()?);
+                Ok(())
+            } else if meta.path.is_ident("schema") || meta.path.is_ident("feature") {
+                Err(meta.error("attribute is not valid in this position"))
+            } else {
+                Err(meta.error("unknown rustlock attribute, expected `schema` or `feature`"))
+            }
+        })?;
+    }
+
+    Ok(value)
+}
+
+/// Loads the schema relative to the crate being compiled
+fn load_schema(path: &LitStr) -> syn::Result<(FeatureSchema, PathBuf)> {
+    let manifest_dir = std::env::var("CARGO_MANIFEST_DIR").unwrap_or_default();
+    let full_path = PathBuf::from(manifest_dir).join(path.value());
+
+    let contents = std::fs::read_to_string(&full_path).map_err(|e| syn::Error::new(path.span(), format!("failed to read schema {}: {e}", full_path.display())))?;
+    let schema = serde_json::from_str::<FeatureSchema>(&contents).map_err(|e| syn::Error::new(path.span(), format!("invalid schema {}: {e}", full_path.display())))?;
+
+    Ok((schema, full_path))
+}
@@ -0,0 +1,21 @@
+//! Mistakes in a `#[derive(LicenseFeatures)]` are reported at compile time, see the `.stderr` file next to each case
+
+use std::path::PathBuf;
+
+/// trybuild compiles the cases in `<target>/tests/trybuild/rustlock-derive`, which is their `CARGO_MANIFEST_DIR`,
+/// so the schema they name is copied to the same relative path there
+fn stage_schema() {
+    let exe = std::env::current_exe().expect("test binary");
+    // <target>/<profile>/deps/<test binary>
+    let target = exe.ancestors().nth(3).expect("target directory");
+    let project: PathBuf = target.join("tests").join("trybuild").join(env!("CARGO_PKG_NAME")).join("tests").join("ui");
+
+    std::fs::create_dir_all(&project).expect("trybuild project");
+    std::fs::copy(PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/ui/schema.json"), project.join("schema.json")).expect("copy schema");
+}
+
+#[test]
+fn ui() {
+    stage_schema();
+    trybuild::TestCases::new().compile_fail("tests/ui/*.rs");
+}
@@ -0,0 +1,9 @@
+use rustlock_derive::LicenseFeatures;
+
+#[derive(LicenseFeatures)]
+#[rustlock(schema = "tests/ui/no-such-schema.json")]
+struct Features {
+    export_pdf: bool,
+}
+
+fn main() {}
@@ -0,0 +1,11 @@
+use rustlock_derive::LicenseFeatures;
+
+#[derive(LicenseFeatures)]
+#[rustlock(schema = "tests/ui/schema.json")]
+struct Features {
+    export_pdf: bool,
+    #[rustlock(feature = "Export PDF")]
+    pdf: bool,
+}
+
+fn main() {}
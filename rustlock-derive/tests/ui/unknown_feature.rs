@@ -0,0 +1,10 @@
+use rustlock_derive::LicenseFeatures;
+
+#[derive(LicenseFeatures)]
+#[rustlock(schema = "tests/ui/schema.json")]
+struct Features {
+    export_pdf: bool,
+    offline_mode: bool,
+}
+
+fn main() {}
@@ -0,0 +1,56 @@
+use serde::{Deserialize, Serialize};
+
+/// Feature schema exported by `rustlock-admin export schema`.
+///
+/// Lists the named features of an application and the license slot (`f1`..`f5`) each one is stored in,
+/// so application code can refer to features by name instead of by slot.
+#[derive(Serialize, Deserialize, Default, Debug, Eq, PartialEq, Clone)]
+pub struct FeatureSchema {
+    pub application: String,
+    pub features: Vec<FeatureSlot>,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Eq, PartialEq, Clone)]
+pub struct FeatureSlot {
+    pub slot: u8,
+    pub name: String,
+}
+
+impl FeatureSchema {
+    /// Find the slot for a feature, matching either the display name or its identifier form
+    #[must_use]
+    pub fn slot_of(&self, name: &str) -> Option<u8> {
+        let ident = feature_ident(name);
+        self.features.iter().find(|f| f.name == name || feature_ident(&f.name) == ident).map(|f| f.slot)
+    }
+
+    /// Display name of the feature stored in `slot`
+    #[must_use]
+    pub fn name_of(&self, slot: u8) -> Option<&str> {
+        self.features.iter().find(|f| f.slot == slot).map(|f| f.name.as_str())
+    }
+}
+
+/// Converts a feature display name such as "Export PDF" into its identifier form `export_pdf`
+#[must_use]
+pub fn feature_ident(name: &str) -> String {
+    let mut ident = String::new();
+    let mut prev_lower = false;
+
+    for ch in name.trim().chars() {
+        if ch.is_ascii_alphanumeric() {
+            if ch.is_ascii_uppercase() && prev_lower {
+                ident.push('_');
+            }
+            prev_lower = ch.is_ascii_lowercase() || ch.is_ascii_digit();
+            ident.push(ch.to_ascii_lowercase());
+        } else {
+            if !ident.is_empty() && !ident.ends_with('_') {
+                ident.push('_');
+            }
+            prev_lower = false;
+        }
+    }
+
+    ident.trim_end_matches('_').to_string()
+}